@@ -0,0 +1,784 @@
+//! Client API for the Day Planner's IPC server.
+//!
+//! Other apps connect to `SERVER_NAME` and ask:
+//!   EventsInRange — events from one date to another, in date and time
+//!                   order (memory message: `RangeQuery` in, `EventList` back)
+//!   PendingTasks  — how many tasks are open, and how many of those are
+//!                   high priority (blocking scalar)
+//!   NextEvent     — the first event after a given moment (memory
+//!                   message: `NextQuery` in, `NextEvent` back)
+//!
+//! and change it, each a memory message answered with an `EditReply`:
+//!   CreateEvent   — `NewEvent` in; the reply carries the new uid
+//!   UpdateEvent   — `EventUpdate` in; fields left `None` are kept
+//!   CreateTask    — `NewTask` in; the reply carries the new uid
+//!   UpdateTask    — `TaskUpdate` in
+//!   CompleteTask  — `ItemRef` in
+//!
+//! The planner has no clock it trusts, so callers say what "now" is.
+//! Events in secret calendars are never served: they'd leave their basis.
+//! Nor can they be changed; to other apps they don't exist.
+//!
+//! Edits are checked as the planner's own screens check them, and refused
+//! whole with an `ApiError` if any part is wrong.
+//!
+//! Apps that want to hear about changes run a server of their own and
+//! register it:
+//!   Subscribe     — `Subscription` in, `SubscribeReply` back (memory message)
+//!   Unsubscribe   — the SID's four words (blocking scalar)
+//!
+//! After each batch of changes, from the planner's UI or any app, every
+//! subscriber gets a `ChangeNotice` as a memory message with the opcode it
+//! chose; `ChangeNotice::from_message` reads it. A subscriber that can no
+//! longer be reached is dropped.
+//!
+//! An `EventList` has to fit in the buffer the query was lent in. If it
+//! doesn't, the server drops events from the end and sets `truncated`;
+//! ask again from the last date received to get the rest.
+//!
+//! `PlannerClient::connect` talks to the running planner. Host tests of
+//! client apps use `PlannerClient::stand_in` instead, which answers from a
+//! `StandIn` in-process with the same logic the planner uses.
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+use planner_core::planner;
+use xous::{Message, MessageEnvelope, CID};
+use xous_ipc::Buffer;
+
+pub const SERVER_NAME: &str = "_Day Planner_";
+
+/// Public opcodes. They start at 16 so they never collide with the
+/// planner's own internal messages.
+#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum Opcode {
+    EventsInRange = 16,
+    PendingTasks = 17,
+    NextEvent = 18,
+    CreateEvent = 19,
+    UpdateEvent = 20,
+    CreateTask = 21,
+    UpdateTask = 22,
+    CompleteTask = 23,
+    Subscribe = 24,
+    Unsubscribe = 25,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, PartialEq)]
+pub struct EventInfo {
+    /// The event's stable id; the same across calls and devices.
+    pub uid: [u8; 16],
+    pub date: Date,
+    /// `None` for all-day events.
+    pub time: Option<Time>,
+    pub title: String,
+    pub priority: Priority,
+    /// Registry ID of the calendar it's in.
+    pub calendar: u8,
+}
+
+impl EventInfo {
+    /// All-day events sort before timed ones on the same day.
+    fn when(&self) -> (Date, Option<Time>) {
+        (self.date, self.time)
+    }
+}
+
+/// Events from `from` to `to`, both inclusive.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RangeQuery {
+    pub from: Date,
+    pub to: Date,
+    /// Most events wanted; 0 for as many as fit.
+    pub max: u32,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EventList {
+    pub events: Vec<EventInfo>,
+    /// More events matched than were returned.
+    pub truncated: bool,
+}
+
+/// The first event after `time` on `date`, or on any later day. With no
+/// time, everything on `date` counts, all-day events included.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct NextQuery {
+    pub date: Date,
+    pub time: Option<Time>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NextEvent {
+    pub event: Option<EventInfo>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, PartialEq)]
+pub struct NewEvent {
+    pub date: Date,
+    /// `None` for an all-day event.
+    pub time: Option<Time>,
+    pub title: String,
+    pub priority: Priority,
+    /// Registry ID of the calendar to put it in; `None` for the one the
+    /// planner's own form would start on.
+    pub calendar: Option<u8>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EventUpdate {
+    pub uid: [u8; 16],
+    pub date: Option<Date>,
+    /// `Some(None)` makes the event all-day.
+    pub time: Option<Option<Time>>,
+    pub title: Option<String>,
+    pub priority: Option<Priority>,
+    pub calendar: Option<u8>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, PartialEq)]
+pub struct NewTask {
+    pub title: String,
+    pub priority: Priority,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TaskUpdate {
+    pub uid: [u8; 16],
+    pub title: Option<String>,
+    pub priority: Option<Priority>,
+    pub done: Option<bool>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ItemRef {
+    pub uid: [u8; 16],
+}
+
+/// Why an edit was refused. Nothing was changed.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiError {
+    /// Blank, too long (`EVENT_TITLE_MAX` or `TASK_TITLE_MAX`
+    /// characters), or containing control characters.
+    BadTitle = 1,
+    BadDate = 2,
+    BadTime = 3,
+    /// Not in the registry, or a secret calendar.
+    NoSuchCalendar = 4,
+    /// No event or task with that uid, or one in a secret calendar.
+    NotFound = 5,
+    /// The planner couldn't load its stored data, so can't save either.
+    Unavailable = 6,
+    /// The request couldn't be read.
+    Malformed = 7,
+    /// The message didn't get through. Only clients report this.
+    Ipc = 8,
+    /// `MAX_SUBSCRIBERS` are already registered.
+    TooManySubscribers = 9,
+}
+
+/// The answer to every edit: the item's uid, or why it was refused.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct EditReply {
+    pub uid: [u8; 16],
+    pub error: Option<ApiError>,
+}
+
+impl EditReply {
+    fn new(result: Result<[u8; 16], ApiError>) -> Self {
+        match result {
+            Ok(uid) => Self { uid, error: None },
+            Err(e) => Self { uid: [0; 16], error: Some(e) },
+        }
+    }
+
+    fn result(self) -> Result<[u8; 16], ApiError> {
+        match self.error {
+            None => Ok(self.uid),
+            Some(e) => Err(e),
+        }
+    }
+}
+
+/// Most subscribers the planner keeps at once.
+pub const MAX_SUBSCRIBERS: usize = 8;
+/// A notice lists at most this many changes; past that it's a reset.
+pub const MAX_NOTICE_CHANGES: usize = 64;
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Subscription {
+    /// The subscriber's server, from `SID::to_array`.
+    pub sid: [u32; 4],
+    /// Opcode the planner sends notices with.
+    pub opcode: u32,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SubscribeReply {
+    pub error: Option<ApiError>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Event,
+    Task,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Changed,
+    /// Deleted, or for an event, moved into a secret calendar.
+    Removed,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    pub item: Item,
+    pub kind: ChangeKind,
+    pub uid: [u8; 16],
+}
+
+/// What changed since the last notice. Each item appears once, with
+/// where it ended up: an item added and then changed is just added.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChangeNotice {
+    pub changes: Vec<Change>,
+    /// Too much changed to list (a restore, an import, or more than
+    /// `MAX_NOTICE_CHANGES`); `changes` is empty and everything held
+    /// should be fetched again.
+    pub reset: bool,
+}
+
+impl ChangeNotice {
+    /// A notice listing `changes`, or a reset if `reset` is set or there
+    /// are too many to list.
+    pub fn new(changes: Vec<Change>, reset: bool) -> Self {
+        if reset || changes.len() > MAX_NOTICE_CHANGES {
+            return Self { changes: Vec::new(), reset: true };
+        }
+        Self { changes, reset: false }
+    }
+
+    /// Read a notice sent to a subscriber.
+    pub fn from_message(msg: &MessageEnvelope) -> Option<Self> {
+        let mem = msg.body.memory_message()?;
+        let buffer = unsafe { Buffer::from_memory_message(mem) };
+        buffer.to_original::<ChangeNotice, _>().ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TaskCounts {
+    pub open: u32,
+    /// Open tasks with high priority.
+    pub high: u32,
+}
+
+/// What the server answers from: the planner's state, or a `StandIn`.
+pub trait PlannerData {
+    /// Every event that may be served, in any order.
+    fn events(&self) -> Vec<EventInfo>;
+    fn task_counts(&self) -> TaskCounts;
+}
+
+/// What the server makes edits to. Each returns the item's uid.
+pub trait PlannerEdits {
+    fn create_event(&mut self, event: NewEvent) -> Result<[u8; 16], ApiError>;
+    fn update_event(&mut self, update: EventUpdate) -> Result<[u8; 16], ApiError>;
+    fn create_task(&mut self, task: NewTask) -> Result<[u8; 16], ApiError>;
+    fn update_task(&mut self, update: TaskUpdate) -> Result<[u8; 16], ApiError>;
+    fn complete_task(&mut self, uid: [u8; 16]) -> Result<[u8; 16], ApiError> {
+        self.update_task(TaskUpdate { uid, done: Some(true), ..TaskUpdate::default() })
+    }
+}
+
+/// Answer a `RangeQuery` from `events`.
+pub fn events_in_range(events: &[EventInfo], query: &RangeQuery) -> EventList {
+    let mut found: Vec<EventInfo> =
+        events.iter().filter(|e| e.date >= query.from && e.date <= query.to).cloned().collect();
+    found.sort_by_key(|e| e.when());
+    let mut truncated = false;
+    if query.max > 0 && found.len() > query.max as usize {
+        found.truncate(query.max as usize);
+        truncated = true;
+    }
+    EventList { events: found, truncated }
+}
+
+/// Answer a `NextQuery` from `events`.
+pub fn next_event(events: &[EventInfo], query: &NextQuery) -> NextEvent {
+    let upcoming = |e: &&EventInfo| match query.time {
+        None => e.date >= query.date,
+        Some(now) => e.date > query.date || (e.date == query.date && e.time.is_some_and(|t| t > now)),
+    };
+    NextEvent { event: events.iter().filter(upcoming).min_by_key(|e| e.when()).cloned() }
+}
+
+/// A task as a `StandIn` holds it. The planner doesn't serve tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskInfo {
+    pub uid: [u8; 16],
+    pub title: String,
+    pub priority: Priority,
+    pub done: bool,
+}
+
+/// Canned planner contents for host tests.
+///
+/// Edits made through a stand-in client land here. Only the checks that
+/// need no planner state are made (titles, times, unknown uids); the
+/// planner also checks dates and calendars.
+#[derive(Debug, Clone, Default)]
+pub struct StandIn {
+    pub events: Vec<EventInfo>,
+    pub tasks: Vec<TaskInfo>,
+}
+
+impl StandIn {
+    /// Uids count up; nothing is ever removed, so they stay unique.
+    fn next_uid(&self) -> [u8; 16] {
+        ((self.events.len() + self.tasks.len() + 1) as u128).to_be_bytes()
+    }
+}
+
+/// Longest titles the planner takes, as its entry screens do.
+pub use planner_core::planner::{EVENT_TITLE_MAX, TASK_TITLE_MAX};
+
+/// The planner's title check (`planner::clean_title`).
+fn check_title(title: &str, max: usize) -> Result<String, ApiError> {
+    planner::clean_title(title, max).ok_or(ApiError::BadTitle)
+}
+
+fn check_time(time: Option<Time>) -> Result<(), ApiError> {
+    match time {
+        Some(t) if !planner::Time { hour: t.hour, minute: t.minute }.is_valid() => Err(ApiError::BadTime),
+        _ => Ok(()),
+    }
+}
+
+impl PlannerData for StandIn {
+    fn events(&self) -> Vec<EventInfo> {
+        self.events.clone()
+    }
+
+    fn task_counts(&self) -> TaskCounts {
+        let open = self.tasks.iter().filter(|t| !t.done);
+        TaskCounts {
+            open: open.clone().count() as u32,
+            high: open.filter(|t| t.priority == Priority::High).count() as u32,
+        }
+    }
+}
+
+impl PlannerEdits for StandIn {
+    fn create_event(&mut self, event: NewEvent) -> Result<[u8; 16], ApiError> {
+        let title = check_title(&event.title, EVENT_TITLE_MAX)?;
+        check_time(event.time)?;
+        let uid = self.next_uid();
+        self.events.push(EventInfo {
+            uid,
+            date: event.date,
+            time: event.time,
+            title,
+            priority: event.priority,
+            calendar: event.calendar.unwrap_or(0),
+        });
+        Ok(uid)
+    }
+
+    fn update_event(&mut self, update: EventUpdate) -> Result<[u8; 16], ApiError> {
+        let title = update.title.as_deref().map(|t| check_title(t, EVENT_TITLE_MAX)).transpose()?;
+        check_time(update.time.flatten())?;
+        let ev = self.events.iter_mut().find(|e| e.uid == update.uid).ok_or(ApiError::NotFound)?;
+        if let Some(title) = title {
+            ev.title = title;
+        }
+        ev.date = update.date.unwrap_or(ev.date);
+        ev.time = update.time.unwrap_or(ev.time);
+        ev.priority = update.priority.unwrap_or(ev.priority);
+        ev.calendar = update.calendar.unwrap_or(ev.calendar);
+        Ok(update.uid)
+    }
+
+    fn create_task(&mut self, task: NewTask) -> Result<[u8; 16], ApiError> {
+        let title = check_title(&task.title, TASK_TITLE_MAX)?;
+        let uid = self.next_uid();
+        self.tasks.push(TaskInfo { uid, title, priority: task.priority, done: false });
+        Ok(uid)
+    }
+
+    fn update_task(&mut self, update: TaskUpdate) -> Result<[u8; 16], ApiError> {
+        let title = update.title.as_deref().map(|t| check_title(t, TASK_TITLE_MAX)).transpose()?;
+        let task = self.tasks.iter_mut().find(|t| t.uid == update.uid).ok_or(ApiError::NotFound)?;
+        if let Some(title) = title {
+            task.title = title;
+        }
+        task.priority = update.priority.unwrap_or(task.priority);
+        task.done = update.done.unwrap_or(task.done);
+        Ok(update.uid)
+    }
+}
+
+/// Handle `msg` if it's one of the public opcodes, answering from `data`.
+/// Returns `false`, leaving `msg` alone, for anything else.
+pub fn serve(msg: &mut MessageEnvelope, data: &impl PlannerData) -> bool {
+    let Some(op) = Opcode::from_usize(msg.body.id()) else { return false };
+    match op {
+        Opcode::EventsInRange => {
+            let Some(mem) = msg.body.memory_message_mut() else { return true };
+            let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+            let Ok(query) = buffer.to_original::<RangeQuery, _>() else { return true };
+            let mut list = events_in_range(&data.events(), &query);
+            // Shrink until the answer fits in the caller's buffer.
+            while buffer.replace(list.clone()).is_err() && !list.events.is_empty() {
+                let keep = list.events.len() / 2;
+                list.events.truncate(keep);
+                list.truncated = true;
+            }
+        }
+        Opcode::PendingTasks => {
+            if msg.body.is_blocking() {
+                let counts = data.task_counts();
+                xous::return_scalar2(msg.sender, counts.open as usize, counts.high as usize).ok();
+            }
+        }
+        Opcode::NextEvent => {
+            let Some(mem) = msg.body.memory_message_mut() else { return true };
+            let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+            let Ok(query) = buffer.to_original::<NextQuery, _>() else { return true };
+            buffer.replace(next_event(&data.events(), &query)).ok();
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// An edit as `serve_edits` reads it from a message, one per opcode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditRequest {
+    CreateEvent(NewEvent),
+    UpdateEvent(EventUpdate),
+    CreateTask(NewTask),
+    UpdateTask(TaskUpdate),
+    CompleteTask(ItemRef),
+}
+
+/// Make `request` to `target`, and the reply `serve_edits` sends back.
+pub fn edit(target: &mut impl PlannerEdits, request: EditRequest) -> EditReply {
+    EditReply::new(match request {
+        EditRequest::CreateEvent(event) => target.create_event(event),
+        EditRequest::UpdateEvent(update) => target.update_event(update),
+        EditRequest::CreateTask(task) => target.create_task(task),
+        EditRequest::UpdateTask(update) => target.update_task(update),
+        EditRequest::CompleteTask(item) => target.complete_task(item.uid),
+    })
+}
+
+/// Handle `msg` if it's one of the edit opcodes, making the edit to
+/// `target`. Returns `false`, leaving `msg` alone, for anything else.
+pub fn serve_edits(msg: &mut MessageEnvelope, target: &mut impl PlannerEdits) -> bool {
+    let Some(op) = Opcode::from_usize(msg.body.id()) else { return false };
+    let edit_op = matches!(
+        op,
+        Opcode::CreateEvent | Opcode::UpdateEvent | Opcode::CreateTask | Opcode::UpdateTask | Opcode::CompleteTask
+    );
+    if !edit_op {
+        return false;
+    }
+    let Some(mem) = msg.body.memory_message_mut() else { return true };
+    let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+    let request = match op {
+        Opcode::CreateEvent => buffer.to_original::<NewEvent, _>().map(EditRequest::CreateEvent),
+        Opcode::UpdateEvent => buffer.to_original::<EventUpdate, _>().map(EditRequest::UpdateEvent),
+        Opcode::CreateTask => buffer.to_original::<NewTask, _>().map(EditRequest::CreateTask),
+        Opcode::UpdateTask => buffer.to_original::<TaskUpdate, _>().map(EditRequest::UpdateTask),
+        Opcode::CompleteTask => buffer.to_original::<ItemRef, _>().map(EditRequest::CompleteTask),
+        _ => return false,
+    };
+    let reply = match request {
+        Ok(request) => edit(target, request),
+        Err(_) => EditReply::new(Err(ApiError::Malformed)),
+    };
+    buffer.replace(reply).ok();
+    true
+}
+
+struct Subscriber {
+    sid: [u32; 4],
+    cid: CID,
+    opcode: u32,
+}
+
+/// How `Subscribers` reaches its subscribers: Xous IPC in the planner,
+/// something in-process in tests.
+pub trait Transport {
+    /// Open a connection to the server `sid`.
+    fn connect(&mut self, sid: [u32; 4]) -> Option<CID>;
+    /// Send `notice` with `opcode`. `false` if it didn't get through.
+    fn send(&mut self, cid: CID, opcode: u32, notice: &ChangeNotice) -> bool;
+    fn disconnect(&mut self, cid: CID);
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XousTransport;
+
+impl Transport for XousTransport {
+    fn connect(&mut self, sid: [u32; 4]) -> Option<CID> {
+        xous::connect(xous::SID::from_array(sid)).ok()
+    }
+
+    fn send(&mut self, cid: CID, opcode: u32, notice: &ChangeNotice) -> bool {
+        match Buffer::into_buf(notice.clone()) {
+            Ok(buf) => buf.send(cid, opcode).is_ok(),
+            // Nothing to send; not the subscriber's fault.
+            Err(_) => true,
+        }
+    }
+
+    fn disconnect(&mut self, cid: CID) {
+        unsafe { xous::disconnect(cid).ok() };
+    }
+}
+
+/// The planner's list of subscribers.
+#[derive(Default)]
+pub struct Subscribers<T: Transport = XousTransport> {
+    list: Vec<Subscriber>,
+    transport: T,
+}
+
+impl<T: Transport> Subscribers<T> {
+    pub fn new(transport: T) -> Self {
+        Self { list: Vec::new(), transport }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Handle `msg` if it's `Subscribe` or `Unsubscribe`. Returns `false`,
+    /// leaving `msg` alone, for anything else.
+    pub fn serve(&mut self, msg: &mut MessageEnvelope) -> bool {
+        match Opcode::from_usize(msg.body.id()) {
+            Some(Opcode::Subscribe) => {
+                let Some(mem) = msg.body.memory_message_mut() else { return true };
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+                let result = match buffer.to_original::<Subscription, _>() {
+                    Ok(sub) => self.subscribe(sub),
+                    Err(_) => Err(ApiError::Malformed),
+                };
+                buffer.replace(SubscribeReply { error: result.err() }).ok();
+            }
+            Some(Opcode::Unsubscribe) => {
+                if let Some(xous::ScalarMessage { arg1, arg2, arg3, arg4, .. }) = msg.body.scalar_message() {
+                    self.unsubscribe([*arg1 as u32, *arg2 as u32, *arg3 as u32, *arg4 as u32]);
+                }
+                if msg.body.is_blocking() {
+                    xous::return_scalar(msg.sender, 0).ok();
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Registering again just changes the opcode.
+    pub fn subscribe(&mut self, sub: Subscription) -> Result<(), ApiError> {
+        if let Some(known) = self.list.iter_mut().find(|s| s.sid == sub.sid) {
+            known.opcode = sub.opcode;
+            return Ok(());
+        }
+        if self.list.len() >= MAX_SUBSCRIBERS {
+            return Err(ApiError::TooManySubscribers);
+        }
+        let cid = self.transport.connect(sub.sid).ok_or(ApiError::Ipc)?;
+        self.list.push(Subscriber { sid: sub.sid, cid, opcode: sub.opcode });
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, sid: [u32; 4]) {
+        if let Some(pos) = self.list.iter().position(|s| s.sid == sid) {
+            let gone = self.list.remove(pos);
+            self.transport.disconnect(gone.cid);
+        }
+    }
+
+    /// Send `notice` to every subscriber. One that can't be reached has
+    /// gone away without unsubscribing, so it's dropped.
+    pub fn notify(&mut self, notice: &ChangeNotice) {
+        let transport = &mut self.transport;
+        self.list.retain(|s| {
+            let reached = transport.send(s.cid, s.opcode, notice);
+            if !reached {
+                transport.disconnect(s.cid);
+            }
+            reached
+        });
+    }
+}
+
+enum Backend {
+    Server(CID),
+    StandIn(StandIn),
+}
+
+pub struct PlannerClient {
+    backend: Backend,
+}
+
+impl PlannerClient {
+    /// Connect to the running planner, waiting for it to start.
+    pub fn connect(xns: &xous_names::XousNames) -> Result<Self, xous::Error> {
+        let conn = xns.request_connection_blocking(SERVER_NAME).or(Err(xous::Error::ServerNotFound))?;
+        Ok(Self { backend: Backend::Server(conn) })
+    }
+
+    /// A client answered in-process from `data`, for host tests.
+    pub fn stand_in(data: StandIn) -> Self {
+        Self { backend: Backend::StandIn(data) }
+    }
+
+    pub fn events_in_range(&self, from: Date, to: Date, max: u32) -> Result<EventList, xous::Error> {
+        let query = RangeQuery { from, to, max };
+        match &self.backend {
+            Backend::StandIn(data) => Ok(events_in_range(&data.events, &query)),
+            Backend::Server(conn) => {
+                let mut buf = Buffer::into_buf(query).or(Err(xous::Error::InternalError))?;
+                buf.lend_mut(*conn, Opcode::EventsInRange.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+                buf.to_original::<EventList, _>().or(Err(xous::Error::InternalError))
+            }
+        }
+    }
+
+    /// Events on one day.
+    pub fn events_on(&self, date: Date) -> Result<EventList, xous::Error> {
+        self.events_in_range(date, date, 0)
+    }
+
+    pub fn pending_tasks(&self) -> Result<TaskCounts, xous::Error> {
+        match &self.backend {
+            Backend::StandIn(data) => Ok(data.task_counts()),
+            Backend::Server(conn) => {
+                let op = Opcode::PendingTasks.to_usize().unwrap();
+                match xous::send_message(*conn, Message::new_blocking_scalar(op, 0, 0, 0, 0))? {
+                    xous::Result::Scalar2(open, high) => Ok(TaskCounts { open: open as u32, high: high as u32 }),
+                    _ => Err(xous::Error::InternalError),
+                }
+            }
+        }
+    }
+
+    /// The first event after `time` on `date` (see `NextQuery`).
+    pub fn next_event(&self, date: Date, time: Option<Time>) -> Result<Option<EventInfo>, xous::Error> {
+        let query = NextQuery { date, time };
+        match &self.backend {
+            Backend::StandIn(data) => Ok(next_event(&data.events, &query).event),
+            Backend::Server(conn) => {
+                let mut buf = Buffer::into_buf(query).or(Err(xous::Error::InternalError))?;
+                buf.lend_mut(*conn, Opcode::NextEvent.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+                let answer = buf.to_original::<NextEvent, _>().or(Err(xous::Error::InternalError))?;
+                Ok(answer.event)
+            }
+        }
+    }
+
+    /// Add an event. Returns its uid.
+    pub fn create_event(&mut self, event: NewEvent) -> Result<[u8; 16], ApiError> {
+        match &mut self.backend {
+            Backend::StandIn(data) => data.create_event(event),
+            Backend::Server(conn) => send_edit(*conn, Opcode::CreateEvent, Buffer::into_buf(event).ok()),
+        }
+    }
+
+    pub fn update_event(&mut self, update: EventUpdate) -> Result<[u8; 16], ApiError> {
+        match &mut self.backend {
+            Backend::StandIn(data) => data.update_event(update),
+            Backend::Server(conn) => send_edit(*conn, Opcode::UpdateEvent, Buffer::into_buf(update).ok()),
+        }
+    }
+
+    /// Add a task. Returns its uid.
+    pub fn create_task(&mut self, task: NewTask) -> Result<[u8; 16], ApiError> {
+        match &mut self.backend {
+            Backend::StandIn(data) => data.create_task(task),
+            Backend::Server(conn) => send_edit(*conn, Opcode::CreateTask, Buffer::into_buf(task).ok()),
+        }
+    }
+
+    pub fn update_task(&mut self, update: TaskUpdate) -> Result<[u8; 16], ApiError> {
+        match &mut self.backend {
+            Backend::StandIn(data) => data.update_task(update),
+            Backend::Server(conn) => send_edit(*conn, Opcode::UpdateTask, Buffer::into_buf(update).ok()),
+        }
+    }
+
+    pub fn complete_task(&mut self, uid: [u8; 16]) -> Result<[u8; 16], ApiError> {
+        match &mut self.backend {
+            Backend::StandIn(data) => data.complete_task(uid),
+            Backend::Server(conn) => send_edit(*conn, Opcode::CompleteTask, Buffer::into_buf(ItemRef { uid }).ok()),
+        }
+    }
+
+    /// Have notices sent to `sid` with `opcode` (see `ChangeNotice`).
+    /// A stand-in accepts subscriptions but never sends notices.
+    pub fn subscribe(&self, sid: xous::SID, opcode: u32) -> Result<(), ApiError> {
+        let conn = match &self.backend {
+            Backend::StandIn(_) => return Ok(()),
+            Backend::Server(conn) => *conn,
+        };
+        let mut buf = Buffer::into_buf(Subscription { sid: sid.to_array(), opcode }).or(Err(ApiError::Ipc))?;
+        buf.lend_mut(conn, Opcode::Subscribe.to_u32().unwrap()).or(Err(ApiError::Ipc))?;
+        match buf.to_original::<SubscribeReply, _>().or(Err(ApiError::Ipc))?.error {
+            None => Ok(()),
+            Some(e) => Err(e),
+        }
+    }
+
+    /// Stop notices to `sid`. Call before destroying the server, so no
+    /// notice is in flight to it.
+    pub fn unsubscribe(&self, sid: xous::SID) -> Result<(), ApiError> {
+        let Backend::Server(conn) = &self.backend else { return Ok(()) };
+        let [a, b, c, d] = sid.to_array();
+        let op = Opcode::Unsubscribe.to_usize().unwrap();
+        let msg = Message::new_blocking_scalar(op, a as usize, b as usize, c as usize, d as usize);
+        xous::send_message(*conn, msg).or(Err(ApiError::Ipc))?;
+        Ok(())
+    }
+}
+
+/// Lend a request, already in `buf`, to the planner and read back its
+/// `EditReply`.
+fn send_edit(conn: CID, op: Opcode, buf: Option<Buffer>) -> Result<[u8; 16], ApiError> {
+    let mut buf = buf.ok_or(ApiError::Ipc)?;
+    buf.lend_mut(conn, op.to_u32().unwrap()).or(Err(ApiError::Ipc))?;
+    buf.to_original::<EditReply, _>().or(Err(ApiError::Ipc))?.result()
+}
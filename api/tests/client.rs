@@ -0,0 +1,115 @@
+//! `PlannerClient` answered by a `StandIn`, the way client apps test
+//! against it: every query, and the errors edits come back with.
+
+use planner_api::{
+    ApiError, Date, EventInfo, EventUpdate, NewEvent, NewTask, PlannerClient, Priority, StandIn, TaskCounts, TaskInfo,
+    TaskUpdate, Time,
+};
+
+fn day(day: u8) -> Date {
+    Date { year: 2026, month: 3, day }
+}
+
+fn at(hour: u8, minute: u8) -> Option<Time> {
+    Some(Time { hour, minute })
+}
+
+fn event(n: u8, date: Date, time: Option<Time>, title: &str) -> EventInfo {
+    EventInfo { uid: [n; 16], date, time, title: title.into(), priority: Priority::Normal, calendar: 0 }
+}
+
+fn task(n: u8, title: &str, priority: Priority, done: bool) -> TaskInfo {
+    TaskInfo { uid: [n; 16], title: title.into(), priority, done }
+}
+
+/// Listed out of order on purpose.
+fn client() -> PlannerClient {
+    PlannerClient::stand_in(StandIn {
+        events: vec![
+            event(1, day(5), at(9, 0), "Thursday"),
+            event(2, day(4), at(14, 0), "Late"),
+            event(3, day(4), None, "All day"),
+            event(4, day(4), at(8, 30), "Early"),
+            event(5, Date { year: 2026, month: 4, day: 1 }, None, "April"),
+            event(6, day(3), at(23, 0), "Before"),
+        ],
+        tasks: vec![
+            task(7, "Open, high", Priority::High, false),
+            task(8, "Open, low", Priority::Low, false),
+            task(9, "Done, high", Priority::High, true),
+        ],
+    })
+}
+
+fn titles(events: &[EventInfo]) -> Vec<&str> {
+    events.iter().map(|e| e.title.as_str()).collect()
+}
+
+#[test]
+fn events_in_range() {
+    let client = client();
+    let list = client.events_in_range(day(4), day(31), 0).unwrap();
+    assert_eq!(titles(&list.events), ["All day", "Early", "Late", "Thursday"]);
+    assert!(!list.truncated);
+
+    let list = client.events_in_range(day(4), day(31), 2).unwrap();
+    assert_eq!(titles(&list.events), ["All day", "Early"]);
+    assert!(list.truncated);
+    let list = client.events_in_range(day(4), day(31), 4).unwrap();
+    assert!(!list.truncated, "exactly max is not truncated");
+
+    let list = client.events_in_range(day(1), Date { year: 2026, month: 12, day: 31 }, 0).unwrap();
+    assert_eq!(list.events.len(), 6);
+    assert_eq!(list.events[5].uid, [5; 16]);
+    assert!(client.events_in_range(day(6), day(31), 0).unwrap().events.is_empty());
+    assert!(client.events_in_range(day(5), day(4), 0).unwrap().events.is_empty(), "backwards");
+}
+
+#[test]
+fn events_on() {
+    let client = client();
+    assert_eq!(titles(&client.events_on(day(4)).unwrap().events), ["All day", "Early", "Late"]);
+    assert_eq!(titles(&client.events_on(day(3)).unwrap().events), ["Before"]);
+    assert_eq!(client.events_on(day(10)).unwrap(), Default::default());
+}
+
+#[test]
+fn pending_tasks() {
+    assert_eq!(client().pending_tasks().unwrap(), TaskCounts { open: 2, high: 1 });
+    assert_eq!(PlannerClient::stand_in(StandIn::default()).pending_tasks().unwrap(), TaskCounts::default());
+}
+
+#[test]
+fn next_event() {
+    let client = client();
+    let next = |date, time| client.next_event(date, time).unwrap().map(|e| e.title);
+    assert_eq!(next(day(4), None).as_deref(), Some("All day"));
+    assert_eq!(next(day(4), at(0, 0)).as_deref(), Some("Early"));
+    assert_eq!(next(day(4), at(8, 30)).as_deref(), Some("Late"), "strictly after");
+    assert_eq!(next(day(4), at(14, 0)).as_deref(), Some("Thursday"));
+    assert_eq!(next(day(3), at(23, 30)).as_deref(), Some("All day"));
+    assert_eq!(next(day(6), None).as_deref(), Some("April"));
+    assert_eq!(next(Date { year: 2026, month: 4, day: 2 }, None), None);
+}
+
+#[test]
+fn edit_errors() {
+    let mut client = client();
+    let blank = NewEvent { date: day(6), time: None, title: " \t".into(), priority: Priority::Low, calendar: None };
+    assert_eq!(client.create_event(blank), Err(ApiError::BadTitle));
+    let late =
+        NewEvent { date: day(6), time: at(24, 0), title: "Late".into(), priority: Priority::Low, calendar: None };
+    assert_eq!(client.create_event(late), Err(ApiError::BadTime));
+    let update = EventUpdate { uid: [1; 16], time: Some(at(9, 60)), ..EventUpdate::default() };
+    assert_eq!(client.update_event(update), Err(ApiError::BadTime));
+    assert_eq!(client.update_event(EventUpdate { uid: [42; 16], ..EventUpdate::default() }), Err(ApiError::NotFound));
+
+    assert_eq!(client.create_task(NewTask { title: String::new(), priority: Priority::Low }), Err(ApiError::BadTitle));
+    assert_eq!(client.update_task(TaskUpdate { uid: [1; 16], ..TaskUpdate::default() }), Err(ApiError::NotFound));
+    assert_eq!(client.complete_task([42; 16]), Err(ApiError::NotFound));
+
+    // Refused edits change nothing.
+    assert_eq!(client.events_in_range(day(1), day(31), 0).unwrap().events.len(), 5);
+    assert_eq!(client.events_on(day(5)).unwrap().events[0].time, at(9, 0));
+    assert_eq!(client.pending_tasks().unwrap(), TaskCounts { open: 2, high: 1 });
+}
@@ -0,0 +1,104 @@
+//! Edit requests as `serve_edits` carries them out, made to a `StandIn`:
+//! the replies, what gets refused, and that accepted edits stay made.
+
+use planner_api::{
+    edit, ApiError, Date, EditReply, EditRequest, EventUpdate, ItemRef, NewEvent, NewTask, PlannerClient, Priority,
+    StandIn, TaskCounts, TaskUpdate, Time, EVENT_TITLE_MAX, TASK_TITLE_MAX,
+};
+
+const MARCH_4: Date = Date { year: 2026, month: 3, day: 4 };
+
+fn new_event(title: &str) -> EditRequest {
+    EditRequest::CreateEvent(NewEvent {
+        date: MARCH_4,
+        time: Some(Time { hour: 9, minute: 0 }),
+        title: title.into(),
+        priority: Priority::Normal,
+        calendar: None,
+    })
+}
+
+fn new_task(title: &str) -> EditRequest {
+    EditRequest::CreateTask(NewTask { title: title.into(), priority: Priority::High })
+}
+
+fn refused(error: ApiError) -> EditReply {
+    EditReply { uid: [0; 16], error: Some(error) }
+}
+
+fn made(reply: EditReply) -> [u8; 16] {
+    assert_eq!(reply.error, None);
+    assert_ne!(reply.uid, [0; 16]);
+    reply.uid
+}
+
+#[test]
+fn titles() {
+    let mut data = StandIn::default();
+    for bad in ["", "   ", "two\nlines", "tab\there"] {
+        assert_eq!(edit(&mut data, new_event(bad)), refused(ApiError::BadTitle), "{:?}", bad);
+        assert_eq!(edit(&mut data, new_task(bad)), refused(ApiError::BadTitle), "{:?}", bad);
+    }
+    assert_eq!(edit(&mut data, new_event(&"e".repeat(EVENT_TITLE_MAX + 1))), refused(ApiError::BadTitle));
+    assert_eq!(edit(&mut data, new_task(&"t".repeat(TASK_TITLE_MAX + 1))), refused(ApiError::BadTitle));
+    assert!(data.events.is_empty() && data.tasks.is_empty());
+
+    // Limits count characters, after trimming.
+    made(edit(&mut data, new_event(&format!("  {}  ", "é".repeat(EVENT_TITLE_MAX)))));
+    made(edit(&mut data, new_task(&"t".repeat(TASK_TITLE_MAX))));
+    assert_eq!(data.events[0].title, "é".repeat(EVENT_TITLE_MAX));
+
+    let uid = data.events[0].uid;
+    let too_long = EventUpdate { uid, title: Some("e".repeat(EVENT_TITLE_MAX + 1)), ..EventUpdate::default() };
+    assert_eq!(edit(&mut data, EditRequest::UpdateEvent(too_long)), refused(ApiError::BadTitle));
+    let uid = data.tasks[0].uid;
+    let blank = TaskUpdate { uid, title: Some(" ".into()), done: Some(true), ..TaskUpdate::default() };
+    assert_eq!(edit(&mut data, EditRequest::UpdateTask(blank)), refused(ApiError::BadTitle));
+    assert!(!data.tasks[0].done, "refused whole");
+}
+
+#[test]
+fn unknown_uids() {
+    let mut data = StandIn::default();
+    made(edit(&mut data, new_event("Standup")));
+    made(edit(&mut data, new_task("Pay rent")));
+    let nobody = [0xee; 16];
+    for request in [
+        EditRequest::UpdateEvent(EventUpdate { uid: nobody, ..EventUpdate::default() }),
+        EditRequest::UpdateTask(TaskUpdate { uid: nobody, ..TaskUpdate::default() }),
+        EditRequest::CompleteTask(ItemRef { uid: nobody }),
+        // Events and tasks are told apart.
+        EditRequest::UpdateEvent(EventUpdate { uid: data.tasks[0].uid, ..EventUpdate::default() }),
+        EditRequest::CompleteTask(ItemRef { uid: data.events[0].uid }),
+    ] {
+        assert_eq!(edit(&mut data, request.clone()), refused(ApiError::NotFound), "{:?}", request);
+    }
+}
+
+#[test]
+fn edits_stay_made() {
+    let mut data = StandIn::default();
+    let event = made(edit(&mut data, new_event("Standup")));
+    let task = made(edit(&mut data, new_task("Pay rent")));
+    let other = made(edit(&mut data, new_task("Call Bob")));
+    assert!(event != task && task != other && event != other);
+
+    let moved = EventUpdate {
+        uid: event,
+        date: Some(Date { day: 5, ..MARCH_4 }),
+        time: Some(None),
+        priority: Some(Priority::High),
+        ..EventUpdate::default()
+    };
+    assert_eq!(edit(&mut data, EditRequest::UpdateEvent(moved)), EditReply { uid: event, error: None });
+    assert_eq!(edit(&mut data, EditRequest::CompleteTask(ItemRef { uid: task })), EditReply { uid: task, error: None });
+    let renamed = TaskUpdate { uid: other, title: Some("Call Bob back".into()), ..TaskUpdate::default() };
+    made(edit(&mut data, EditRequest::UpdateTask(renamed)));
+
+    // Queries see the result, and untouched fields are kept.
+    let client = PlannerClient::stand_in(data);
+    assert!(client.events_on(MARCH_4).unwrap().events.is_empty());
+    let moved = &client.events_on(Date { day: 5, ..MARCH_4 }).unwrap().events[0];
+    assert_eq!((moved.uid, moved.time, moved.priority, moved.title.as_str()), (event, None, Priority::High, "Standup"));
+    assert_eq!(client.pending_tasks().unwrap(), TaskCounts { open: 1, high: 1 });
+}
@@ -0,0 +1,129 @@
+//! The planner's subscriber list over an in-process transport: who gets
+//! each notice, and how many times.
+
+use std::collections::BTreeSet;
+
+use planner_api::{
+    ApiError, Change, ChangeKind, ChangeNotice, Item, Subscribers, Subscription, Transport, MAX_NOTICE_CHANGES,
+    MAX_SUBSCRIBERS,
+};
+use xous::CID;
+
+/// Connection IDs are the first word of the SID; servers in `gone` have
+/// exited.
+#[derive(Default)]
+struct Fake {
+    sent: Vec<(CID, u32, ChangeNotice)>,
+    gone: BTreeSet<CID>,
+    open: BTreeSet<CID>,
+}
+
+impl Transport for Fake {
+    fn connect(&mut self, sid: [u32; 4]) -> Option<CID> {
+        let cid = sid[0] as CID;
+        if self.gone.contains(&cid) {
+            return None;
+        }
+        self.open.insert(cid);
+        Some(cid)
+    }
+
+    fn send(&mut self, cid: CID, opcode: u32, notice: &ChangeNotice) -> bool {
+        if self.gone.contains(&cid) {
+            return false;
+        }
+        self.sent.push((cid, opcode, notice.clone()));
+        true
+    }
+
+    fn disconnect(&mut self, cid: CID) {
+        assert!(self.open.remove(&cid), "disconnected twice");
+    }
+}
+
+fn sub(n: u32, opcode: u32) -> Subscription {
+    Subscription { sid: [n, 0, 0, 0], opcode }
+}
+
+fn notice(n: u8) -> ChangeNotice {
+    ChangeNotice::new(vec![Change { item: Item::Task, kind: ChangeKind::Changed, uid: [n; 16] }], false)
+}
+
+/// Who got what, in order, and clear the record.
+fn delivered(subs: &mut Subscribers<Fake>) -> Vec<(CID, u32)> {
+    subs.transport().sent.drain(..).map(|(cid, opcode, _)| (cid, opcode)).collect()
+}
+
+#[test]
+fn one_notice_per_subscriber() {
+    let mut subs = Subscribers::new(Fake::default());
+    subs.subscribe(sub(1, 100)).unwrap();
+    subs.subscribe(sub(2, 200)).unwrap();
+    // Registering again changes the opcode, not the count.
+    subs.subscribe(sub(1, 101)).unwrap();
+    assert_eq!(subs.len(), 2);
+
+    subs.notify(&notice(1));
+    assert_eq!(delivered(&mut subs), [(1, 101), (2, 200)]);
+    subs.notify(&notice(2));
+    let sent = &subs.transport().sent;
+    assert_eq!(sent.len(), 2);
+    assert!(sent.iter().all(|(_, _, n)| *n == notice(2)));
+}
+
+#[test]
+fn unreachable_subscribers_are_dropped() {
+    let mut subs = Subscribers::new(Fake::default());
+    for n in 1..=3 {
+        subs.subscribe(sub(n, 100)).unwrap();
+    }
+    subs.transport().gone.insert(2);
+    subs.notify(&notice(1));
+    assert_eq!(delivered(&mut subs), [(1, 100), (3, 100)]);
+    assert_eq!(subs.len(), 2);
+    assert_eq!(subs.transport().open, BTreeSet::from([1, 3]));
+
+    subs.notify(&notice(2));
+    assert_eq!(delivered(&mut subs), [(1, 100), (3, 100)], "not tried again");
+
+    // A server that's gone can't subscribe either.
+    assert_eq!(subs.subscribe(sub(2, 100)), Err(ApiError::Ipc));
+    assert_eq!(subs.len(), 2);
+}
+
+#[test]
+fn unsubscribe() {
+    let mut subs = Subscribers::new(Fake::default());
+    subs.subscribe(sub(1, 100)).unwrap();
+    subs.subscribe(sub(2, 100)).unwrap();
+    subs.unsubscribe([1, 0, 0, 0]);
+    subs.unsubscribe([9, 0, 0, 0]);
+    assert_eq!(subs.transport().open, BTreeSet::from([2]));
+    subs.notify(&notice(1));
+    assert_eq!(delivered(&mut subs), [(2, 100)]);
+    subs.unsubscribe([2, 0, 0, 0]);
+    assert!(subs.is_empty());
+}
+
+#[test]
+fn subscriber_limit() {
+    let mut subs = Subscribers::new(Fake::default());
+    for n in 0..MAX_SUBSCRIBERS as u32 {
+        subs.subscribe(sub(n, 100)).unwrap();
+    }
+    assert_eq!(subs.subscribe(sub(99, 100)), Err(ApiError::TooManySubscribers));
+    assert!(subs.subscribe(sub(0, 5)).is_ok(), "known ones may still change opcode");
+    subs.unsubscribe([3, 0, 0, 0]);
+    assert!(subs.subscribe(sub(99, 100)).is_ok());
+}
+
+#[test]
+fn long_notices_become_resets() {
+    let change = |n: u8| Change { item: Item::Event, kind: ChangeKind::Added, uid: [n; 16] };
+    let most: Vec<Change> = (0..MAX_NOTICE_CHANGES as u8).map(change).collect();
+    assert_eq!(ChangeNotice::new(most.clone(), false).changes, most);
+    let mut too_many = most;
+    too_many.push(change(0xff));
+    assert_eq!(ChangeNotice::new(too_many, false), ChangeNotice { changes: Vec::new(), reset: true });
+    assert_eq!(ChangeNotice::new(vec![change(1)], true), ChangeNotice { changes: Vec::new(), reset: true });
+}
@@ -0,0 +1,86 @@
+//! Encoded size and decode time of stored events, JSON vs postcard.
+//!
+//!   cargo bench -p planner-core --features binary-storage --bench storage_codec
+//!
+//! Runs on the host, against `planner-core`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use planner_core::codec::{self, Format};
+use planner_core::planner::{Date, Event, Priority, Time};
+use planner_core::schema::{self, Record, SCHEMA_VERSION};
+
+const TITLES: [&str; 5] = [
+    "Standup",
+    "Dentist appointment",
+    "Lunch with Sam #personal",
+    "Quarterly planning review #work",
+    "Call",
+];
+
+fn sample_events(n: usize) -> Vec<Event> {
+    let start = Date::new(2026, 1, 1);
+    (0..n)
+        .map(|i| {
+            let mut ev = Event::new(i as u32 + 1, start.add_days((i / 4) as i32), TITLES[i % TITLES.len()].to_string());
+            if i % 3 != 0 {
+                ev.time = Some(Time::new(8 + (i % 10) as u8, (i % 12) as u8 * 5));
+            }
+            ev.priority = match i % 3 {
+                0 => Priority::Low,
+                1 => Priority::Normal,
+                _ => Priority::High,
+            };
+            ev
+        })
+        .collect()
+}
+
+fn run(n: usize, format: Format) {
+    let events = sample_events(n);
+
+    // Per-record layout: one key per event.
+    let records: Vec<Vec<u8>> = events
+        .iter()
+        .map(|e| codec::encode_as(&Record { version: SCHEMA_VERSION, item: e.clone() }, format))
+        .collect();
+    let record_bytes: usize = records.iter().map(|r| r.len()).sum();
+
+    let rounds = (100_000 / n).max(3);
+    let t = Instant::now();
+    for _ in 0..rounds {
+        for r in records.iter() {
+            let rec: Record<Event> = codec::decode(black_box(r)).expect("decode");
+            black_box(rec);
+        }
+    }
+    let per_round = t.elapsed() / rounds as u32;
+
+    // Whole-collection envelope, for comparison with the old layout.
+    let blob = codec::encode_as(&schema::Envelope { version: SCHEMA_VERSION, items: events }, format);
+    let t = Instant::now();
+    for _ in 0..rounds {
+        let env: schema::Envelope<Event> = codec::decode(black_box(&blob)).expect("decode");
+        black_box(env);
+    }
+    let blob_round = t.elapsed() / rounds as u32;
+
+    println!(
+        "{:>6} events  {:<9} records {:>9} B {:>10.2?}   envelope {:>9} B {:>10.2?}",
+        n,
+        format!("{:?}", format),
+        record_bytes,
+        per_round,
+        blob.len(),
+        blob_round,
+    );
+}
+
+fn main() {
+    for &n in [1_000, 10_000].iter() {
+        for &format in [Format::Json, Format::Postcard].iter() {
+            run(n, format);
+        }
+    }
+}
@@ -0,0 +1,2352 @@
+//! State machine for Day Planner.
+//!
+//! States:
+//!   DayView     — shows events for the selected date
+//!   TaskList    — shows the to-do list
+//!   AddEvent    — multi-field form for new event
+//!   EditEvent   — edit an existing event
+//!   AddTask     — text entry for new task
+//!   ConfirmDel  — confirm deletion of event or task
+//!   MonthView   — calendar month grid for date picking
+//!   QueryList   — saved filters
+//!   QueryResults — live results of the selected filter
+//!   AddQuery    — name + query text entry for a new filter
+//!   Recovery    — stored data couldn't be read; retry, export or start fresh
+//!   Snapshots   — restore points; restore, delete or take one now
+//!   Calendars   — show or hide each calendar
+//!   ImportReview — imported items (from the inbox, via I) against the
+//!                 planner's; accept, skip or merge each
+
+extern crate alloc;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::backend::Backend;
+use crate::backup::{self, Backup, BackupError, ImportMode, ImportReport};
+use crate::calendar::{Calendar, BASIS_CALENDAR_BASE, DEFAULT_CALENDAR};
+use crate::csv::{self, CsvError};
+use crate::dedup::{Choice, Entry, Review, Reviewable};
+use crate::ical::{self, IcalError, Issue};
+use crate::planner::*;
+use crate::query::{self, Hit, Query, QueryError, SavedQuery};
+use crate::seal::{self, KdfParams, SealError, NONCE_LEN, SALT_LEN};
+use crate::storage::{Batch, SnapshotInfo, SnapshotReason, Storage, StorageError, StorageStats};
+
+// Keyboard constants, as `handle_key` takes them
+pub const KEY_UP: char = '\u{F700}';
+pub const KEY_DOWN: char = '\u{F701}';
+pub const KEY_LEFT: char = '\u{F702}';
+pub const KEY_RIGHT: char = '\u{F703}';
+pub const KEY_ENTER: char = '\u{000D}';
+pub const KEY_BACKSPACE: char = '\u{0008}';
+pub const KEY_MENU: char = '\u{2234}'; // ∴
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppState {
+    DayView,
+    TaskList,
+    AddEvent,
+    EditEvent,
+    AddTask,
+    ConfirmDel,
+    MonthView,
+    QueryList,
+    QueryResults,
+    AddQuery,
+    Recovery,
+    Snapshots,
+    Calendars,
+    ImportReview,
+}
+
+/// Which field is being edited in AddEvent/EditEvent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventField {
+    Title,
+    Hour,
+    Minute,
+    Priority,
+    Calendar,
+}
+
+/// Which field is being edited in AddQuery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryField {
+    Name,
+    Text,
+}
+
+/// What we're about to delete.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeleteTarget {
+    Event(u32, CalendarId),
+    Task(u32),
+    Query(usize),
+    Snapshot(usize),
+}
+
+/// Changes made in memory but not yet written; see `save_dirty`.
+#[derive(Debug, Default)]
+struct Dirty {
+    events: BTreeSet<u32>,
+    /// Removed events, with the calendar they were stored in.
+    deleted_events: BTreeSet<(u32, CalendarId)>,
+    tasks: BTreeSet<u32>,
+    deleted_tasks: BTreeSet<u32>,
+    next_id: bool,
+    queries: bool,
+    calendars: BTreeSet<CalendarId>,
+}
+
+impl Dirty {
+    fn is_empty(&self) -> bool {
+        self.events.is_empty()
+            && self.deleted_events.is_empty()
+            && self.tasks.is_empty()
+            && self.deleted_tasks.is_empty()
+            && !self.next_id
+            && !self.queries
+            && self.calendars.is_empty()
+    }
+}
+
+/// What happened to an item, as told to subscribers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// Changes other apps haven't been told about yet; see `take_changes`.
+/// Items in secret calendars never appear here.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub events: BTreeMap<Uid, ChangeKind>,
+    pub tasks: BTreeMap<Uid, ChangeKind>,
+    /// Everything may have changed (a reload, restore or replacing
+    /// import); the maps are empty.
+    pub reset: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.tasks.is_empty() && !self.reset
+    }
+
+    /// Fold `kind` into what's already noted for `uid`, so one notice says
+    /// where the item ended up: added then changed is still added, added
+    /// then removed is nothing at all.
+    fn note(map: &mut BTreeMap<Uid, ChangeKind>, uid: Uid, kind: ChangeKind) {
+        use ChangeKind::*;
+        let folded = match (map.get(&uid).copied(), kind) {
+            (None, kind) => Some(kind),
+            (Some(Added), Changed) => Some(Added),
+            (Some(Added), Removed) => None,
+            (Some(Removed), Added) => Some(Changed),
+            (Some(_), kind) => Some(kind),
+        };
+        match folded {
+            Some(kind) => map.insert(uid, kind),
+            None => map.remove(&uid),
+        };
+    }
+}
+
+/// How well deferred saving is doing; logged on flush.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SaveStats {
+    /// Edits marked dirty. Each used to be its own write and sync.
+    pub changes: u32,
+    /// Edits to something already waiting to be written.
+    pub coalesced: u32,
+    /// Times dirty state was written out.
+    pub batches: u32,
+}
+
+/// Why an edit asked for by another app was refused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditError {
+    /// Blank, too long, or containing control characters.
+    BadTitle,
+    BadDate,
+    BadTime,
+    /// Not in the registry, or a secret calendar.
+    NoSuchCalendar,
+    /// No event or task with that uid that other apps may see.
+    NotFound,
+    /// Stored data couldn't be loaded, so nothing can be saved.
+    Unavailable,
+}
+
+/// Fields to change in an event; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default)]
+pub struct EventChanges {
+    pub date: Option<Date>,
+    /// `Some(None)` makes the event all-day.
+    pub time: Option<Option<Time>>,
+    pub title: Option<String>,
+    pub priority: Option<Priority>,
+    pub calendar: Option<CalendarId>,
+}
+
+/// Fields to change in a task; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default)]
+pub struct TaskChanges {
+    pub title: Option<String>,
+    pub priority: Option<Priority>,
+    pub done: Option<bool>,
+}
+
+pub struct PlannerApp<B: Backend> {
+    pub state: AppState,
+    pub needs_redraw: bool,
+
+    // Date navigation
+    pub current_date: Date,
+    pub today: Date,
+
+    // Events & tasks
+    pub events: Vec<Event>,
+    pub tasks: Vec<Task>,
+    pub next_id: u32,
+    uids: UidGen,
+
+    // Day view cursor
+    pub day_cursor: usize,
+
+    // Task list cursor
+    pub task_cursor: usize,
+
+    // Event form fields
+    pub form_title: String,
+    pub form_hour: u8,
+    pub form_minute: u8,
+    pub form_has_time: bool,
+    pub form_priority: Priority,
+    pub form_calendar: CalendarId,
+    pub form_field: EventField,
+    pub editing_event_id: Option<u32>,
+
+    // Task form
+    pub task_input: String,
+
+    // Delete confirmation
+    pub delete_target: Option<DeleteTarget>,
+
+    // Month view
+    pub month_view_year: u16,
+    pub month_view_month: u8,
+    pub month_cursor_day: u8,
+
+    // Saved queries
+    pub queries: Vec<SavedQuery>,
+    pub query_cursor: usize,
+    pub query_result_cursor: usize,
+    pub query_name_input: String,
+    pub query_text_input: String,
+    pub query_field: QueryField,
+    pub query_error: Option<String>,
+
+    // Recovery: set when stored data couldn't be loaded. Saving is
+    // suspended until the user retries successfully or starts fresh.
+    pub storage_error: Option<StorageError>,
+    pub recovery_status: Option<String>,
+    pub recovery_confirm_fresh: bool,
+
+    // Snapshots, newest first
+    pub snapshots: Vec<SnapshotInfo>,
+    pub snapshot_cursor: usize,
+    pub snapshot_status: Option<String>,
+    pub snapshot_confirm_restore: bool,
+
+    // Calendar registry
+    pub calendars: Vec<Calendar>,
+    pub calendar_cursor: usize,
+
+    // Which store `storage_error` came from
+    pub recovery_calendar: CalendarId,
+
+    // Import review: the cursor runs over events, then tasks
+    pub event_review: Review<Event>,
+    pub task_review: Review<Task>,
+    pub review_cursor: usize,
+    /// Set while choosing fields of a conflicting item.
+    pub review_field: Option<usize>,
+    /// The inbox file under review, removed once the review is applied.
+    pub review_file: Option<String>,
+    pub review_status: Option<String>,
+
+    // Deferred saving
+    dirty: Dirty,
+    pub save_stats: SaveStats,
+    changes: Changes,
+
+    // Storage: `planner.data`, then one per non-default calendar
+    storage: Option<Storage<B>>,
+    calendar_stores: BTreeMap<CalendarId, Storage<B>>,
+    // Files to import; see `storage::INBOX_DICT`
+    inbox: Option<B>,
+}
+
+impl<B: Backend> PlannerApp<B> {
+    pub fn new(initial_date: Date) -> Self {
+        Self {
+            state: AppState::DayView,
+            needs_redraw: true,
+            current_date: initial_date,
+            today: initial_date,
+            events: Vec::new(),
+            tasks: Vec::new(),
+            next_id: 1,
+            uids: UidGen::new([0; 16]),
+            day_cursor: 0,
+            task_cursor: 0,
+            form_title: String::new(),
+            form_hour: 9,
+            form_minute: 0,
+            form_has_time: true,
+            form_priority: Priority::Normal,
+            form_calendar: DEFAULT_CALENDAR,
+            form_field: EventField::Title,
+            editing_event_id: None,
+            task_input: String::new(),
+            delete_target: None,
+            month_view_year: initial_date.year,
+            month_view_month: initial_date.month,
+            month_cursor_day: initial_date.day,
+            queries: Vec::new(),
+            query_cursor: 0,
+            query_result_cursor: 0,
+            query_name_input: String::new(),
+            query_text_input: String::new(),
+            query_field: QueryField::Name,
+            query_error: None,
+            storage_error: None,
+            recovery_status: None,
+            recovery_confirm_fresh: false,
+            snapshots: Vec::new(),
+            snapshot_cursor: 0,
+            snapshot_status: None,
+            snapshot_confirm_restore: false,
+            calendars: crate::calendar::default_calendars(),
+            calendar_cursor: 0,
+            recovery_calendar: DEFAULT_CALENDAR,
+            event_review: Review::default(),
+            task_review: Review::default(),
+            review_cursor: 0,
+            review_field: None,
+            review_file: None,
+            review_status: None,
+            dirty: Dirty::default(),
+            save_stats: SaveStats::default(),
+            changes: Changes::default(),
+            storage: None,
+            calendar_stores: BTreeMap::new(),
+            inbox: None,
+        }
+    }
+
+    /// Seed the uid generator. Call with TRNG output before attaching
+    /// storage; the built-in seed is only fit for tests.
+    pub fn seed_uids(&mut self, seed: [u8; 16]) {
+        self.uids = UidGen::new(seed);
+    }
+
+    /// Use `st` for a non-default calendar's events. Attach these before
+    /// the main storage, or call `reload_storage` afterwards.
+    pub fn attach_calendar_storage(&mut self, id: CalendarId, st: Storage<B>) {
+        self.calendar_stores.insert(id, st);
+    }
+
+    /// Use `inbox` as the import inbox (see `storage::INBOX_DICT`).
+    pub fn attach_inbox(&mut self, inbox: B) {
+        self.inbox = Some(inbox);
+    }
+
+    /// Load state from `st` and keep it for subsequent saves.
+    pub fn attach_storage(&mut self, st: Storage<B>) {
+        self.storage = Some(st);
+        self.reload_storage();
+    }
+
+    /// (Re)load everything from storage, entering Recovery on failure.
+    pub fn reload_storage(&mut self) {
+        self.save_dirty();
+        match self.load_all() {
+            Ok(()) => {
+                self.dirty = Dirty::default();
+                self.storage_error = None;
+                self.assign_missing_uids();
+                self.note_reset();
+                if self.state == AppState::Recovery {
+                    self.state = AppState::DayView;
+                }
+                self.snapshot_if_due();
+            }
+            Err(e) => {
+                log::error!("couldn't load planner data: {}", e);
+                self.storage_error = Some(e);
+                self.recovery_status = None;
+                self.recovery_confirm_fresh = false;
+                self.state = AppState::Recovery;
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    fn load_all(&mut self) -> Result<(), StorageError> {
+        self.recovery_calendar = DEFAULT_CALENDAR;
+        let Some(st) = self.storage.as_mut() else { return Ok(()) };
+        st.recover_journal()?;
+        st.upgrade_schema()?;
+        let mut events = st.load_events()?;
+        let tasks = st.load_tasks()?;
+        let queries = st.load_queries()?;
+        let mut calendars = st.load_calendars()?;
+        let stored_next = st.load_next_id()?;
+        let mut lost = Vec::new();
+        for (&id, cs) in self.calendar_stores.iter_mut() {
+            self.recovery_calendar = id;
+            let loaded = cs
+                .recover_journal()
+                .and_then(|_| cs.upgrade_schema())
+                .and_then(|_| cs.load_events());
+            let mut more = match loaded {
+                Ok(more) => more,
+                // Most likely locked under us; it'll be detached anyway.
+                Err(e) if id >= BASIS_CALENDAR_BASE => {
+                    log::warn!("basis calendar unreadable, dropping it: {}", e);
+                    lost.push(id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for ev in more.iter_mut() {
+                ev.calendar = id;
+            }
+            events.append(&mut more);
+        }
+        self.recovery_calendar = DEFAULT_CALENDAR;
+        // Basis calendars aren't in the registry; keep the attached ones.
+        calendars.extend(
+            self.calendars
+                .iter()
+                .filter(|c| c.basis.is_some() && !lost.contains(&c.id))
+                .cloned(),
+        );
+        for id in lost {
+            self.calendar_stores.remove(&id);
+        }
+        // Never hand out an ID that's already in use, even if next_id lagged.
+        let max_id = events
+            .iter()
+            .map(|e| e.id)
+            .chain(tasks.iter().map(|t| t.id))
+            .max()
+            .unwrap_or(0);
+        self.events = events;
+        self.tasks = tasks;
+        self.queries = queries;
+        self.calendars = calendars;
+        self.next_id = stored_next.max(max_id + 1);
+        Ok(())
+    }
+
+    pub fn storage(&mut self) -> Option<&mut Storage<B>> {
+        self.storage.as_mut()
+    }
+
+    /// The store holding a calendar's events. Calendars without an open
+    /// store of their own fall back to the default one.
+    pub fn calendar_storage(&mut self, cal: CalendarId) -> Option<&mut Storage<B>> {
+        route(&mut self.storage, &mut self.calendar_stores, cal)
+    }
+
+    fn open_calendars(&self) -> BTreeSet<CalendarId> {
+        self.calendar_stores.keys().copied().collect()
+    }
+
+    /// Bases that currently have a calendar attached.
+    pub fn attached_bases(&self) -> Vec<String> {
+        self.calendars.iter().filter_map(|c| c.basis.clone()).collect()
+    }
+
+    /// Add the calendar for an unlocked secret basis, loading its events
+    /// from `st` (which must be pinned to that basis).
+    pub fn attach_basis_storage(&mut self, basis: &str, mut st: Storage<B>) {
+        let Some(id) = (BASIS_CALENDAR_BASE..=CalendarId::MAX)
+            .find(|id| self.calendars.iter().all(|c| c.id != *id))
+        else {
+            log::warn!("too many basis calendars; ignoring one");
+            return;
+        };
+        let loaded = st
+            .recover_journal()
+            .and_then(|_| st.upgrade_schema())
+            .and_then(|_| Ok((st.load_own_calendar()?, st.load_events()?)));
+        let (saved, mut events) = match loaded {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!("basis calendar unreadable, skipping it: {}", e);
+                return;
+            }
+        };
+        let mut cal = saved.unwrap_or_else(|| Calendar::for_basis(id, basis));
+        cal.id = id;
+        cal.basis = Some(String::from(basis));
+        for ev in events.iter_mut() {
+            ev.calendar = id;
+        }
+        self.events.append(&mut events);
+        sort_events(&mut self.events);
+        self.calendars.push(cal);
+        self.calendar_stores.insert(id, st);
+        self.needs_redraw = true;
+    }
+
+    /// Forget a locked basis's calendar and everything loaded from it.
+    pub fn detach_basis(&mut self, basis: &str) {
+        let Some(pos) = self.calendars.iter().position(|c| c.basis.as_deref() == Some(basis)) else {
+            return;
+        };
+        let id = self.calendars.remove(pos).id;
+        self.calendar_stores.remove(&id);
+        // Basis calendar edits are saved as they're made, so this should be
+        // empty; if not, the basis is gone and they can't be written.
+        let unsaved: Vec<u32> = self
+            .events
+            .iter()
+            .filter(|e| e.calendar == id && self.dirty.events.contains(&e.id))
+            .map(|e| e.id)
+            .collect();
+        for eid in unsaved.iter() {
+            self.dirty.events.remove(eid);
+        }
+        self.dirty.deleted_events.retain(|&(_, cal)| cal != id);
+        if self.dirty.calendars.remove(&id) || !unsaved.is_empty() {
+            log::warn!("basis locked with unsaved changes; they're lost");
+        }
+        let editing_gone = self
+            .editing_event_id
+            .is_some_and(|eid| self.events.iter().any(|e| e.id == eid && e.calendar == id));
+        self.events.retain(|e| e.calendar != id);
+        if editing_gone
+            || (matches!(self.state, AppState::AddEvent | AppState::EditEvent) && self.form_calendar == id)
+            || matches!(self.delete_target, Some(DeleteTarget::Event(_, cal)) if cal == id)
+        {
+            self.delete_target = None;
+            self.editing_event_id = None;
+            self.state = AppState::DayView;
+        }
+        if self.form_calendar == id {
+            self.form_calendar = self.new_event_calendar();
+        }
+        self.day_cursor = 0;
+        self.calendar_cursor = self.calendar_cursor.min(self.calendars.len().saturating_sub(1));
+        if self.state == AppState::QueryResults {
+            self.query_result_cursor = 0;
+        }
+        self.needs_redraw = true;
+    }
+
+    fn is_basis_calendar(&self, id: CalendarId) -> bool {
+        self.calendar(id).is_some_and(|c| c.basis.is_some())
+    }
+
+    /// Rewrite everything. Routine edits use the per-record savers below;
+    /// this is for bulk changes that touch most of the data.
+    pub fn save_state(&mut self) {
+        if self.storage_error.is_some() {
+            return;
+        }
+        // Each dictionary is saved atomically, but not all of them together.
+        let groups = group_events(&self.events, &self.open_calendars());
+        let mut saved = true;
+        for (cal, events) in groups {
+            let result = if cal == DEFAULT_CALENDAR {
+                match self.storage.as_mut() {
+                    Some(st) => st.save_all(&events, &self.tasks, self.next_id),
+                    None => Ok(()),
+                }
+            } else {
+                match self.calendar_stores.get_mut(&cal) {
+                    Some(cs) => cs.save_events(&events),
+                    None => Ok(()),
+                }
+            };
+            if let Err(e) = result {
+                log::error!("save failed: {}", e);
+                saved = false;
+            }
+        }
+        if saved {
+            self.dirty.events.clear();
+            self.dirty.deleted_events.clear();
+            self.dirty.tasks.clear();
+            self.dirty.deleted_tasks.clear();
+            self.dirty.next_id = false;
+        }
+    }
+
+    pub fn save_pending(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    fn note_change(&mut self, already_dirty: bool) {
+        self.save_stats.changes += 1;
+        if already_dirty {
+            self.save_stats.coalesced += 1;
+        }
+    }
+
+    /// Queue an added or changed event for saving.
+    fn mark_event(&mut self, id: u32) {
+        let fresh = self.dirty.events.insert(id);
+        self.note_change(!fresh);
+        // Moving an event back cancels its pending delete from there.
+        if let Some(cal) = self.events.iter().find(|e| e.id == id).map(|e| e.calendar) {
+            self.dirty.deleted_events.remove(&(id, cal));
+        }
+    }
+
+    /// Queue removing an event from the store of calendar `cal`.
+    fn mark_event_deleted(&mut self, id: u32, cal: CalendarId) {
+        let pending = self.dirty.events.remove(&id);
+        self.note_change(pending);
+        self.dirty.deleted_events.insert((id, cal));
+    }
+
+    fn mark_task(&mut self, id: u32) {
+        let fresh = self.dirty.tasks.insert(id);
+        self.note_change(!fresh);
+    }
+
+    fn mark_task_deleted(&mut self, id: u32) {
+        let pending = self.dirty.tasks.remove(&id);
+        self.note_change(pending);
+        self.dirty.deleted_tasks.insert(id);
+    }
+
+    fn mark_queries(&mut self) {
+        let pending = core::mem::replace(&mut self.dirty.queries, true);
+        self.note_change(pending);
+    }
+
+    /// Queue a calendar's settings: saved into the registry, or for a
+    /// basis calendar, into its own basis.
+    fn mark_calendar(&mut self, id: CalendarId) {
+        let fresh = self.dirty.calendars.insert(id);
+        self.note_change(!fresh);
+    }
+
+    /// Whether anything dirty belongs to a basis calendar.
+    fn basis_dirty(&self) -> bool {
+        self.dirty.calendars.iter().any(|&id| self.is_basis_calendar(id))
+            || self.dirty.deleted_events.iter().any(|&(_, cal)| self.is_basis_calendar(cal))
+            || self
+                .events
+                .iter()
+                .any(|e| self.dirty.events.contains(&e.id) && self.is_basis_calendar(e.calendar))
+    }
+
+    /// Write everything marked dirty, one transaction per dictionary.
+    /// Runs off the debounce timer, on flush, and before anything that
+    /// needs storage to match memory. On failure it all stays dirty; the
+    /// writes are idempotent, so retrying is safe.
+    pub fn save_dirty(&mut self) {
+        if self.dirty.is_empty() || self.storage_error.is_some() {
+            return;
+        }
+        let dirty = core::mem::take(&mut self.dirty);
+        let open = self.open_calendars();
+        let store_of = |cal: CalendarId| if open.contains(&cal) { cal } else { DEFAULT_CALENDAR };
+        let registry: Vec<Calendar> = self.calendars.iter().filter(|c| c.basis.is_none()).cloned().collect();
+
+        // The default dictionary sorts first, so next_id is written before
+        // any record using a new ID. Events that moved calendar are deleted
+        // from the old one last, once every new copy is in.
+        let mut batches: BTreeMap<CalendarId, Batch> = BTreeMap::new();
+        let mut moves: BTreeMap<CalendarId, Batch> = BTreeMap::new();
+        for ev in self.events.iter().filter(|e| dirty.events.contains(&e.id)) {
+            batches.entry(store_of(ev.calendar)).or_default().events.push(ev);
+        }
+        for &(id, cal) in dirty.deleted_events.iter() {
+            let moved = self.events.iter().any(|e| e.id == id);
+            let target = if moved { &mut moves } else { &mut batches };
+            target.entry(store_of(cal)).or_default().deleted_events.push(id);
+        }
+        let main = batches.entry(DEFAULT_CALENDAR).or_default();
+        main.tasks = self.tasks.iter().filter(|t| dirty.tasks.contains(&t.id)).collect();
+        main.deleted_tasks = dirty.deleted_tasks.iter().copied().collect();
+        main.next_id = dirty.next_id.then_some(self.next_id);
+        main.queries = dirty.queries.then_some(&self.queries[..]);
+        for cal in self.calendars.iter().filter(|c| dirty.calendars.contains(&c.id)) {
+            if cal.basis.is_some() {
+                batches.entry(cal.id).or_default().own_calendar = Some(cal);
+            } else {
+                batches.entry(DEFAULT_CALENDAR).or_default().calendars = Some(&registry[..]);
+            }
+        }
+
+        let mut ok = true;
+        for (phase, cal, batch) in batches.iter().map(|(c, b)| (0, c, b)).chain(moves.iter().map(|(c, b)| (1, c, b))) {
+            if phase == 1 && !ok {
+                break;
+            }
+            if let Some(st) = route(&mut self.storage, &mut self.calendar_stores, *cal) {
+                if let Err(e) = st.save_batch(batch) {
+                    log::error!("saving calendar {} failed: {}", cal, e);
+                    ok = false;
+                }
+            }
+        }
+        if ok {
+            self.save_stats.batches += 1;
+        } else {
+            self.dirty = dirty;
+        }
+    }
+
+    /// Storage activity summed over every open dictionary.
+    pub fn io_stats(&self) -> StorageStats {
+        let mut total = StorageStats::default();
+        for st in self.storage.iter().chain(self.calendar_stores.values()) {
+            let s = st.stats();
+            total.writes += s.writes;
+            total.deletes += s.deletes;
+            total.syncs += s.syncs;
+        }
+        total
+    }
+
+    fn delete_snapshot(&mut self, idx: usize) {
+        if self.storage_error.is_some() {
+            return;
+        }
+        let (Some(st), Some(info)) = (self.storage.as_mut(), self.snapshots.get(idx)) else { return };
+        if let Err(e) = st.delete_snapshot(&info.key) {
+            log::error!("deleting snapshot {} failed: {}", info.key, e);
+        }
+    }
+
+    /// Write anything pending and flush storage; called when going to the
+    /// background or quitting.
+    pub fn flush(&mut self) {
+        self.save_dirty();
+        if let Some(ref mut st) = self.storage {
+            st.flush().ok();
+        }
+        for cs in self.calendar_stores.values_mut() {
+            cs.flush().ok();
+        }
+        let io = self.io_stats();
+        log::info!(
+            "saves: {} edits ({} coalesced) in {} batches; {} writes, {} deletes, {} syncs",
+            self.save_stats.changes,
+            self.save_stats.coalesced,
+            self.save_stats.batches,
+            io.writes,
+            io.deletes,
+            io.syncs
+        );
+    }
+
+    /// Snapshot the current state. Returns false if nothing was stored.
+    pub fn take_snapshot(&mut self, reason: SnapshotReason) -> bool {
+        if self.storage_error.is_some() {
+            return false;
+        }
+        // Snapshots live in the system basis; secret events stay out of them.
+        let events: Vec<&Event> = self.events.iter().filter(|e| !self.is_basis_calendar(e.calendar)).collect();
+        let Some(st) = self.storage.as_mut() else { return false };
+        match st.take_snapshot(self.today, reason, &events, &self.tasks, self.next_id, &self.queries) {
+            Ok(_) => true,
+            Err(e) => {
+                log::error!("snapshot failed: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Take the daily snapshot if there isn't one from today yet.
+    fn snapshot_if_due(&mut self) {
+        if self.events.is_empty() && self.tasks.is_empty() {
+            return;
+        }
+        let latest = match self.storage.as_mut().map(|st| st.latest_snapshot_date()) {
+            Some(Ok(latest)) => latest,
+            Some(Err(e)) => {
+                log::error!("couldn't list snapshots: {}", e);
+                return;
+            }
+            None => return,
+        };
+        if latest.is_none_or(|d| d < self.today) {
+            self.take_snapshot(SnapshotReason::Daily);
+        }
+    }
+
+    fn refresh_snapshots(&mut self) {
+        self.snapshots = match self.storage.as_mut().map(|st| st.snapshots()) {
+            Some(Ok(list)) => list,
+            Some(Err(e)) => {
+                self.snapshot_status = Some(format!("Couldn't list snapshots: {}", e));
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        self.snapshot_cursor = self.snapshot_cursor.min(self.snapshots.len().saturating_sub(1));
+    }
+
+    /// Replace the live state with the selected snapshot, after taking a
+    /// snapshot of the current state so the restore itself can be undone.
+    fn restore_selected_snapshot(&mut self) {
+        let Some(key) = self.snapshots.get(self.snapshot_cursor).map(|s| s.key.clone()) else {
+            return;
+        };
+        if self.storage_error.is_some() {
+            return;
+        }
+        self.save_dirty();
+        let snap = match self.storage.as_mut().map(|st| st.load_snapshot(&key)) {
+            Some(Ok(snap)) => snap,
+            Some(Err(e)) => {
+                self.snapshot_status = Some(format!("Restore failed: {}", e));
+                return;
+            }
+            None => return,
+        };
+        if !self.take_snapshot(SnapshotReason::Before(String::from("restore"))) {
+            self.snapshot_status = Some(String::from("Couldn't save current state; not restoring"));
+            return;
+        }
+        // IDs handed out since the snapshot must not be reused.
+        let next_id = self.next_id.max(snap.next_id);
+        // Basis calendars aren't in snapshots; leave them as they are.
+        let mut open = self.open_calendars();
+        open.retain(|&id| !self.is_basis_calendar(id));
+        let groups = group_events(&snap.events, &open);
+        let mut result = Ok(());
+        for (cal, events) in groups {
+            let step = if cal == DEFAULT_CALENDAR {
+                match self.storage.as_mut() {
+                    Some(st) => st.restore(&events, &snap.tasks, next_id, &snap.queries),
+                    None => Ok(()),
+                }
+            } else {
+                match self.calendar_stores.get_mut(&cal) {
+                    Some(cs) => cs.save_events(&events),
+                    None => Ok(()),
+                }
+            };
+            result = result.and(step);
+        }
+        match result {
+            Ok(()) => {
+                let basis_ids: BTreeSet<CalendarId> =
+                    self.calendars.iter().filter(|c| c.basis.is_some()).map(|c| c.id).collect();
+                let mut secret: Vec<Event> =
+                    self.events.drain(..).filter(|e| basis_ids.contains(&e.calendar)).collect();
+                self.events = snap.events;
+                self.events.append(&mut secret);
+                self.tasks = snap.tasks;
+                self.queries = snap.queries;
+                self.next_id = next_id;
+                sort_events(&mut self.events);
+                sort_tasks(&mut self.tasks);
+                self.day_cursor = 0;
+                self.task_cursor = 0;
+                self.query_cursor = 0;
+                self.note_reset();
+                self.snapshot_status = Some(format!("Restored {} {}", snap.taken.display(), snap.reason));
+            }
+            Err(e) => self.snapshot_status = Some(format!("Restore failed: {}", e)),
+        }
+        self.snapshot_cursor = 0;
+        self.refresh_snapshots();
+    }
+
+    /// Events that may leave the planner: everything outside secret
+    /// calendars.
+    pub fn shareable_events(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(|e| !self.is_basis_calendar(e.calendar))
+    }
+
+    /// Everything outside secret bases as a backup bundle; see `backup.rs`.
+    pub fn export_backup(&self) -> String {
+        let events: Vec<&Event> = self.shareable_events().collect();
+        let registry: Vec<Calendar> = self.calendars.iter().filter(|c| c.basis.is_none()).cloned().collect();
+        Backup::new(self.today, &events, &self.tasks, self.next_id, &self.queries, &registry).to_json()
+    }
+
+    /// Load a backup bundle. Replacing takes a snapshot first so it can be
+    /// undone; secret-basis calendars are left alone either way.
+    pub fn import_backup(&mut self, json: &str, mode: ImportMode) -> Result<ImportReport, BackupError> {
+        let backup = Backup::from_json(json)?;
+        self.save_dirty();
+        let mut open = self.open_calendars();
+        open.retain(|&id| !self.is_basis_calendar(id));
+        let report = match mode {
+            ImportMode::Merge => {
+                let first_event = self.events.len();
+                let first_task = self.tasks.len();
+                let uids = &mut self.uids;
+                let report = backup::merge(
+                    &backup,
+                    &open,
+                    &mut self.events,
+                    &mut self.tasks,
+                    &mut self.next_id,
+                    &mut self.queries,
+                    &mut || uids.generate(),
+                );
+                let event_ids: Vec<u32> = self.events[first_event..].iter().map(|e| e.id).collect();
+                let task_ids: Vec<u32> = self.tasks[first_task..].iter().map(|t| t.id).collect();
+                for id in event_ids {
+                    self.mark_event(id);
+                    self.note_event(id, ChangeKind::Added);
+                }
+                for id in task_ids {
+                    self.mark_task(id);
+                    self.note_task(id, ChangeKind::Added);
+                }
+                self.dirty.next_id = true;
+                if report.queries_added > 0 {
+                    self.mark_queries();
+                }
+                report
+            }
+            ImportMode::Replace => {
+                if !self.take_snapshot(SnapshotReason::Before(String::from("import"))) {
+                    return Err(BackupError::NoSnapshot);
+                }
+                // Secret events stay. Everything else is replaced, going
+                // through the same ID remapping as a merge so nothing
+                // imported takes the ID of an event that was kept.
+                let basis_ids: BTreeSet<CalendarId> =
+                    self.calendars.iter().filter(|c| c.basis.is_some()).map(|c| c.id).collect();
+                let mut events: Vec<Event> =
+                    self.events.drain(..).filter(|e| basis_ids.contains(&e.calendar)).collect();
+                let mut tasks = Vec::new();
+                let mut queries = Vec::new();
+                let uids = &mut self.uids;
+                let report = backup::merge(
+                    &backup,
+                    &open,
+                    &mut events,
+                    &mut tasks,
+                    &mut self.next_id,
+                    &mut queries,
+                    &mut || uids.generate(),
+                );
+                self.events = events;
+                self.tasks = tasks;
+                self.queries = queries;
+                // IDs handed out here before must not be reused.
+                self.next_id = self.next_id.max(backup.next_id_floor());
+                for cal in backup.calendars.iter() {
+                    let Some(mine) = self.calendars.iter_mut().find(|c| c.id == cal.id && c.basis.is_none()) else {
+                        continue;
+                    };
+                    mine.name = cal.name.clone();
+                    mine.marker = cal.marker;
+                    mine.visible = cal.visible;
+                    self.dirty.calendars.insert(cal.id);
+                }
+                self.save_state();
+                self.mark_queries();
+                self.note_reset();
+                self.day_cursor = 0;
+                self.task_cursor = 0;
+                self.query_cursor = 0;
+                report
+            }
+        };
+        sort_events(&mut self.events);
+        sort_tasks(&mut self.tasks);
+        self.save_dirty();
+        self.needs_redraw = true;
+        Ok(report)
+    }
+
+    /// `export_backup`, encrypted under `passphrase`; see `seal.rs`. The
+    /// salt and nonce must come fresh from the TRNG.
+    pub fn export_backup_sealed(
+        &self,
+        passphrase: &str,
+        salt: [u8; SALT_LEN],
+        nonce: [u8; NONCE_LEN],
+    ) -> Result<Vec<u8>, SealError> {
+        seal::seal(self.export_backup().as_bytes(), passphrase, KdfParams::default(), salt, nonce)
+    }
+
+    /// Decrypt a sealed backup and import it. Wrong passphrases and
+    /// altered files are refused before anything changes.
+    pub fn import_backup_sealed(
+        &mut self,
+        data: &[u8],
+        passphrase: &str,
+        mode: ImportMode,
+    ) -> Result<ImportReport, BackupError> {
+        let plain = seal::open(data, passphrase).map_err(BackupError::Sealed)?;
+        let json = core::str::from_utf8(&plain).map_err(|_| BackupError::Parse(String::from("not UTF-8")))?;
+        self.import_backup(json, mode)
+    }
+
+    /// Dry run of a CSV event import; see `csv::read_events`.
+    pub fn preview_event_csv(&self, input: &str) -> Result<csv::Report<Event>, CsvError> {
+        csv::read_events(input, &self.events)
+    }
+
+    /// Dry run of a CSV task import; see `csv::read_tasks`.
+    pub fn preview_task_csv(&self, input: &str) -> Result<csv::Report<Task>, CsvError> {
+        csv::read_tasks(input, &self.tasks)
+    }
+
+    /// Match imported items against the planner's and open the review
+    /// screen; see `dedup.rs`. Nothing changes until `apply_review`.
+    pub fn review_import(&mut self, events: Vec<Event>, tasks: Vec<Task>) {
+        self.event_review = Review::classify(events, &self.events);
+        self.task_review = Review::classify(tasks, &self.tasks);
+        self.review_cursor = 0;
+        self.review_field = None;
+        self.review_file = None;
+        self.review_status = None;
+        self.state = AppState::ImportReview;
+        self.needs_redraw = true;
+    }
+
+    /// Review an iCalendar file's events and tasks. Returns what couldn't
+    /// be represented, for the caller to show.
+    pub fn review_ics(&mut self, input: &str) -> Result<Vec<Issue>, IcalError> {
+        // IDs are assigned when the review is applied.
+        let imported = ical::import(input, &mut 1)?;
+        self.review_import(imported.events, imported.tasks);
+        Ok(imported.issues)
+    }
+
+    /// Review a CSV file of events. Rows that couldn't be read are in the
+    /// returned report; the accepted ones go to the review.
+    pub fn review_event_csv(&mut self, input: &str) -> Result<csv::Report<Event>, CsvError> {
+        let mut report = csv::read_events(input, &[])?;
+        self.review_import(core::mem::take(&mut report.accepted), Vec::new());
+        Ok(report)
+    }
+
+    pub fn review_task_csv(&mut self, input: &str) -> Result<csv::Report<Task>, CsvError> {
+        let mut report = csv::read_tasks(input, &[])?;
+        self.review_import(Vec::new(), core::mem::take(&mut report.accepted));
+        Ok(report)
+    }
+
+    /// Review the first file in the import inbox, by name: `.ics` files
+    /// as iCalendar, `.csv` as events if they have a date column and as
+    /// tasks if not. Whatever goes wrong is left in `review_status`, on an
+    /// empty review. Applying the review removes the file, even one that
+    /// couldn't be read, so it doesn't hold up the ones after it.
+    pub fn review_inbox(&mut self) {
+        let name = match self.first_inbox_name() {
+            Ok(name) => name,
+            Err(status) => {
+                self.review_import(Vec::new(), Vec::new());
+                self.review_status = Some(status);
+                return;
+            }
+        };
+        let reviewed = match self.read_inbox(&name) {
+            Ok(text) => self.review_named(&name, &text),
+            Err(e) => Err(e),
+        };
+        let status = reviewed.unwrap_or_else(|e| {
+            self.review_import(Vec::new(), Vec::new());
+            e
+        });
+        self.review_file = Some(name.clone());
+        self.review_status = Some(format!("{}: {}", name, status));
+    }
+
+    fn first_inbox_name(&mut self) -> Result<String, String> {
+        let inbox = self.inbox.as_mut().ok_or_else(|| String::from("No import inbox"))?;
+        let names = inbox.list().map_err(|_| String::from("Couldn't list the import inbox"))?;
+        names.into_iter().min().ok_or_else(|| String::from("Import inbox is empty"))
+    }
+
+    fn read_inbox(&mut self, name: &str) -> Result<String, String> {
+        let Some(inbox) = self.inbox.as_mut() else { return Err(String::from("no import inbox")) };
+        match inbox.read(name) {
+            Ok(Some(data)) => String::from_utf8(data).map_err(|_| String::from("not UTF-8 text")),
+            Ok(None) => Err(String::from("empty file")),
+            Err(_) => Err(String::from("couldn't read it")),
+        }
+    }
+
+    /// Open the review for a file's contents; `Ok` holds a note on rows or
+    /// components left out.
+    fn review_named(&mut self, name: &str, text: &str) -> Result<String, String> {
+        let lower = name.to_lowercase();
+        if lower.ends_with(".ics") {
+            let issues = self.review_ics(text).map_err(|e| format!("{}", e))?;
+            Ok(format!("{} issues", issues.len()))
+        } else if lower.ends_with(".csv") {
+            let rejected = match self.review_event_csv(text) {
+                Ok(report) => report.rejected.len(),
+                Err(CsvError::MissingColumn("date")) => {
+                    self.review_task_csv(text).map_err(|e| format!("{}", e))?.rejected.len()
+                }
+                Err(e) => return Err(format!("{}", e)),
+            };
+            Ok(format!("{} rows rejected", rejected))
+        } else {
+            Err(String::from("not a .ics or .csv file"))
+        }
+    }
+
+    /// Carry out the review's choices. Returns how many items were added
+    /// and how many existing ones updated.
+    pub fn apply_review(&mut self) -> (usize, usize) {
+        let events = core::mem::take(&mut self.event_review).resolve(&self.events);
+        let tasks = core::mem::take(&mut self.task_review).resolve(&self.tasks);
+        let added = events.added.len() + tasks.added.len();
+        let updated = events.updated.len() + tasks.updated.len();
+        for ev in events.updated {
+            self.edit_event(ev.id, |mine| *mine = ev);
+        }
+        for task in tasks.updated {
+            self.edit_task(task.id, |mine| *mine = task);
+        }
+        self.commit_events(events.added);
+        self.commit_tasks(tasks.added);
+        self.review_cursor = 0;
+        self.review_field = None;
+        log::info!("import review: {} added, {} updated", added, updated);
+        (added, updated)
+    }
+
+    /// Add the accepted rows of a previewed import, with fresh IDs, to the
+    /// calendar new events go to.
+    pub fn commit_events(&mut self, events: Vec<Event>) {
+        let cal = self.new_event_calendar();
+        for mut ev in events {
+            ev.calendar = cal;
+            self.add_event(ev);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn commit_tasks(&mut self, tasks: Vec<Task>) {
+        for task in tasks {
+            self.add_task(task);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Add an event with a fresh ID, and a uid if it has none, and queue
+    /// it for saving. Every way of adding an event comes through here.
+    fn add_event(&mut self, mut event: Event) -> u32 {
+        let id = self.alloc_id();
+        event.id = id;
+        if event.uid.is_nil() {
+            event.uid = self.new_uid();
+        }
+        self.events.push(event);
+        self.mark_event(id);
+        self.note_event(id, ChangeKind::Added);
+        self.needs_redraw = true;
+        id
+    }
+
+    /// Change event `id` in place and queue it for saving, moving it
+    /// between calendar stores if its calendar changed. Returns false if
+    /// there's no such event.
+    fn edit_event(&mut self, id: u32, change: impl FnOnce(&mut Event)) -> bool {
+        let Some(ev) = self.events.iter_mut().find(|e| e.id == id) else { return false };
+        let before = ev.calendar;
+        change(ev);
+        let after = ev.calendar;
+        // save_dirty writes the new copy before removing the old one,
+        // so a failure in between duplicates the event rather than
+        // losing it.
+        if after != before {
+            self.mark_event_deleted(id, before);
+        }
+        self.mark_event(id);
+        // To other apps, moving into a secret calendar is a removal and
+        // moving out of one an addition.
+        match (self.is_basis_calendar(before), self.is_basis_calendar(after)) {
+            (false, false) => self.note_event(id, ChangeKind::Changed),
+            (false, true) => self.note_uid(ChangeKind::Removed, before, self.event_uid(id)),
+            (true, false) => self.note_event(id, ChangeKind::Added),
+            (true, true) => {}
+        }
+        self.needs_redraw = true;
+        true
+    }
+
+    /// Delete event `id`, stored in calendar `cal`.
+    fn remove_event(&mut self, id: u32, cal: CalendarId) {
+        let uid = self.event_uid(id);
+        self.events.retain(|e| e.id != id);
+        self.mark_event_deleted(id, cal);
+        self.note_uid(ChangeKind::Removed, cal, uid);
+        self.needs_redraw = true;
+    }
+
+    /// Like `add_event`, keeping the task list sorted.
+    fn add_task(&mut self, mut task: Task) -> u32 {
+        let id = self.alloc_id();
+        task.id = id;
+        if task.uid.is_nil() {
+            task.uid = self.new_uid();
+        }
+        self.tasks.push(task);
+        sort_tasks(&mut self.tasks);
+        self.mark_task(id);
+        self.note_task(id, ChangeKind::Added);
+        self.needs_redraw = true;
+        id
+    }
+
+    fn edit_task(&mut self, id: u32, change: impl FnOnce(&mut Task)) -> bool {
+        let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) else { return false };
+        change(task);
+        ical::note_revision(&mut task.extra);
+        sort_tasks(&mut self.tasks);
+        self.mark_task(id);
+        self.note_task(id, ChangeKind::Changed);
+        self.needs_redraw = true;
+        true
+    }
+
+    fn remove_task(&mut self, id: u32) {
+        self.note_task(id, ChangeKind::Removed);
+        self.tasks.retain(|t| t.id != id);
+        self.mark_task_deleted(id);
+        self.needs_redraw = true;
+    }
+
+    /// Changes since the last call, for other apps; see `Changes`.
+    pub fn take_changes(&mut self) -> Changes {
+        core::mem::take(&mut self.changes)
+    }
+
+    fn event_uid(&self, id: u32) -> Uid {
+        self.events.iter().find(|e| e.id == id).map_or(Uid::NIL, |e| e.uid)
+    }
+
+    fn note_event(&mut self, id: u32, kind: ChangeKind) {
+        let Some(cal) = self.events.iter().find(|e| e.id == id).map(|e| e.calendar) else { return };
+        self.note_uid(kind, cal, self.event_uid(id));
+    }
+
+    /// Note a change to the event `uid` in calendar `cal`, unless it's secret.
+    fn note_uid(&mut self, kind: ChangeKind, cal: CalendarId, uid: Uid) {
+        if !uid.is_nil() && !self.is_basis_calendar(cal) && !self.changes.reset {
+            Changes::note(&mut self.changes.events, uid, kind);
+        }
+    }
+
+    fn note_task(&mut self, id: u32, kind: ChangeKind) {
+        let Some(uid) = self.tasks.iter().find(|t| t.id == id).map(|t| t.uid) else { return };
+        if !uid.is_nil() && !self.changes.reset {
+            Changes::note(&mut self.changes.tasks, uid, kind);
+        }
+    }
+
+    /// Too much changed to list; subscribers should look again.
+    fn note_reset(&mut self) {
+        self.changes = Changes { reset: true, ..Changes::default() };
+    }
+
+    /// Add an event for another app. Checked as the event form would:
+    /// a title that fits, a real date and time, and a calendar that exists
+    /// and isn't secret. `None` picks the calendar the form starts on.
+    pub fn create_event(
+        &mut self,
+        date: Date,
+        time: Option<Time>,
+        title: &str,
+        priority: Priority,
+        calendar: Option<CalendarId>,
+    ) -> Result<Uid, EditError> {
+        self.check_writable()?;
+        let title = check_title(title, EVENT_TITLE_MAX)?;
+        check_when(Some(date), time)?;
+        let calendar = match calendar {
+            Some(id) => self.check_calendar(id)?,
+            None => self.new_event_calendar(),
+        };
+        let mut event = Event::new(0, date, title);
+        event.time = time;
+        event.priority = priority;
+        event.calendar = calendar;
+        let uid = self.new_uid();
+        event.uid = uid;
+        self.add_event(event);
+        Ok(uid)
+    }
+
+    /// Change an event for another app. Fields left `None` are kept; the
+    /// rest are checked as in `create_event`, all before anything changes.
+    pub fn update_event(&mut self, uid: Uid, changes: EventChanges) -> Result<(), EditError> {
+        self.check_writable()?;
+        let id = self.shareable_events().find(|e| e.uid == uid).map(|e| e.id).ok_or(EditError::NotFound)?;
+        let title = changes.title.as_deref().map(|t| check_title(t, EVENT_TITLE_MAX)).transpose()?;
+        check_when(changes.date, changes.time.flatten())?;
+        let calendar = changes.calendar.map(|c| self.check_calendar(c)).transpose()?;
+        self.edit_event(id, |ev| {
+            if let Some(title) = title {
+                ev.title = title;
+            }
+            if let Some(date) = changes.date {
+                ev.date = date;
+            }
+            if let Some(time) = changes.time {
+                ev.time = time;
+            }
+            if let Some(priority) = changes.priority {
+                ev.priority = priority;
+            }
+            if let Some(calendar) = calendar {
+                ev.calendar = calendar;
+            }
+        });
+        Ok(())
+    }
+
+    /// Add a task for another app, checked as the task entry would.
+    pub fn create_task(&mut self, title: &str, priority: Priority) -> Result<Uid, EditError> {
+        self.check_writable()?;
+        let mut task = Task::new(0, check_title(title, TASK_TITLE_MAX)?);
+        task.priority = priority;
+        let uid = self.new_uid();
+        task.uid = uid;
+        self.add_task(task);
+        Ok(uid)
+    }
+
+    /// Change a task for another app. Fields left `None` are kept.
+    pub fn update_task(&mut self, uid: Uid, changes: TaskChanges) -> Result<(), EditError> {
+        self.check_writable()?;
+        let id = self.tasks.iter().find(|t| t.uid == uid).map(|t| t.id).ok_or(EditError::NotFound)?;
+        let title = changes.title.as_deref().map(|t| check_title(t, TASK_TITLE_MAX)).transpose()?;
+        self.edit_task(id, |task| {
+            if let Some(title) = title {
+                task.title = title;
+            }
+            if let Some(priority) = changes.priority {
+                task.priority = priority;
+            }
+            if let Some(done) = changes.done {
+                task.done = done;
+            }
+        });
+        Ok(())
+    }
+
+    /// Mark a task done. Already done is fine.
+    pub fn complete_task(&mut self, uid: Uid) -> Result<(), EditError> {
+        self.update_task(uid, TaskChanges { done: Some(true), ..TaskChanges::default() })
+    }
+
+    /// Edits are refused while stored data couldn't be loaded: they'd be
+    /// lost, or worse, saved over what's there.
+    fn check_writable(&self) -> Result<(), EditError> {
+        if self.storage_error.is_some() {
+            return Err(EditError::Unavailable);
+        }
+        Ok(())
+    }
+
+    fn check_calendar(&self, id: CalendarId) -> Result<CalendarId, EditError> {
+        match self.calendar(id) {
+            Some(c) if c.basis.is_none() => Ok(id),
+            _ => Err(EditError::NoSuchCalendar),
+        }
+    }
+
+    fn new_uid(&mut self) -> Uid {
+        self.uids.generate()
+    }
+
+    /// Give every item loaded without a uid (data from before schema v3)
+    /// its own, and write them out straight away.
+    fn assign_missing_uids(&mut self) {
+        let mut events = Vec::new();
+        for ev in self.events.iter_mut().filter(|e| e.uid.is_nil()) {
+            ev.uid = self.uids.generate();
+            events.push(ev.id);
+        }
+        let mut tasks = Vec::new();
+        for task in self.tasks.iter_mut().filter(|t| t.uid.is_nil()) {
+            task.uid = self.uids.generate();
+            tasks.push(task.id);
+        }
+        if events.is_empty() && tasks.is_empty() {
+            return;
+        }
+        log::info!("assigned uids to {} events and {} tasks", events.len(), tasks.len());
+        for id in events {
+            self.mark_event(id);
+        }
+        for id in tasks {
+            self.mark_task(id);
+        }
+        self.save_dirty();
+    }
+
+    /// Hand out a fresh ID. The counter is saved no later than whatever
+    /// uses the ID (see `save_dirty`), so a crash can skip an ID but never
+    /// reuse one.
+    fn alloc_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let pending = core::mem::replace(&mut self.dirty.next_id, true);
+        self.note_change(pending);
+        id
+    }
+
+    /// Events for the currently selected date, sorted by time.
+    pub fn events_for_date(&self) -> Vec<&Event> {
+        let mut day_events: Vec<&Event> = self
+            .events
+            .iter()
+            .filter(|e| e.date == self.current_date && self.calendar_visible(e.calendar))
+            .collect();
+        day_events.sort_by(|a, b| {
+            let ta = a.time.map(|t| (t.hour as u16) * 60 + t.minute as u16).unwrap_or(0);
+            let tb = b.time.map(|t| (t.hour as u16) * 60 + t.minute as u16).unwrap_or(0);
+            let ha = a.time.is_some() as u8;
+            let hb = b.time.is_some() as u8;
+            ha.cmp(&hb).then(ta.cmp(&tb))
+        });
+        day_events
+    }
+
+    /// Count events for a given date (for month view dots).
+    pub fn event_count_for(&self, date: Date) -> usize {
+        self.events
+            .iter()
+            .filter(|e| e.date == date && self.calendar_visible(e.calendar))
+            .count()
+    }
+
+    pub fn calendar(&self, id: CalendarId) -> Option<&Calendar> {
+        self.calendars.iter().find(|c| c.id == id)
+    }
+
+    /// Unknown calendars count as visible so their events can't go missing.
+    pub fn calendar_visible(&self, id: CalendarId) -> bool {
+        self.calendar(id).is_none_or(|c| c.visible)
+    }
+
+    pub fn calendar_marker(&self, id: CalendarId) -> char {
+        self.calendar(id).map_or('?', |c| c.marker)
+    }
+
+    /// Markers of the visible calendars with events on `date`, in registry
+    /// order (for the month view).
+    pub fn calendar_markers_for(&self, date: Date) -> String {
+        self.calendars
+            .iter()
+            .filter(|c| c.visible)
+            .filter(|c| self.events.iter().any(|e| e.date == date && e.calendar == c.id))
+            .map(|c| c.marker)
+            .collect()
+    }
+
+    /// Where new events go: the default calendar, or the first visible one
+    /// if that's hidden.
+    fn new_event_calendar(&self) -> CalendarId {
+        self.calendars
+            .iter()
+            .find(|c| c.visible && c.id == DEFAULT_CALENDAR)
+            .or_else(|| self.calendars.iter().find(|c| c.visible))
+            .map_or(DEFAULT_CALENDAR, |c| c.id)
+    }
+
+    /// The calendar after (or before) `id` in the registry, for the picker.
+    fn cycle_calendar(&self, id: CalendarId, forward: bool) -> CalendarId {
+        let n = self.calendars.len();
+        if n == 0 {
+            return id;
+        }
+        let pos = self.calendars.iter().position(|c| c.id == id).unwrap_or(0);
+        let next = if forward { (pos + 1) % n } else { (pos + n - 1) % n };
+        self.calendars[next].id
+    }
+
+    /// Count incomplete tasks.
+    pub fn pending_task_count(&self) -> usize {
+        self.tasks.iter().filter(|t| !t.done).count()
+    }
+
+    /// Live results for the selected saved query.
+    pub fn query_hits(&self) -> Result<Vec<Hit<'_>>, QueryError> {
+        let text = self
+            .queries
+            .get(self.query_cursor)
+            .map(|q| q.text.as_str())
+            .unwrap_or("");
+        let q = Query::parse(text)?;
+        Ok(query::run(&q, &self.events, &self.tasks, self.today))
+    }
+
+    /// handle_key returns true to keep running, false to quit.
+    pub fn handle_key(&mut self, key: char) -> bool {
+        self.needs_redraw = true;
+        let keep_running = self.dispatch_key(key);
+        // A basis can be locked at any moment; don't leave its edits waiting.
+        if self.basis_dirty() {
+            self.save_dirty();
+        }
+        keep_running
+    }
+
+    fn dispatch_key(&mut self, key: char) -> bool {
+        match self.state {
+            AppState::DayView => self.handle_day_view(key),
+            AppState::TaskList => self.handle_task_list(key),
+            AppState::AddEvent => self.handle_add_event(key),
+            AppState::EditEvent => self.handle_edit_event(key),
+            AppState::AddTask => self.handle_add_task(key),
+            AppState::ConfirmDel => self.handle_confirm_del(key),
+            AppState::MonthView => self.handle_month_view(key),
+            AppState::QueryList => self.handle_query_list(key),
+            AppState::QueryResults => self.handle_query_results(key),
+            AppState::AddQuery => self.handle_add_query(key),
+            AppState::Recovery => self.handle_recovery(key),
+            AppState::Snapshots => self.handle_snapshots(key),
+            AppState::Calendars => self.handle_calendars(key),
+            AppState::ImportReview => self.handle_import_review(key),
+        }
+    }
+
+    // Key handlers keep one arm per key, with the conditions inside; a
+    // guard that failed would fall through to the catch-all arms instead.
+    #[allow(clippy::collapsible_match)]
+    fn handle_day_view(&mut self, key: char) -> bool {
+        let count = self.events_for_date().len();
+        match key {
+            KEY_MENU => return false,
+            KEY_UP => {
+                if count > 0 && self.day_cursor > 0 {
+                    self.day_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.day_cursor < count - 1 {
+                    self.day_cursor += 1;
+                }
+            }
+            KEY_LEFT => {
+                self.current_date = self.current_date.prev_day();
+                self.day_cursor = 0;
+            }
+            KEY_RIGHT => {
+                self.current_date = self.current_date.next_day();
+                self.day_cursor = 0;
+            }
+            'a' | 'A' => {
+                // Add event
+                self.form_title.clear();
+                self.form_hour = 9;
+                self.form_minute = 0;
+                self.form_has_time = true;
+                self.form_priority = Priority::Normal;
+                self.form_calendar = self.new_event_calendar();
+                self.form_field = EventField::Title;
+                self.editing_event_id = None;
+                self.state = AppState::AddEvent;
+            }
+            'e' | 'E' => {
+                // Edit selected event
+                let selected = self.events_for_date().get(self.day_cursor).map(|&ev| ev.clone());
+                if let Some(ev) = selected {
+                    self.form_title = ev.title;
+                    self.form_hour = ev.time.map(|t| t.hour).unwrap_or(9);
+                    self.form_minute = ev.time.map(|t| t.minute).unwrap_or(0);
+                    self.form_has_time = ev.time.is_some();
+                    self.form_priority = ev.priority;
+                    self.form_calendar = ev.calendar;
+                    self.form_field = EventField::Title;
+                    self.editing_event_id = Some(ev.id);
+                    self.state = AppState::EditEvent;
+                }
+            }
+            'd' | 'D' => {
+                // Delete selected event
+                let day_events = self.events_for_date();
+                if let Some(ev) = day_events.get(self.day_cursor) {
+                    self.delete_target = Some(DeleteTarget::Event(ev.id, ev.calendar));
+                    self.state = AppState::ConfirmDel;
+                }
+            }
+            't' | 'T' => {
+                self.task_cursor = 0;
+                self.state = AppState::TaskList;
+            }
+            'm' | 'M' => {
+                self.month_view_year = self.current_date.year;
+                self.month_view_month = self.current_date.month;
+                self.month_cursor_day = self.current_date.day;
+                self.state = AppState::MonthView;
+            }
+            'f' | 'F' => {
+                self.state = AppState::QueryList;
+            }
+            'c' | 'C' => {
+                self.calendar_cursor = 0;
+                self.state = AppState::Calendars;
+            }
+            'i' | 'I' => self.review_inbox(),
+            's' | 'S' => {
+                self.snapshot_status = None;
+                self.snapshot_confirm_restore = false;
+                self.refresh_snapshots();
+                self.state = AppState::Snapshots;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_task_list(&mut self, key: char) -> bool {
+        let count = self.tasks.len();
+        match key {
+            KEY_MENU | KEY_LEFT => {
+                self.state = AppState::DayView;
+            }
+            KEY_UP => {
+                if count > 0 && self.task_cursor > 0 {
+                    self.task_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.task_cursor < count - 1 {
+                    self.task_cursor += 1;
+                }
+            }
+            KEY_ENTER => {
+                // Toggle done
+                if self.task_cursor < self.tasks.len() {
+                    let id = self.tasks[self.task_cursor].id;
+                    self.edit_task(id, |t| t.done = !t.done);
+                }
+            }
+            'a' | 'A' => {
+                self.task_input.clear();
+                self.state = AppState::AddTask;
+            }
+            'p' | 'P' => {
+                // Cycle priority of selected task
+                if self.task_cursor < self.tasks.len() {
+                    let id = self.tasks[self.task_cursor].id;
+                    self.edit_task(id, |t| t.priority = t.priority.cycle());
+                }
+            }
+            'd' | 'D' => {
+                if self.task_cursor < self.tasks.len() {
+                    self.delete_target =
+                        Some(DeleteTarget::Task(self.tasks[self.task_cursor].id));
+                    self.state = AppState::ConfirmDel;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_event_form(&mut self, key: char) -> bool {
+        match self.form_field {
+            EventField::Title => match key {
+                KEY_MENU => {
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_BACKSPACE => {
+                    self.form_title.pop();
+                }
+                KEY_DOWN => {
+                    self.form_field = EventField::Hour;
+                }
+                KEY_ENTER => {
+                    // Submit handled by caller
+                    return false; // signal submit
+                }
+                c if (' '..='~').contains(&c) => {
+                    if self.form_title.len() < EVENT_TITLE_MAX {
+                        self.form_title.push(c);
+                    }
+                }
+                _ => {}
+            },
+            EventField::Hour => match key {
+                KEY_MENU => {
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_UP => {
+                    self.form_field = EventField::Title;
+                }
+                KEY_DOWN => {
+                    self.form_field = EventField::Minute;
+                }
+                KEY_LEFT => {
+                    if self.form_hour > 0 {
+                        self.form_hour -= 1;
+                    } else {
+                        self.form_hour = 23;
+                    }
+                }
+                KEY_RIGHT => {
+                    if self.form_hour < 23 {
+                        self.form_hour += 1;
+                    } else {
+                        self.form_hour = 0;
+                    }
+                }
+                ' ' => {
+                    self.form_has_time = !self.form_has_time;
+                }
+                KEY_ENTER => return false,
+                _ => {}
+            },
+            EventField::Minute => match key {
+                KEY_MENU => {
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_UP => {
+                    self.form_field = EventField::Hour;
+                }
+                KEY_DOWN => {
+                    self.form_field = EventField::Priority;
+                }
+                KEY_LEFT => {
+                    if self.form_minute >= 5 {
+                        self.form_minute -= 5;
+                    } else {
+                        self.form_minute = 55;
+                    }
+                }
+                KEY_RIGHT => {
+                    if self.form_minute < 55 {
+                        self.form_minute += 5;
+                    } else {
+                        self.form_minute = 0;
+                    }
+                }
+                KEY_ENTER => return false,
+                _ => {}
+            },
+            EventField::Priority => match key {
+                KEY_MENU => {
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_UP => {
+                    self.form_field = EventField::Minute;
+                }
+                KEY_DOWN => {
+                    self.form_field = EventField::Calendar;
+                }
+                KEY_LEFT | KEY_RIGHT | ' ' => {
+                    self.form_priority = self.form_priority.cycle();
+                }
+                KEY_ENTER => return false,
+                _ => {}
+            },
+            EventField::Calendar => match key {
+                KEY_MENU => {
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_UP => {
+                    self.form_field = EventField::Priority;
+                }
+                KEY_LEFT => {
+                    self.form_calendar = self.cycle_calendar(self.form_calendar, false);
+                }
+                KEY_RIGHT | ' ' => {
+                    self.form_calendar = self.cycle_calendar(self.form_calendar, true);
+                }
+                KEY_ENTER => return false,
+                _ => {}
+            },
+        }
+        true
+    }
+
+    fn handle_add_event(&mut self, key: char) -> bool {
+        let still_editing = self.handle_event_form(key);
+        if !still_editing {
+            // Submit
+            if !self.form_title.is_empty() {
+                let mut event = Event::new(0, self.current_date, self.form_title.clone());
+                if self.form_has_time {
+                    event.time = Some(Time::new(self.form_hour, self.form_minute));
+                }
+                event.priority = self.form_priority;
+                event.calendar = self.form_calendar;
+                self.add_event(event);
+            }
+            self.state = AppState::DayView;
+        }
+        true
+    }
+
+    fn handle_edit_event(&mut self, key: char) -> bool {
+        let still_editing = self.handle_event_form(key);
+        if !still_editing {
+            // Apply edits
+            if let Some(eid) = self.editing_event_id {
+                let title = (!self.form_title.is_empty()).then(|| self.form_title.clone());
+                let time = self.form_has_time.then(|| Time::new(self.form_hour, self.form_minute));
+                let (priority, calendar) = (self.form_priority, self.form_calendar);
+                self.edit_event(eid, |ev| {
+                    if let Some(title) = title {
+                        ev.title = title;
+                    }
+                    ev.time = time;
+                    ev.priority = priority;
+                    ev.calendar = calendar;
+                });
+            }
+            self.state = AppState::DayView;
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_add_task(&mut self, key: char) -> bool {
+        match key {
+            KEY_MENU => {
+                self.state = AppState::TaskList;
+            }
+            KEY_BACKSPACE => {
+                self.task_input.pop();
+            }
+            KEY_ENTER => {
+                if !self.task_input.is_empty() {
+                    self.add_task(Task::new(0, self.task_input.clone()));
+                }
+                self.state = AppState::TaskList;
+            }
+            c if (' '..='~').contains(&c) => {
+                if self.task_input.len() < TASK_TITLE_MAX {
+                    self.task_input.push(c);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_confirm_del(&mut self, key: char) -> bool {
+        match key {
+            'y' | 'Y' | KEY_ENTER => {
+                if let Some(target) = self.delete_target.take() {
+                    match target {
+                        DeleteTarget::Event(id, cal) => {
+                            self.remove_event(id, cal);
+                            self.day_cursor = 0;
+                            self.state = AppState::DayView;
+                        }
+                        DeleteTarget::Task(id) => {
+                            self.remove_task(id);
+                            if self.task_cursor > 0
+                                && self.task_cursor >= self.tasks.len()
+                            {
+                                self.task_cursor = self.tasks.len().saturating_sub(1);
+                            }
+                            self.state = AppState::TaskList;
+                        }
+                        DeleteTarget::Query(idx) => {
+                            if idx < self.queries.len() {
+                                self.queries.remove(idx);
+                            }
+                            self.query_cursor = self.query_cursor.min(self.queries.len().saturating_sub(1));
+                            self.mark_queries();
+                            self.state = AppState::QueryList;
+                        }
+                        DeleteTarget::Snapshot(idx) => {
+                            self.delete_snapshot(idx);
+                            self.refresh_snapshots();
+                            self.state = AppState::Snapshots;
+                        }
+                    }
+                }
+            }
+            _ => {
+                // Any other key = cancel
+                self.delete_target = None;
+                self.state = AppState::DayView;
+            }
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_month_view(&mut self, key: char) -> bool {
+        match key {
+            KEY_MENU | KEY_ENTER => {
+                // Select this date and go to day view
+                let dim = Date::days_in_month(self.month_view_year, self.month_view_month);
+                if self.month_cursor_day >= 1 && self.month_cursor_day <= dim {
+                    self.current_date = Date::new(
+                        self.month_view_year,
+                        self.month_view_month,
+                        self.month_cursor_day,
+                    );
+                    self.day_cursor = 0;
+                }
+                self.state = AppState::DayView;
+            }
+            KEY_LEFT => {
+                if self.month_cursor_day > 1 {
+                    self.month_cursor_day -= 1;
+                }
+            }
+            KEY_RIGHT => {
+                let dim = Date::days_in_month(self.month_view_year, self.month_view_month);
+                if self.month_cursor_day < dim {
+                    self.month_cursor_day += 1;
+                }
+            }
+            KEY_UP => {
+                if self.month_cursor_day > 7 {
+                    self.month_cursor_day -= 7;
+                } else {
+                    self.month_cursor_day = 1;
+                }
+            }
+            KEY_DOWN => {
+                let dim = Date::days_in_month(self.month_view_year, self.month_view_month);
+                if self.month_cursor_day + 7 <= dim {
+                    self.month_cursor_day += 7;
+                } else {
+                    self.month_cursor_day = dim;
+                }
+            }
+            '[' => {
+                // Previous month
+                if self.month_view_month > 1 {
+                    self.month_view_month -= 1;
+                } else {
+                    self.month_view_month = 12;
+                    self.month_view_year -= 1;
+                }
+                let dim = Date::days_in_month(self.month_view_year, self.month_view_month);
+                if self.month_cursor_day > dim {
+                    self.month_cursor_day = dim;
+                }
+            }
+            ']' => {
+                // Next month
+                if self.month_view_month < 12 {
+                    self.month_view_month += 1;
+                } else {
+                    self.month_view_month = 1;
+                    self.month_view_year += 1;
+                }
+                let dim = Date::days_in_month(self.month_view_year, self.month_view_month);
+                if self.month_cursor_day > dim {
+                    self.month_cursor_day = dim;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_query_list(&mut self, key: char) -> bool {
+        let count = self.queries.len();
+        match key {
+            KEY_MENU | KEY_LEFT => {
+                self.state = AppState::DayView;
+            }
+            KEY_UP => {
+                if count > 0 && self.query_cursor > 0 {
+                    self.query_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.query_cursor < count - 1 {
+                    self.query_cursor += 1;
+                }
+            }
+            KEY_ENTER | KEY_RIGHT => {
+                if self.query_cursor < count {
+                    self.query_result_cursor = 0;
+                    self.state = AppState::QueryResults;
+                }
+            }
+            'a' | 'A' => {
+                self.query_name_input.clear();
+                self.query_text_input.clear();
+                self.query_field = QueryField::Name;
+                self.query_error = None;
+                self.state = AppState::AddQuery;
+            }
+            'd' | 'D' => {
+                if self.query_cursor < count {
+                    self.delete_target = Some(DeleteTarget::Query(self.query_cursor));
+                    self.state = AppState::ConfirmDel;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_query_results(&mut self, key: char) -> bool {
+        let hits: Vec<Hit> = self.query_hits().unwrap_or_default();
+        let count = hits.len();
+        match key {
+            KEY_MENU | KEY_LEFT => {
+                self.state = AppState::QueryList;
+            }
+            KEY_UP => {
+                if count > 0 && self.query_result_cursor > 0 {
+                    self.query_result_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.query_result_cursor < count - 1 {
+                    self.query_result_cursor += 1;
+                }
+            }
+            KEY_ENTER => match hits.get(self.query_result_cursor).copied() {
+                Some(Hit::Event(ev)) => {
+                    // Jump to the event's day
+                    self.current_date = ev.date;
+                    self.day_cursor = 0;
+                    self.state = AppState::DayView;
+                }
+                Some(Hit::Task(t)) => {
+                    let id = t.id;
+                    self.edit_task(id, |t| t.done = !t.done);
+                    let remaining = self.query_hits().map(|h| h.len()).unwrap_or(0);
+                    if self.query_result_cursor >= remaining {
+                        self.query_result_cursor = remaining.saturating_sub(1);
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_add_query(&mut self, key: char) -> bool {
+        let input = match self.query_field {
+            QueryField::Name => &mut self.query_name_input,
+            QueryField::Text => &mut self.query_text_input,
+        };
+        match key {
+            KEY_MENU => {
+                self.state = AppState::QueryList;
+            }
+            KEY_UP => {
+                self.query_field = QueryField::Name;
+            }
+            KEY_DOWN => {
+                self.query_field = QueryField::Text;
+            }
+            KEY_BACKSPACE => {
+                input.pop();
+            }
+            KEY_ENTER => {
+                if self.query_name_input.is_empty() {
+                    self.query_field = QueryField::Name;
+                    return true;
+                }
+                match Query::parse(&self.query_text_input) {
+                    Ok(_) => {
+                        self.queries.push(SavedQuery::new(
+                            self.query_name_input.clone(),
+                            self.query_text_input.clone(),
+                        ));
+                        self.query_cursor = self.queries.len() - 1;
+                        self.query_error = None;
+                        self.mark_queries();
+                        self.state = AppState::QueryList;
+                    }
+                    Err(e) => {
+                        self.query_error = Some(format!("{}", e));
+                        self.query_field = QueryField::Text;
+                    }
+                }
+            }
+            c if (' '..='~').contains(&c) => {
+                let max = if self.query_field == QueryField::Name { 24 } else { 60 };
+                if input.len() < max {
+                    input.push(c);
+                }
+                self.query_error = None;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Dump the quarantined bytes to the log, where they can be captured
+    /// over the debug console. They also remain under `recovery.<key>`.
+    fn export_recovery(&mut self) {
+        let key = match self.storage_error {
+            Some(StorageError::Corrupt(ref key)) => key.clone(),
+            _ => {
+                self.recovery_status = Some(String::from("Nothing quarantined to export"));
+                return;
+            }
+        };
+        let cal = self.recovery_calendar;
+        let blob = match self.calendar_storage(cal).map(|st| st.recovery_blob(&key)) {
+            Some(Ok(Some(blob))) => blob,
+            Some(Err(e)) => {
+                self.recovery_status = Some(format!("Export failed: {}", e));
+                return;
+            }
+            _ => {
+                self.recovery_status = Some(String::from("No recovery copy found"));
+                return;
+            }
+        };
+        log::info!("BEGIN recovery.{} ({} bytes)", key, blob.len());
+        for chunk in blob.chunks(64) {
+            let hex: String = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            log::info!("{}", hex);
+        }
+        log::info!("END recovery.{}", key);
+        self.recovery_status = Some(format!("Logged {} bytes of '{}' as hex", blob.len(), key));
+    }
+
+    fn handle_recovery(&mut self, key: char) -> bool {
+        if key != 'f' && key != 'F' {
+            self.recovery_confirm_fresh = false;
+        }
+        match key {
+            KEY_MENU => return false,
+            'r' | 'R' => {
+                self.reload_storage();
+                if self.state == AppState::Recovery {
+                    self.recovery_status = Some(String::from("Still unreadable"));
+                }
+            }
+            'x' | 'X' => self.export_recovery(),
+            'f' | 'F' => {
+                if !self.recovery_confirm_fresh {
+                    self.recovery_confirm_fresh = true;
+                    return true;
+                }
+                self.recovery_confirm_fresh = false;
+                let Some(err) = self.storage_error.clone() else { return true };
+                let cal = self.recovery_calendar;
+                let result = match self.calendar_storage(cal) {
+                    Some(st) => st.start_fresh(&err),
+                    None => Ok(()),
+                };
+                match result {
+                    Ok(()) => self.reload_storage(),
+                    Err(e) => self.recovery_status = Some(format!("Reset failed: {}", e)),
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_snapshots(&mut self, key: char) -> bool {
+        if key != 'r' && key != 'R' {
+            self.snapshot_confirm_restore = false;
+        }
+        let count = self.snapshots.len();
+        match key {
+            KEY_MENU | KEY_LEFT => {
+                self.state = AppState::DayView;
+            }
+            KEY_UP => {
+                if count > 0 && self.snapshot_cursor > 0 {
+                    self.snapshot_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.snapshot_cursor < count - 1 {
+                    self.snapshot_cursor += 1;
+                }
+            }
+            'r' | 'R' => {
+                if self.snapshot_cursor >= count {
+                    return true;
+                }
+                if !self.snapshot_confirm_restore {
+                    self.snapshot_confirm_restore = true;
+                    return true;
+                }
+                self.snapshot_confirm_restore = false;
+                self.restore_selected_snapshot();
+            }
+            'n' | 'N' => {
+                self.snapshot_status = Some(String::from(if self.take_snapshot(SnapshotReason::Manual) {
+                    "Snapshot taken"
+                } else {
+                    "Snapshot failed"
+                }));
+                self.snapshot_cursor = 0;
+                self.refresh_snapshots();
+            }
+            'd' | 'D' => {
+                if self.snapshot_cursor < count {
+                    self.delete_target = Some(DeleteTarget::Snapshot(self.snapshot_cursor));
+                    self.state = AppState::ConfirmDel;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    #[allow(clippy::collapsible_match)]
+    fn handle_calendars(&mut self, key: char) -> bool {
+        let count = self.calendars.len();
+        match key {
+            KEY_MENU | KEY_LEFT => {
+                self.state = AppState::DayView;
+            }
+            KEY_UP => {
+                if count > 0 && self.calendar_cursor > 0 {
+                    self.calendar_cursor -= 1;
+                }
+            }
+            KEY_DOWN => {
+                if count > 0 && self.calendar_cursor < count - 1 {
+                    self.calendar_cursor += 1;
+                }
+            }
+            KEY_ENTER | ' ' => {
+                if let Some(cal) = self.calendars.get_mut(self.calendar_cursor) {
+                    cal.visible = !cal.visible;
+                    let id = cal.id;
+                    self.day_cursor = 0;
+                    self.mark_calendar(id);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_import_review(&mut self, key: char) -> bool {
+        let event_count = self.event_review.entries.len();
+        let count = event_count + self.task_review.entries.len();
+        if self.review_field.is_none() {
+            match key {
+                KEY_MENU | KEY_LEFT => {
+                    self.event_review = Review::default();
+                    self.task_review = Review::default();
+                    self.review_file = None;
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                KEY_UP => {
+                    if count > 0 && self.review_cursor > 0 {
+                        self.review_cursor -= 1;
+                    }
+                    return true;
+                }
+                KEY_DOWN => {
+                    if count > 0 && self.review_cursor < count - 1 {
+                        self.review_cursor += 1;
+                    }
+                    return true;
+                }
+                KEY_ENTER => {
+                    self.apply_review();
+                    if let Some(name) = self.review_file.take() {
+                        let removed = self.inbox.as_mut().map(|inbox| inbox.delete(&name));
+                        if let Some(Err(e)) = removed {
+                            log::warn!("couldn't remove '{}' from the import inbox: {:?}", name, e);
+                        }
+                    }
+                    self.state = AppState::DayView;
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        let cursor = self.review_cursor;
+        if cursor < event_count {
+            review_entry_key(&mut self.event_review.entries[cursor], &mut self.review_field, key);
+        } else if let Some(entry) = self.task_review.entries.get_mut(cursor - event_count) {
+            review_entry_key(entry, &mut self.review_field, key);
+        }
+        true
+    }
+}
+
+fn check_title(title: &str, max: usize) -> Result<String, EditError> {
+    clean_title(title, max).ok_or(EditError::BadTitle)
+}
+
+fn check_when(date: Option<Date>, time: Option<Time>) -> Result<(), EditError> {
+    if date.is_some_and(|d| !d.is_valid()) {
+        return Err(EditError::BadDate);
+    }
+    if time.is_some_and(|t| !t.is_valid()) {
+        return Err(EditError::BadTime);
+    }
+    Ok(())
+}
+
+/// Review-screen keys that act on the entry under the cursor: choosing
+/// accept/skip/merge, then picking fields while merging.
+fn review_entry_key<T: Reviewable>(entry: &mut Entry<T>, field: &mut Option<usize>, key: char) {
+    let count = entry.fields().len();
+    match (*field, key) {
+        (None, 'a' | 'A') => entry.choice = Choice::Accept,
+        (None, 's' | 'S') => entry.choice = Choice::Skip,
+        (None, 'm' | 'M') if count > 0 => *field = Some(0),
+        (Some(_), KEY_MENU | KEY_LEFT) => *field = None,
+        (Some(i), KEY_UP) if i > 0 => *field = Some(i - 1),
+        (Some(i), KEY_DOWN) if i + 1 < count => *field = Some(i + 1),
+        (Some(i), KEY_ENTER | ' ') => {
+            if let Some(&f) = entry.fields().get(i) {
+                entry.toggle(f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolve a calendar to its store; a free function so callers can hold
+/// other fields of the app borrowed at the same time.
+fn route<'a, B: Backend>(
+    storage: &'a mut Option<Storage<B>>,
+    stores: &'a mut BTreeMap<CalendarId, Storage<B>>,
+    cal: CalendarId,
+) -> Option<&'a mut Storage<B>> {
+    match stores.get_mut(&cal) {
+        Some(cs) => Some(cs),
+        None => storage.as_mut(),
+    }
+}
+
+/// Group events by the store they're saved in, with an entry (possibly
+/// empty) for the default calendar and every open calendar store.
+fn group_events<'a>(events: &'a [Event], open: &BTreeSet<CalendarId>) -> BTreeMap<CalendarId, Vec<&'a Event>> {
+    let mut groups: BTreeMap<CalendarId, Vec<&Event>> = open.iter().map(|&c| (c, Vec::new())).collect();
+    groups.entry(DEFAULT_CALENDAR).or_default();
+    for ev in events {
+        let cal = if open.contains(&ev.calendar) { ev.calendar } else { DEFAULT_CALENDAR };
+        groups.entry(cal).or_default().push(ev);
+    }
+    groups
+}
@@ -0,0 +1,164 @@
+//! Key-value backends for Day Planner storage.
+//!
+//! `Storage` only needs get/put/delete/list over a flat key space within
+//! one dictionary. On device that is PDDB (the planner binary's
+//! `PddbBackend`); on a host it can be an in-memory map or, with the `std`
+//! feature, a directory of files.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Failure of the underlying medium (as opposed to bad data in it).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackendError {
+    Io,
+}
+
+pub trait Backend {
+    /// Read a key. Missing and empty keys both read as `Ok(None)`.
+    fn read(&mut self, key: &str) -> Result<Option<Vec<u8>>, BackendError>;
+
+    /// Replace a key's contents.
+    fn write(&mut self, key: &str, data: &[u8]) -> Result<(), BackendError>;
+
+    /// Remove a key. Removing a missing key is not an error.
+    fn delete(&mut self, key: &str) -> Result<(), BackendError>;
+
+    /// All keys currently present.
+    fn list(&mut self) -> Result<Vec<String>, BackendError>;
+
+    /// Flush buffered writes to the medium.
+    fn sync(&mut self) -> Result<(), BackendError> {
+        Ok(())
+    }
+}
+
+/// Volatile backend, for host tests and scratch state.
+#[derive(Debug, Clone, Default)]
+pub struct MemBackend {
+    pub map: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Backend for MemBackend {
+    fn read(&mut self, key: &str) -> Result<Option<Vec<u8>>, BackendError> {
+        Ok(self.map.get(key).filter(|v| !v.is_empty()).cloned())
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) -> Result<(), BackendError> {
+        self.map.insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), BackendError> {
+        self.map.remove(key);
+        Ok(())
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, BackendError> {
+        Ok(self.map.keys().cloned().collect())
+    }
+}
+
+/// One file per key under a directory, for poking at stored data on a host.
+#[cfg(feature = "std")]
+pub struct DirBackend {
+    root: std::path::PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl DirBackend {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> std::io::Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Backend for DirBackend {
+    fn read(&mut self, key: &str) -> Result<Option<Vec<u8>>, BackendError> {
+        match std::fs::read(self.root.join(key)) {
+            Ok(v) if v.is_empty() => Ok(None),
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(BackendError::Io),
+        }
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) -> Result<(), BackendError> {
+        std::fs::write(self.root.join(key), data).map_err(|_| BackendError::Io)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), BackendError> {
+        match std::fs::remove_file(self.root.join(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(BackendError::Io),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, BackendError> {
+        let rd = std::fs::read_dir(&self.root).map_err(|_| BackendError::Io)?;
+        Ok(rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect())
+    }
+}
+
+/// Wraps another backend and fails every write and delete once
+/// `writes_left` runs out, to simulate losing power mid-save on a host.
+#[derive(Debug, Clone, Default)]
+pub struct FaultBackend<B: Backend> {
+    pub inner: B,
+    /// Mutations still allowed to succeed; `None` never faults.
+    pub writes_left: Option<usize>,
+}
+
+impl<B: Backend> FaultBackend<B> {
+    pub fn new(inner: B, writes_left: Option<usize>) -> Self {
+        Self { inner, writes_left }
+    }
+
+    fn spend(&mut self) -> Result<(), BackendError> {
+        match self.writes_left {
+            Some(0) => Err(BackendError::Io),
+            Some(ref mut n) => {
+                *n -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<B: Backend> Backend for FaultBackend<B> {
+    fn read(&mut self, key: &str) -> Result<Option<Vec<u8>>, BackendError> {
+        self.inner.read(key)
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) -> Result<(), BackendError> {
+        self.spend()?;
+        self.inner.write(key, data)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), BackendError> {
+        self.spend()?;
+        self.inner.delete(key)
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, BackendError> {
+        self.inner.list()
+    }
+
+    fn sync(&mut self) -> Result<(), BackendError> {
+        self.inner.sync()
+    }
+}
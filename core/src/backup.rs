@@ -0,0 +1,261 @@
+//! Backup bundles for Day Planner.
+//!
+//! A backup is one self-describing JSON document with everything needed to
+//! rebuild the planner on another device:
+//!   format          — always `BACKUP_FORMAT`, so other JSON is rejected
+//!   version         — `BACKUP_VERSION`; bundles from a newer version are refused
+//!   exported        — the day it was made
+//!   events, event_calendars — events, and which calendar each is in
+//!   tasks, next_id
+//!   queries, calendars — settings: saved filters and the calendar registry
+//!
+//! Secret-basis calendars and their events are never included; the bundle
+//! would otherwise carry them out of their basis.
+//!
+//! Import either replaces the planner's contents or merges into them. A
+//! merge matches items by `uid`: one the planner already has is skipped if
+//! identical and counted as a conflict if not, so nothing existing is ever
+//! overwritten and importing the same backup twice adds nothing the second
+//! time. New items keep their local ID unless it's taken, in which case
+//! they get a fresh one. Replacing keeps secret-basis events, so their IDs
+//! count as taken there too.
+//!
+//! Version 1 bundles predate uids. Their items are matched the old way, by
+//! local ID and contents, and given fresh uids when added.
+
+extern crate alloc;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::{Calendar, DEFAULT_CALENDAR};
+use crate::planner::{CalendarId, Date, Event, Task, Uid};
+use crate::query::SavedQuery;
+use crate::seal::SealError;
+
+pub const BACKUP_FORMAT: &str = "precursor-planner-backup";
+pub const BACKUP_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackupError {
+    /// Not JSON, or not the shape of a backup.
+    Parse(String),
+    /// JSON, but not a planner backup.
+    NotBackup,
+    /// Made by a newer version of the planner.
+    TooNew(u32),
+    /// Replacing was refused because the current state couldn't be
+    /// snapshotted first.
+    NoSnapshot,
+    /// An encrypted backup that couldn't be decrypted.
+    Sealed(SealError),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Parse(e) => write!(f, "unreadable backup: {}", e),
+            BackupError::NotBackup => write!(f, "not a planner backup"),
+            BackupError::TooNew(v) => write!(f, "backup version {} is newer than this planner", v),
+            BackupError::NoSnapshot => write!(f, "couldn't save current state; not replacing"),
+            BackupError::Sealed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Throw away current events, tasks and filters and use the backup's.
+    Replace,
+    /// Add the backup's items to what's there.
+    Merge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: String,
+    pub version: u32,
+    pub exported: Date,
+    pub events: Vec<Event>,
+    /// `Event::calendar` isn't serialized, so it travels separately.
+    #[serde(default)]
+    pub event_calendars: Vec<(u32, CalendarId)>,
+    pub tasks: Vec<Task>,
+    pub next_id: u32,
+    #[serde(default)]
+    pub queries: Vec<SavedQuery>,
+    #[serde(default)]
+    pub calendars: Vec<Calendar>,
+}
+
+/// Just enough of a bundle to decide whether to read the rest.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    format: String,
+    #[serde(default)]
+    version: u32,
+}
+
+/// What an import did.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImportReport {
+    pub events_added: usize,
+    pub tasks_added: usize,
+    /// Items that were given a new ID because theirs was taken.
+    pub remapped: usize,
+    /// Items already present, identical.
+    pub unchanged: usize,
+    /// Items already present (same uid) with different contents; the
+    /// planner's copy was kept.
+    pub conflicts: usize,
+    pub queries_added: usize,
+}
+
+impl Backup {
+    /// Build a bundle. Callers leave secret-basis events and calendars out.
+    pub fn new(
+        exported: Date,
+        events: &[&Event],
+        tasks: &[Task],
+        next_id: u32,
+        queries: &[SavedQuery],
+        calendars: &[Calendar],
+    ) -> Self {
+        Self {
+            format: String::from(BACKUP_FORMAT),
+            version: BACKUP_VERSION,
+            exported,
+            events: events.iter().map(|&e| e.clone()).collect(),
+            event_calendars: events
+                .iter()
+                .filter(|e| e.calendar != DEFAULT_CALENDAR)
+                .map(|e| (e.id, e.calendar))
+                .collect(),
+            tasks: tasks.to_vec(),
+            next_id,
+            queries: queries.to_vec(),
+            calendars: calendars.to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only plain structs and strings; serializing can't fail.
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Backup, BackupError> {
+        let header: Header = serde_json::from_str(text).map_err(|e| BackupError::Parse(format!("{}", e)))?;
+        if header.format != BACKUP_FORMAT {
+            return Err(BackupError::NotBackup);
+        }
+        if header.version > BACKUP_VERSION {
+            return Err(BackupError::TooNew(header.version));
+        }
+        let mut backup: Backup = serde_json::from_str(text).map_err(|e| BackupError::Parse(format!("{}", e)))?;
+        for ev in backup.events.iter_mut() {
+            if let Some(&(_, cal)) = backup.event_calendars.iter().find(|(id, _)| *id == ev.id) {
+                ev.calendar = cal;
+            }
+        }
+        Ok(backup)
+    }
+
+    /// The smallest safe `next_id` after loading this bundle: past its
+    /// counter and every ID in it.
+    pub fn next_id_floor(&self) -> u32 {
+        let max = self.events.iter().map(|e| e.id).chain(self.tasks.iter().map(|t| t.id)).max();
+        self.next_id.max(max.map_or(0, |m| m + 1))
+    }
+}
+
+fn same_event(a: &Event, b: &Event) -> bool {
+    a.date == b.date && a.time == b.time && a.title == b.title && a.priority == b.priority && a.calendar == b.calendar
+}
+
+fn same_task(a: &Task, b: &Task) -> bool {
+    a.title == b.title && a.done == b.done && a.priority == b.priority
+}
+
+/// Merge `backup` into existing state. Events whose calendar isn't in
+/// `known_calendars` go to the default one; items without a uid get one
+/// from `new_uid`. Returns what was added; the added items are the tail of
+/// `events` and `tasks`.
+pub fn merge(
+    backup: &Backup,
+    known_calendars: &BTreeSet<CalendarId>,
+    events: &mut Vec<Event>,
+    tasks: &mut Vec<Task>,
+    next_id: &mut u32,
+    queries: &mut Vec<SavedQuery>,
+    new_uid: &mut dyn FnMut() -> Uid,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    // Events and tasks share one ID counter.
+    let mut used: BTreeSet<u32> = events.iter().map(|e| e.id).chain(tasks.iter().map(|t| t.id)).collect();
+    *next_id = (*next_id).max(used.iter().next_back().map_or(0, |m| m + 1));
+    let mut claim = |id: u32, report: &mut ImportReport| -> u32 {
+        let id = if used.contains(&id) {
+            report.remapped += 1;
+            *next_id
+        } else {
+            id
+        };
+        used.insert(id);
+        *next_id = (*next_id).max(id + 1);
+        id
+    };
+    for ev in backup.events.iter() {
+        let mut ev = ev.clone();
+        if !known_calendars.contains(&ev.calendar) {
+            ev.calendar = DEFAULT_CALENDAR;
+        }
+        let present = if ev.uid.is_nil() {
+            events.iter().find(|e| e.id == ev.id && same_event(e, &ev))
+        } else {
+            events.iter().find(|e| e.uid == ev.uid)
+        };
+        match present {
+            Some(e) if same_event(e, &ev) => report.unchanged += 1,
+            Some(_) => report.conflicts += 1,
+            None => {
+                if ev.uid.is_nil() {
+                    ev.uid = new_uid();
+                }
+                ev.id = claim(ev.id, &mut report);
+                events.push(ev);
+                report.events_added += 1;
+            }
+        }
+    }
+    for task in backup.tasks.iter() {
+        let present = if task.uid.is_nil() {
+            tasks.iter().find(|t| t.id == task.id && same_task(t, task))
+        } else {
+            tasks.iter().find(|t| t.uid == task.uid)
+        };
+        match present {
+            Some(t) if same_task(t, task) => report.unchanged += 1,
+            Some(_) => report.conflicts += 1,
+            None => {
+                let mut task = task.clone();
+                if task.uid.is_nil() {
+                    task.uid = new_uid();
+                }
+                task.id = claim(task.id, &mut report);
+                tasks.push(task);
+                report.tasks_added += 1;
+            }
+        }
+    }
+    for q in backup.queries.iter() {
+        if !queries.iter().any(|e| e.name == q.name) {
+            queries.push(q.clone());
+            report.queries_added += 1;
+        }
+    }
+    report
+}
@@ -0,0 +1,71 @@
+//! Calendar registry for Day Planner.
+//!
+//! Each calendar keeps its events in its own PDDB dictionary, so one can be
+//! hidden, shared or wiped without touching the others. The default
+//! calendar uses `planner.data`, which also holds tasks, filters, snapshots,
+//! the ID counter and this registry (under `calendars`). IDs come from that
+//! one counter, so they're unique across calendars.
+//!
+//! Each unlocked secret basis also gets a calendar, pinned to that basis:
+//! its definition and events live in `planner.secret` inside the basis, so
+//! nothing outside it (not the registry, not snapshots) mentions it. When
+//! the basis is locked the calendar and its events simply go away. Basis
+//! calendars get an ID from `BASIS_CALENDAR_BASE` up each time they're
+//! attached; the ID isn't stored.
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::planner::CalendarId;
+use crate::storage::{BASIS_DICT, DICT};
+
+/// The calendar whose events live in `planner.data`.
+pub const DEFAULT_CALENDAR: CalendarId = 0;
+
+/// Registry calendars use IDs below this; basis calendars at or above it.
+pub const BASIS_CALENDAR_BASE: CalendarId = 128;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calendar {
+    pub id: CalendarId,
+    pub name: String,
+    /// PDDB dictionary holding this calendar's events.
+    pub dict: String,
+    /// Shown next to events in the day and month views.
+    pub marker: char,
+    pub visible: bool,
+    /// The secret basis this calendar is pinned to, if any.
+    #[serde(default)]
+    pub basis: Option<String>,
+}
+
+impl Calendar {
+    pub fn new(id: CalendarId, name: &str, dict: &str, marker: char) -> Self {
+        Self {
+            id,
+            name: String::from(name),
+            dict: String::from(dict),
+            marker,
+            visible: true,
+            basis: None,
+        }
+    }
+
+    /// The calendar for a secret basis that hasn't saved its own yet.
+    pub fn for_basis(id: CalendarId, basis: &str) -> Self {
+        let mut cal = Self::new(id, basis, BASIS_DICT, '#');
+        cal.basis = Some(String::from(basis));
+        cal
+    }
+}
+
+/// The registry used until one has been saved.
+pub fn default_calendars() -> Vec<Calendar> {
+    alloc::vec![
+        Calendar::new(DEFAULT_CALENDAR, "Personal", DICT, 'P'),
+        Calendar::new(1, "Work", "planner.cal.work", 'W'),
+        Calendar::new(2, "Shared", "planner.cal.shared", 'S'),
+    ]
+}
@@ -0,0 +1,95 @@
+//! Wire format for stored values.
+//!
+//! Every value `Storage` writes starts with a one-byte format tag:
+//!   0x01 — JSON (`serde_json`)
+//!   0x02 — postcard, a compact binary encoding (`binary-storage` feature)
+//!
+//! Neither byte can start a JSON document, so untagged data from before
+//! tagging existed is read as JSON. Builds without `binary-storage` still
+//! write JSON and can't read postcard values; builds with it read both,
+//! so data converts record by record as it is rewritten.
+
+extern crate alloc;
+use alloc::vec::Vec;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const TAG_JSON: u8 = 0x01;
+const TAG_POSTCARD: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Untagged JSON, as written before format tags.
+    LegacyJson,
+    Json,
+    Postcard,
+}
+
+impl Format {
+    /// The format new values are written in.
+    pub fn preferred() -> Format {
+        if cfg!(feature = "binary-storage") {
+            Format::Postcard
+        } else {
+            Format::Json
+        }
+    }
+
+    pub fn of(blob: &[u8]) -> Format {
+        match blob.first() {
+            Some(&TAG_JSON) => Format::Json,
+            Some(&TAG_POSTCARD) => Format::Postcard,
+            _ => Format::LegacyJson,
+        }
+    }
+}
+
+/// Encode in the preferred format.
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    encode_as(value, Format::preferred())
+}
+
+/// Encode in a specific format. Asking for `LegacyJson` writes untagged JSON.
+pub fn encode_as<T: Serialize + ?Sized>(value: &T, format: Format) -> Vec<u8> {
+    match format {
+        Format::LegacyJson => serde_json::to_vec(value).unwrap_or_default(),
+        Format::Json => {
+            let mut out = alloc::vec![TAG_JSON];
+            out.extend(serde_json::to_vec(value).unwrap_or_default());
+            out
+        }
+        #[cfg(feature = "binary-storage")]
+        Format::Postcard => {
+            let mut out = alloc::vec![TAG_POSTCARD];
+            out.extend(postcard::to_allocvec(value).unwrap_or_default());
+            out
+        }
+        #[cfg(not(feature = "binary-storage"))]
+        Format::Postcard => encode_as(value, Format::Json),
+    }
+}
+
+/// Decode a value in whichever format it was written.
+pub fn decode<T: DeserializeOwned>(blob: &[u8]) -> Option<T> {
+    match Format::of(blob) {
+        Format::LegacyJson => serde_json::from_slice(blob).ok(),
+        Format::Json => serde_json::from_slice(&blob[1..]).ok(),
+        #[cfg(feature = "binary-storage")]
+        Format::Postcard => postcard::from_bytes(&blob[1..]).ok(),
+        #[cfg(not(feature = "binary-storage"))]
+        Format::Postcard => {
+            log::warn!("binary value found but binary-storage is disabled");
+            None
+        }
+    }
+}
+
+/// The JSON text of a value, if it is JSON. Migrations that work on
+/// `serde_json::Value` use this; they can't see into binary values.
+pub fn json_body(blob: &[u8]) -> Option<&[u8]> {
+    match Format::of(blob) {
+        Format::LegacyJson => Some(blob),
+        Format::Json => Some(&blob[1..]),
+        Format::Postcard => None,
+    }
+}
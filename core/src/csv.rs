@@ -0,0 +1,421 @@
+//! CSV import and export for Day Planner.
+//!
+//! Columns:
+//!   events — date,time,title,priority,uid
+//!   tasks  — title,done,priority,uid
+//!
+//! Files start with that header row. On import, columns are matched by
+//! header name, in any order and any case. Fields with a comma, quote,
+//! line break or leading/trailing space are quoted, doubling any quotes
+//! (RFC 4180). Rows end in CRLF; import accepts bare LF too.
+//!
+//! Values on import:
+//!   date      — `YYYY-MM-DD`, as `Date::display` writes it
+//!   time      — `14:30`, `2:30PM` (`Time::display`), or empty / `All day`
+//!   title     — trimmed, with line breaks made spaces; a title longer
+//!               than `EVENT_TITLE_MAX` / `TASK_TITLE_MAX` characters is
+//!               shortened, with a warning. A leading `'` before `=`, `+`,
+//!               `-` or `@` is dropped, undoing what export adds
+//!   priority  — Low, Normal or High in any case; empty is Normal
+//!   done      — yes/no, true/false, 1/0, x; empty is not done
+//!   uid       — the item's stable id; optional, empty gets a fresh one
+//!
+//! Export puts a `'` before titles starting with `=`, `+`, `-` or `@` so
+//! spreadsheets don't run them as formulas.
+//!
+//! Import is a dry run: it returns a `Report` of the rows it would add
+//! (noting any it had to change), the rows it can't read and why, and the rows that duplicate something
+//! already in the planner or earlier in the file: the same uid, or the same
+//! contents. Nothing changes until
+//! the caller commits the accepted rows (see `PlannerApp::commit_events`).
+
+extern crate alloc;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::planner::{fit_title, Date, Event, Priority, Task, Time, Uid, EVENT_TITLE_MAX, TASK_TITLE_MAX};
+
+const EVENT_COLUMNS: [&str; 5] = ["date", "time", "title", "priority", "uid"];
+const TASK_COLUMNS: [&str; 4] = ["title", "done", "priority", "uid"];
+
+/// Input that can't be read as CSV of the expected kind at all.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// No header row.
+    Empty,
+    MissingColumn(&'static str),
+    /// A quoted field still open at the end of the input; holds its row.
+    UnterminatedQuote(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Empty => write!(f, "no header row"),
+            CsvError::MissingColumn(c) => write!(f, "missing '{}' column", c),
+            CsvError::UnterminatedQuote(row) => write!(f, "row {}: missing closing quote", row),
+        }
+    }
+}
+
+/// A row that couldn't be imported. Rows are numbered by the line they
+/// start on; the header is row 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejected {
+    pub row: usize,
+    pub reason: String,
+}
+
+/// A row that will be imported, but not quite as written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub row: usize,
+    pub message: String,
+}
+
+/// What an import would do.
+#[derive(Debug)]
+pub struct Report<T> {
+    /// Items to add. IDs are assigned on commit.
+    pub accepted: Vec<T>,
+    /// Accepted rows that were changed to fit.
+    pub warnings: Vec<Warning>,
+    pub rejected: Vec<Rejected>,
+    /// Rows matching an existing item or an earlier row; not added.
+    pub duplicates: Vec<usize>,
+    /// Header names that aren't known columns; their values are ignored.
+    pub ignored_columns: Vec<String>,
+}
+
+impl<T> Report<T> {
+    fn new(ignored_columns: Vec<String>) -> Self {
+        Self {
+            accepted: Vec::new(),
+            warnings: Vec::new(),
+            rejected: Vec::new(),
+            duplicates: Vec::new(),
+            ignored_columns,
+        }
+    }
+
+    /// One line for the status bar, e.g. "12 to add, 1 rejected, 3
+    /// duplicates", plus ", 2 warnings" if there are any.
+    pub fn summary(&self) -> String {
+        let mut line = format!(
+            "{} to add, {} rejected, {} duplicates",
+            self.accepted.len(),
+            self.rejected.len(),
+            self.duplicates.len()
+        );
+        if !self.warnings.is_empty() {
+            line.push_str(&format!(", {} warnings", self.warnings.len()));
+        }
+        line
+    }
+}
+
+// --- Export ---
+
+pub fn write_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> String {
+    let mut out = String::new();
+    push_row(&mut out, &EVENT_COLUMNS);
+    for ev in events {
+        let time = ev.time.map(|t| format!("{:02}:{:02}", t.hour, t.minute)).unwrap_or_default();
+        let title = title_text(&ev.title);
+        push_row(&mut out, &[&ev.date.display(), &time, &title, ev.priority.label(), &uid_text(ev.uid)]);
+    }
+    out
+}
+
+pub fn write_tasks<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let mut out = String::new();
+    push_row(&mut out, &TASK_COLUMNS);
+    for task in tasks {
+        let done = if task.done { "yes" } else { "no" };
+        push_row(&mut out, &[&title_text(&task.title), done, task.priority.label(), &uid_text(task.uid)]);
+    }
+    out
+}
+
+/// Characters that make a spreadsheet read a cell as a formula.
+const FORMULA_START: [char; 4] = ['=', '+', '-', '@'];
+
+/// The title, with a `'` in front if a spreadsheet would take it for a
+/// formula.
+fn title_text(title: &str) -> String {
+    if title.starts_with(FORMULA_START) {
+        format!("'{}", title)
+    } else {
+        String::from(title)
+    }
+}
+
+fn uid_text(uid: Uid) -> String {
+    if uid.is_nil() {
+        String::new()
+    } else {
+        format!("{}", uid)
+    }
+}
+
+fn push_row(out: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_field(out, field);
+    }
+    out.push_str("\r\n");
+}
+
+fn push_field(out: &mut String, field: &str) {
+    let needs_quotes = field.contains([',', '"', '\r', '\n'])
+        || field.starts_with(char::is_whitespace)
+        || field.ends_with(char::is_whitespace);
+    if !needs_quotes {
+        out.push_str(field);
+        return;
+    }
+    out.push('"');
+    for c in field.chars() {
+        if c == '"' {
+            out.push('"');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+// --- Import ---
+
+/// Split `input` into rows of fields, each with the line it starts on.
+/// Blank lines are skipped.
+fn parse_rows(input: &str) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut rows = Vec::new();
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut line = 1;
+    let mut row_start = 1;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() && !was_quoted => {
+                quoted = true;
+                was_quoted = true;
+            }
+            ',' => {
+                fields.push(core::mem::take(&mut field));
+                was_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(core::mem::take(&mut field));
+                if !(fields.len() == 1 && fields[0].is_empty() && !was_quoted) {
+                    rows.push((row_start, core::mem::take(&mut fields)));
+                }
+                fields.clear();
+                was_quoted = false;
+                row_start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(CsvError::UnterminatedQuote(row_start));
+    }
+    if !field.is_empty() || !fields.is_empty() || was_quoted {
+        fields.push(field);
+        rows.push((row_start, fields));
+    }
+    Ok(rows)
+}
+
+/// Column positions for `wanted`, plus any header names left over.
+fn map_columns(
+    header: &[String],
+    wanted: &[&'static str],
+    required: &[&'static str],
+) -> Result<(Vec<Option<usize>>, Vec<String>), CsvError> {
+    let names: Vec<String> = header.iter().map(|h| h.trim().to_ascii_lowercase()).collect();
+    let cols: Vec<Option<usize>> = wanted.iter().map(|w| names.iter().position(|n| n == w)).collect();
+    for (w, col) in wanted.iter().zip(cols.iter()) {
+        if col.is_none() && required.contains(w) {
+            return Err(CsvError::MissingColumn(w));
+        }
+    }
+    let ignored = names.into_iter().filter(|n| !wanted.contains(&n.as_str())).collect();
+    Ok((cols, ignored))
+}
+
+fn cell(fields: &[String], col: Option<usize>) -> &str {
+    col.and_then(|i| fields.get(i)).map_or("", |s| s.as_str())
+}
+
+fn parse_time(s: &str) -> Result<Option<Time>, String> {
+    let s = s.trim();
+    if s.is_empty() || s.eq_ignore_ascii_case("all day") {
+        return Ok(None);
+    }
+    let bad = || format!("bad time '{}'", s);
+    let upper = s.to_ascii_uppercase();
+    let (body, pm) = match (upper.strip_suffix("AM"), upper.strip_suffix("PM")) {
+        (Some(b), _) => (b.trim_end(), Some(false)),
+        (_, Some(b)) => (b.trim_end(), Some(true)),
+        _ => (upper.as_str(), None),
+    };
+    let (h, m) = body.split_once(':').ok_or_else(bad)?;
+    let hour: u8 = h.parse().map_err(|_| bad())?;
+    let minute: u8 = m.parse().map_err(|_| bad())?;
+    let hour = match pm {
+        None if hour <= 23 => hour,
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        _ => return Err(bad()),
+    };
+    if minute > 59 {
+        return Err(bad());
+    }
+    Ok(Some(Time::new(hour, minute)))
+}
+
+fn parse_priority(s: &str) -> Result<Priority, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "" | "normal" => Ok(Priority::Normal),
+        "low" => Ok(Priority::Low),
+        "high" => Ok(Priority::High),
+        other => Err(format!("bad priority '{}'", other)),
+    }
+}
+
+fn parse_uid(s: &str) -> Result<Uid, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(Uid::NIL);
+    }
+    Uid::parse(s).ok_or_else(|| format!("bad uid '{}'", s))
+}
+
+/// Same item: both have this uid.
+fn same_uid(a: Uid, b: Uid) -> bool {
+    !a.is_nil() && a == b
+}
+
+fn parse_done(s: &str) -> Result<bool, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "" | "no" | "false" | "0" => Ok(false),
+        "yes" | "true" | "1" | "x" => Ok(true),
+        other => Err(format!("bad done value '{}'", other)),
+    }
+}
+
+/// The title as the planner would store it and whether it had to be
+/// shortened, or why it can't be stored at all.
+fn read_title(text: &str, max: usize) -> Result<(String, bool), String> {
+    let text = match text.trim_start().strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_START) => rest,
+        _ => text,
+    };
+    fit_title(text, max).ok_or_else(|| String::from("no title"))
+}
+
+fn shortened(row: usize, max: usize) -> Warning {
+    Warning { row, message: format!("title shortened to {} characters", max) }
+}
+
+/// Check the row isn't wider than the header before reading it.
+fn check_width(fields: &[String], header: &[String]) -> Result<(), String> {
+    if fields.len() > header.len() {
+        return Err(format!("{} fields, header has {}", fields.len(), header.len()));
+    }
+    Ok(())
+}
+
+/// Dry run of importing events; `existing` is checked for duplicates
+/// (same uid, or same date, time and title).
+pub fn read_events(input: &str, existing: &[Event]) -> Result<Report<Event>, CsvError> {
+    let mut rows = parse_rows(input)?.into_iter();
+    let (_, header) = rows.next().ok_or(CsvError::Empty)?;
+    let (cols, ignored) = map_columns(&header, &EVENT_COLUMNS, &["date", "title"])?;
+    let mut report = Report::new(ignored);
+    for (row, fields) in rows {
+        let parsed = check_width(&fields, &header).and_then(|_| {
+            let date_text = cell(&fields, cols[0]).trim();
+            let date = Date::parse(date_text).ok_or_else(|| format!("bad date '{}'", date_text))?;
+            let (title, cut) = read_title(cell(&fields, cols[2]), EVENT_TITLE_MAX)?;
+            let mut ev = Event::new(0, date, title);
+            ev.time = parse_time(cell(&fields, cols[1]))?;
+            ev.priority = parse_priority(cell(&fields, cols[3]))?;
+            ev.uid = parse_uid(cell(&fields, cols[4]))?;
+            Ok((ev, cut))
+        });
+        match parsed {
+            Err(reason) => report.rejected.push(Rejected { row, reason }),
+            Ok((ev, cut)) => {
+                let same = |e: &Event| {
+                    same_uid(e.uid, ev.uid) || (e.date == ev.date && e.time == ev.time && e.title == ev.title)
+                };
+                if existing.iter().any(same) || report.accepted.iter().any(same) {
+                    report.duplicates.push(row);
+                } else {
+                    if cut {
+                        report.warnings.push(shortened(row, EVENT_TITLE_MAX));
+                    }
+                    report.accepted.push(ev);
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Dry run of importing tasks; `existing` is checked for duplicates
+/// (same uid, or same title).
+pub fn read_tasks(input: &str, existing: &[Task]) -> Result<Report<Task>, CsvError> {
+    let mut rows = parse_rows(input)?.into_iter();
+    let (_, header) = rows.next().ok_or(CsvError::Empty)?;
+    let (cols, ignored) = map_columns(&header, &TASK_COLUMNS, &["title"])?;
+    let mut report = Report::new(ignored);
+    for (row, fields) in rows {
+        let parsed = check_width(&fields, &header).and_then(|_| {
+            let (title, cut) = read_title(cell(&fields, cols[0]), TASK_TITLE_MAX)?;
+            let mut task = Task::new(0, title);
+            task.done = parse_done(cell(&fields, cols[1]))?;
+            task.priority = parse_priority(cell(&fields, cols[2]))?;
+            task.uid = parse_uid(cell(&fields, cols[3]))?;
+            Ok((task, cut))
+        });
+        match parsed {
+            Err(reason) => report.rejected.push(Rejected { row, reason }),
+            Ok((task, cut)) => {
+                let same = |t: &Task| same_uid(t.uid, task.uid) || t.title == task.title;
+                if existing.iter().any(same) || report.accepted.iter().any(same) {
+                    report.duplicates.push(row);
+                } else {
+                    if cut {
+                        report.warnings.push(shortened(row, TASK_TITLE_MAX));
+                    }
+                    report.accepted.push(task);
+                }
+            }
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,357 @@
+//! Duplicate detection for imports.
+//!
+//! Each incoming item is matched against the planner's items:
+//!   uid   — when the incoming item has one and the planner has it too
+//!   fuzzy — otherwise: events on the same date and time with the same
+//!           normalized title, tasks with the same normalized title. A uid
+//!           the planner doesn't know may still be a copy made elsewhere,
+//!           so it falls back to this as well.
+//!
+//! and classified:
+//!   New         — no match
+//!   Identical   — matched, and every field agrees
+//!   Conflicting — matched, but some fields differ (listed)
+//!   Repeated    — no match, but the same as an earlier item in the import
+//!
+//! Normalized titles are lowercased, with runs of whitespace collapsed and
+//! surrounding whitespace and trailing punctuation dropped.
+//!
+//! A `Review` holds each item with its class and a `Choice`: accept, skip,
+//! or merge chosen fields into the planner's copy. New items start out
+//! accepted and everything else skipped, so a review committed untouched
+//! adds only what's new and changes nothing already there. `resolve` turns
+//! the choices into items to add and updated copies of existing ones.
+
+extern crate alloc;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::planner::{Event, Task, Uid};
+
+/// A field that can differ between an incoming item and the planner's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Field {
+    Title,
+    Date,
+    Time,
+    Priority,
+    Done,
+}
+
+impl Field {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Date => "Date",
+            Field::Time => "Time",
+            Field::Priority => "Priority",
+            Field::Done => "Done",
+        }
+    }
+}
+
+/// An item the review can match, compare and merge field by field.
+pub trait Reviewable: Clone {
+    /// The fields compared, in display order.
+    const FIELDS: &'static [Field];
+
+    fn id(&self) -> u32;
+    fn uid(&self) -> Uid;
+    fn set_uid(&mut self, uid: Uid);
+    /// The fuzzy key matches.
+    fn same_key(&self, other: &Self) -> bool;
+    fn differs(&self, other: &Self, field: Field) -> bool;
+    /// Copy `field` from `other`.
+    fn take(&mut self, other: &Self, field: Field);
+    /// `field` as shown on the review screen.
+    fn show(&self, field: Field) -> String;
+}
+
+/// Lowercase, collapse whitespace, drop trailing punctuation.
+pub fn normalize_title(title: &str) -> String {
+    let words: Vec<&str> = title.split_whitespace().collect();
+    let joined = words.join(" ").to_lowercase();
+    String::from(joined.trim_end_matches(|c: char| c.is_ascii_punctuation()).trim_end())
+}
+
+impl Reviewable for Event {
+    const FIELDS: &'static [Field] = &[Field::Title, Field::Date, Field::Time, Field::Priority];
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn uid(&self) -> Uid {
+        self.uid
+    }
+
+    fn set_uid(&mut self, uid: Uid) {
+        self.uid = uid;
+    }
+
+    fn same_key(&self, other: &Self) -> bool {
+        self.date == other.date && self.time == other.time && normalize_title(&self.title) == normalize_title(&other.title)
+    }
+
+    fn differs(&self, other: &Self, field: Field) -> bool {
+        match field {
+            Field::Title => self.title != other.title,
+            Field::Date => self.date != other.date,
+            Field::Time => self.time != other.time,
+            Field::Priority => self.priority != other.priority,
+            Field::Done => false,
+        }
+    }
+
+    fn take(&mut self, other: &Self, field: Field) {
+        match field {
+            Field::Title => self.title = other.title.clone(),
+            Field::Date => self.date = other.date,
+            Field::Time => self.time = other.time,
+            Field::Priority => self.priority = other.priority,
+            Field::Done => {}
+        }
+    }
+
+    fn show(&self, field: Field) -> String {
+        match field {
+            Field::Title => self.title.clone(),
+            Field::Date => self.date.display(),
+            Field::Time => self.time_display(),
+            Field::Priority => String::from(self.priority.label()),
+            Field::Done => String::new(),
+        }
+    }
+}
+
+impl Reviewable for Task {
+    const FIELDS: &'static [Field] = &[Field::Title, Field::Done, Field::Priority];
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn uid(&self) -> Uid {
+        self.uid
+    }
+
+    fn set_uid(&mut self, uid: Uid) {
+        self.uid = uid;
+    }
+
+    fn same_key(&self, other: &Self) -> bool {
+        normalize_title(&self.title) == normalize_title(&other.title)
+    }
+
+    fn differs(&self, other: &Self, field: Field) -> bool {
+        match field {
+            Field::Title => self.title != other.title,
+            Field::Done => self.done != other.done,
+            Field::Priority => self.priority != other.priority,
+            Field::Date | Field::Time => false,
+        }
+    }
+
+    fn take(&mut self, other: &Self, field: Field) {
+        match field {
+            Field::Title => self.title = other.title.clone(),
+            Field::Done => self.done = other.done,
+            Field::Priority => self.priority = other.priority,
+            Field::Date | Field::Time => {}
+        }
+    }
+
+    fn show(&self, field: Field) -> String {
+        match field {
+            Field::Title => self.title.clone(),
+            Field::Done => String::from(if self.done { "Yes" } else { "No" }),
+            Field::Priority => String::from(self.priority.label()),
+            Field::Date | Field::Time => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Class {
+    New,
+    /// Same as the planner's item with this ID.
+    Identical(u32),
+    /// Matches the planner's item with this ID; these fields differ.
+    Conflicting(u32, Vec<Field>),
+    /// Same as the entry at this index earlier in the import.
+    Repeated(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Choice {
+    /// Add a new or repeated item; take every differing field of a
+    /// conflicting one. Nothing to do for an identical one.
+    Accept,
+    Skip,
+    /// Take just these fields into the planner's copy.
+    Merge(Vec<Field>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry<T> {
+    pub item: T,
+    /// The planner's copy, for identical and conflicting entries.
+    pub existing: Option<T>,
+    pub class: Class,
+    pub choice: Choice,
+}
+
+impl<T: Reviewable> Entry<T> {
+    /// Fields that differ from the planner's copy.
+    pub fn fields(&self) -> &[Field] {
+        match &self.class {
+            Class::Conflicting(_, fields) => fields,
+            _ => &[],
+        }
+    }
+
+    /// Whether `field` would come from the incoming item.
+    pub fn takes(&self, field: Field) -> bool {
+        match &self.choice {
+            Choice::Accept => self.fields().contains(&field),
+            Choice::Skip => false,
+            Choice::Merge(take) => take.contains(&field),
+        }
+    }
+
+    /// Flip one field between the planner's and the incoming value. Only
+    /// conflicting entries have fields to flip.
+    pub fn toggle(&mut self, field: Field) {
+        if !self.fields().contains(&field) {
+            return;
+        }
+        let mut take: Vec<Field> = self.fields().iter().copied().filter(|&f| self.takes(f)).collect();
+        match take.iter().position(|&f| f == field) {
+            Some(i) => {
+                take.remove(i);
+            }
+            None => take.push(field),
+        }
+        take.sort();
+        self.choice = Choice::Merge(take);
+    }
+}
+
+/// Items to add and the planner's items to update, after a review.
+#[derive(Debug)]
+pub struct Resolved<T> {
+    /// New items. Their IDs are meaningless; uids may be nil.
+    pub added: Vec<T>,
+    /// Changed copies of existing items, with their own IDs and uids.
+    pub updated: Vec<T>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Review<T> {
+    pub entries: Vec<Entry<T>>,
+}
+
+impl<T> Default for Review<T> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+/// The planner's copy of `item`: by uid if it has one the planner knows,
+/// else by fuzzy key.
+fn find_match<'a, T: Reviewable>(item: &T, existing: &'a [T]) -> Option<&'a T> {
+    let uid = item.uid();
+    if !uid.is_nil() {
+        if let Some(found) = existing.iter().find(|e| e.uid() == uid) {
+            return Some(found);
+        }
+    }
+    existing.iter().find(|e| e.same_key(item))
+}
+
+fn same_item<T: Reviewable>(a: &T, b: &T) -> bool {
+    (!a.uid().is_nil() && a.uid() == b.uid()) || a.same_key(b)
+}
+
+impl<T: Reviewable> Review<T> {
+    /// Classify `incoming` against the planner's `existing` items.
+    pub fn classify(incoming: Vec<T>, existing: &[T]) -> Self {
+        let mut entries: Vec<Entry<T>> = Vec::with_capacity(incoming.len());
+        for item in incoming {
+            let found = find_match(&item, existing);
+            let class = match found {
+                Some(found) => {
+                    let fields: Vec<Field> = T::FIELDS.iter().copied().filter(|&f| found.differs(&item, f)).collect();
+                    if fields.is_empty() {
+                        Class::Identical(found.id())
+                    } else {
+                        Class::Conflicting(found.id(), fields)
+                    }
+                }
+                None => {
+                    let earlier = entries
+                        .iter()
+                        .position(|e| matches!(e.class, Class::New) && same_item(&e.item, &item));
+                    match earlier {
+                        Some(i) => Class::Repeated(i),
+                        None => Class::New,
+                    }
+                }
+            };
+            let choice = if class == Class::New { Choice::Accept } else { Choice::Skip };
+            entries.push(Entry { item, existing: found.cloned(), class, choice });
+        }
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// e.g. "3 new, 2 identical, 1 conflicting, 0 repeated"
+    pub fn summary(&self) -> String {
+        let count = |f: fn(&Class) -> bool| self.entries.iter().filter(|e| f(&e.class)).count();
+        format!(
+            "{} new, {} identical, {} conflicting, {} repeated",
+            count(|c| matches!(c, Class::New)),
+            count(|c| matches!(c, Class::Identical(_))),
+            count(|c| matches!(c, Class::Conflicting(..))),
+            count(|c| matches!(c, Class::Repeated(_))),
+        )
+    }
+
+    /// Apply the choices. Items accepted as repeats lose their uid so they
+    /// don't share it with the copy before them.
+    pub fn resolve(&self, existing: &[T]) -> Resolved<T> {
+        let mut out = Resolved { added: Vec::new(), updated: Vec::new() };
+        for entry in self.entries.iter() {
+            match (&entry.class, &entry.choice) {
+                (_, Choice::Skip) | (Class::Identical(_), _) => {}
+                (Class::New, _) => out.added.push(entry.item.clone()),
+                (Class::Repeated(_), Choice::Accept) => {
+                    let mut copy = entry.item.clone();
+                    copy.set_uid(Uid::NIL);
+                    out.added.push(copy);
+                }
+                (Class::Repeated(_), Choice::Merge(_)) => {}
+                (Class::Conflicting(id, _), _) => {
+                    let at = match out.updated.iter().position(|u| u.id() == *id) {
+                        Some(at) => at,
+                        None => match existing.iter().find(|e| e.id() == *id) {
+                            Some(e) => {
+                                out.updated.push(e.clone());
+                                out.updated.len() - 1
+                            }
+                            None => continue,
+                        },
+                    };
+                    for &field in T::FIELDS.iter().filter(|&&f| entry.takes(f)) {
+                        out.updated[at].take(&entry.item, field);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
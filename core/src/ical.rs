@@ -0,0 +1,614 @@
+//! iCalendar (RFC 5545) import and export for Day Planner.
+//!
+//! Events map to VEVENT components:
+//!   SUMMARY   — title; line breaks and other control characters become
+//!               spaces and a title too long to store is shortened (with
+//!               an `Issue`); an item with a blank one is left out
+//!   DTSTART   — `VALUE=DATE` for all-day events, otherwise a floating
+//!               local date-time (the planner has no time zones)
+//!   DTEND     — all-day events end the next day; timed events have none
+//!   PRIORITY  — High=1, Normal=5, Low=9; on import 1–4 is High, 5 and
+//!               0 (undefined) are Normal, 6–9 Low
+//!   UID       — the event's `uid`; on import a UUID is taken as the
+//!               uid, anything else is hashed into one (`Uid::from_name`),
+//!               so importing the same file twice gives the same uids
+//!   DTSTAMP   — the export date; ignored on import
+//!
+//! Tasks map to VTODO components:
+//!   SUMMARY   — title, checked as for events
+//!   STATUS    — COMPLETED when done, else NEEDS-ACTION; on import
+//!               IN-PROCESS counts as not done and CANCELLED as done
+//!   COMPLETED, PERCENT-COMPLETE — read as done when present (or 100)
+//!               and STATUS is missing; kept like the properties below,
+//!               but not written for a task that's no longer done
+//!   PRIORITY  — as for events
+//!   UID, DTSTAMP — as for events
+//!   others    — DESCRIPTION, DUE and any other property, and nested
+//!               components such as VALARM, are kept as they were in the
+//!               task's `extra` and written back on export. Editing the
+//!               task drops LAST-MODIFIED and bumps SEQUENCE there (see
+//!               `note_revision`)
+//!
+//! Items without a uid (nil) are exported under a
+//! `planner-event-<id>@precursor` / `planner-task-<id>@precursor` UID
+//! instead, and imported items without a UID keep a nil one for the app
+//! to fill in.
+//!
+//! Anything else the model can't hold (other event properties, a TZID, a
+//! DTEND other than the next day, components nested in a VEVENT) is not
+//! dropped silently: import returns an `Issue` for each so the caller can
+//! say what was left out or changed.
+//!
+//! No I/O: export builds a string and import parses one. Callers choose
+//! which events to pass in, e.g. leaving out secret calendars.
+
+extern crate alloc;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::planner::{fit_title, Date, Event, Priority, Task, Time, Uid, EVENT_TITLE_MAX, TASK_TITLE_MAX};
+
+const PRODID: &str = "-//Precursor//Day Planner//EN";
+
+/// Longest content line, in octets, before it's folded.
+const FOLD_AT: usize = 75;
+
+/// Input that can't be read as iCalendar at all.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IcalError {
+    /// No `BEGIN:VCALENDAR` ... `END:VCALENDAR`.
+    NotCalendar,
+    /// A line that isn't `NAME[;PARAM=...]:VALUE`.
+    BadLine(usize),
+    /// `END` that doesn't match its `BEGIN`, or input ending mid-component.
+    Unbalanced(usize, String),
+}
+
+impl fmt::Display for IcalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IcalError::NotCalendar => write!(f, "not an iCalendar file"),
+            IcalError::BadLine(n) => write!(f, "line {}: not a content line", n),
+            IcalError::Unbalanced(n, name) => write!(f, "line {}: unbalanced {}", n, name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// No field for it; left out.
+    Unsupported,
+    /// Imported, but changed to fit the model.
+    Approximated(&'static str),
+    /// Couldn't be parsed; left out. For a component, the whole item was.
+    Invalid,
+}
+
+/// Something in the input that didn't make it into the planner as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub line: usize,
+    /// Property or component name.
+    pub name: String,
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            IssueKind::Unsupported => write!(f, "line {}: {} not supported, skipped", self.line, self.name),
+            IssueKind::Approximated(how) => write!(f, "line {}: {}: {}", self.line, self.name, how),
+            IssueKind::Invalid => write!(f, "line {}: invalid {}, skipped", self.line, self.name),
+        }
+    }
+}
+
+/// The result of an import: what could be read, and what couldn't.
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub events: Vec<Event>,
+    pub tasks: Vec<Task>,
+    pub issues: Vec<Issue>,
+}
+
+// --- Export ---
+
+/// Write events and tasks as one VCALENDAR. `stamp` is used for DTSTAMP.
+pub fn export<'a>(
+    events: impl IntoIterator<Item = &'a Event>,
+    tasks: impl IntoIterator<Item = &'a Task>,
+    stamp: Date,
+) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, &format!("PRODID:{}", PRODID));
+    for ev in events {
+        write_event(&mut out, ev, stamp);
+    }
+    for task in tasks {
+        write_task(&mut out, task, stamp);
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+pub fn export_events<'a>(events: impl IntoIterator<Item = &'a Event>, stamp: Date) -> String {
+    export(events, core::iter::empty(), stamp)
+}
+
+pub fn export_tasks<'a>(tasks: impl IntoIterator<Item = &'a Task>, stamp: Date) -> String {
+    export(core::iter::empty(), tasks, stamp)
+}
+
+fn write_event(out: &mut String, ev: &Event, stamp: Date) {
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}", ical_uid(ev.uid, "event", ev.id)));
+    push_line(out, &format!("DTSTAMP:{}T000000Z", ical_date(stamp)));
+    match ev.time {
+        None => {
+            push_line(out, &format!("DTSTART;VALUE=DATE:{}", ical_date(ev.date)));
+            push_line(out, &format!("DTEND;VALUE=DATE:{}", ical_date(ev.date.next_day())));
+        }
+        Some(t) => push_line(out, &format!("DTSTART:{}T{:02}{:02}00", ical_date(ev.date), t.hour, t.minute)),
+    }
+    push_line(out, &format!("SUMMARY:{}", escape(&ev.title)));
+    push_line(out, &format!("PRIORITY:{}", priority_to_ical(ev.priority)));
+    push_line(out, "END:VEVENT");
+}
+
+fn write_task(out: &mut String, task: &Task, stamp: Date) {
+    push_line(out, "BEGIN:VTODO");
+    push_line(out, &format!("UID:{}", ical_uid(task.uid, "task", task.id)));
+    push_line(out, &format!("DTSTAMP:{}T000000Z", ical_date(stamp)));
+    push_line(out, &format!("SUMMARY:{}", escape(&task.title)));
+    push_line(out, if task.done { "STATUS:COMPLETED" } else { "STATUS:NEEDS-ACTION" });
+    push_line(out, &format!("PRIORITY:{}", priority_to_ical(task.priority)));
+    let mut depth = 0;
+    for line in task.extra.iter() {
+        match line_name(line).as_str() {
+            "BEGIN" => depth += 1,
+            "END" => depth -= 1,
+            // Reopened since it was imported.
+            "COMPLETED" | "PERCENT-COMPLETE" if depth == 0 && !task.done => continue,
+            _ => {}
+        }
+        push_line(out, line);
+    }
+    push_line(out, "END:VTODO");
+}
+
+/// The property name of a kept content line.
+fn line_name(line: &str) -> String {
+    let end = line.find([';', ':']).unwrap_or(line.len());
+    line[..end].to_ascii_uppercase()
+}
+
+/// Update a task's kept properties after the planner edits it: the
+/// LAST-MODIFIED another app wrote no longer holds, and SEQUENCE counts
+/// one more revision, so apps syncing the task take the new version.
+pub fn note_revision(extra: &mut Vec<String>) {
+    let mut depth = 0;
+    extra.retain_mut(|line| {
+        match line_name(line).as_str() {
+            "BEGIN" => depth += 1,
+            "END" => depth -= 1,
+            "LAST-MODIFIED" if depth == 0 => return false,
+            "SEQUENCE" if depth == 0 => {
+                let n = line.rsplit(':').next().and_then(|v| v.trim().parse::<u32>().ok()).unwrap_or(0);
+                *line = format!("SEQUENCE:{}", n.saturating_add(1));
+            }
+            _ => {}
+        }
+        true
+    });
+}
+
+fn ical_uid(uid: Uid, kind: &str, id: u32) -> String {
+    if uid.is_nil() {
+        format!("planner-{}-{}@precursor", kind, id)
+    } else {
+        format!("{}", uid)
+    }
+}
+
+fn uid_from_ical(value: &str) -> Uid {
+    let value = unescape(value.trim());
+    Uid::parse(&value).unwrap_or_else(|| Uid::from_name(&value))
+}
+
+fn ical_date(d: Date) -> String {
+    format!("{:04}{:02}{:02}", d.year, d.month, d.day)
+}
+
+fn priority_to_ical(p: Priority) -> u8 {
+    match p {
+        Priority::High => 1,
+        Priority::Normal => 5,
+        Priority::Low => 9,
+    }
+}
+
+fn priority_from_ical(n: u8) -> Priority {
+    match n {
+        1..=4 => Priority::High,
+        6..=9 => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+/// Append one content line, folded at `FOLD_AT` octets without splitting
+/// a character, and terminated with CRLF.
+fn push_line(out: &mut String, line: &str) {
+    let mut room = FOLD_AT;
+    let mut used = 0;
+    for c in line.chars() {
+        if used + c.len_utf8() > room {
+            out.push_str("\r\n ");
+            room = FOLD_AT - 1;
+            used = 0;
+        }
+        out.push(c);
+        used += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// --- Parsing ---
+
+#[derive(Debug)]
+struct Property {
+    line: usize,
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+    /// The whole unfolded line, for properties kept as they are.
+    raw: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn issue(&self, kind: IssueKind) -> Issue {
+        Issue { line: self.line, name: self.name.clone(), kind }
+    }
+}
+
+#[derive(Debug)]
+struct Component {
+    line: usize,
+    name: String,
+    props: Vec<Property>,
+    children: Vec<Component>,
+}
+
+impl Component {
+    fn issue(&self, kind: IssueKind) -> Issue {
+        Issue { line: self.line, name: self.name.clone(), kind }
+    }
+
+    /// The component as unfolded content lines, BEGIN to END.
+    fn write_lines(&self, out: &mut Vec<String>) {
+        out.push(format!("BEGIN:{}", self.name));
+        out.extend(self.props.iter().map(|p| p.raw.clone()));
+        for child in self.children.iter() {
+            child.write_lines(out);
+        }
+        out.push(format!("END:{}", self.name));
+    }
+}
+
+/// Join folded lines back together, keeping the number of each one's
+/// first physical line.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (i, raw) in input.split('\n').enumerate() {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some((_, prev))) => prev.push_str(rest),
+            _ if raw.trim().is_empty() => {}
+            _ => lines.push((i + 1, raw.to_string())),
+        }
+    }
+    lines
+}
+
+/// Split `NAME;P1=a;P2="b:c":VALUE`. Colons and semicolons inside quoted
+/// parameter values don't count.
+fn parse_line(line: usize, text: &str) -> Result<Property, IcalError> {
+    let mut quoted = false;
+    let mut cuts = Vec::new();
+    let mut colon = None;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => cuts.push(i),
+            ':' if !quoted => {
+                colon = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let colon = colon.ok_or(IcalError::BadLine(line))?;
+    let head = &text[..colon];
+    let name_end = cuts.first().copied().unwrap_or(colon);
+    let name = head[..name_end].to_ascii_uppercase();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(IcalError::BadLine(line));
+    }
+    let mut params = Vec::new();
+    for (n, &start) in cuts.iter().enumerate() {
+        let end = cuts.get(n + 1).copied().unwrap_or(colon);
+        let param = &text[start + 1..end];
+        let (k, v) = param.split_once('=').ok_or(IcalError::BadLine(line))?;
+        params.push((k.to_ascii_uppercase(), v.trim_matches('"').to_string()));
+    }
+    Ok(Property { line, name, params, value: text[colon + 1..].to_string(), raw: text.to_string() })
+}
+
+/// Parse into top-level components, every one of which must be a VCALENDAR.
+fn parse(input: &str) -> Result<Vec<Component>, IcalError> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<Component> = Vec::new();
+    for (line, text) in unfold(input) {
+        let prop = parse_line(line, &text)?;
+        match prop.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                line,
+                name: prop.value.trim().to_ascii_uppercase(),
+                props: Vec::new(),
+                children: Vec::new(),
+            }),
+            "END" => {
+                let name = prop.value.trim().to_ascii_uppercase();
+                let comp = match stack.pop() {
+                    Some(c) if c.name == name => c,
+                    _ => return Err(IcalError::Unbalanced(line, name)),
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(comp),
+                    None => roots.push(comp),
+                }
+            }
+            _ => match stack.last_mut() {
+                Some(comp) => comp.props.push(prop),
+                None => return Err(IcalError::NotCalendar),
+            },
+        }
+    }
+    if let Some(open) = stack.pop() {
+        return Err(IcalError::Unbalanced(open.line, open.name));
+    }
+    if roots.is_empty() || roots.iter().any(|c| c.name != "VCALENDAR") {
+        return Err(IcalError::NotCalendar);
+    }
+    Ok(roots)
+}
+
+fn parse_date(s: &str) -> Option<Date> {
+    if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Date::parse(&format!("{}-{}-{}", &s[..4], &s[4..6], &s[6..]))
+}
+
+/// Read a DTSTART/DTEND value as a date and optional time, noting any
+/// time zone or seconds that had to be dropped.
+fn parse_when(p: &Property, issues: &mut Vec<Issue>) -> Option<(Date, Option<Time>)> {
+    let value = p.value.trim();
+    let Some((date, time)) = value.split_once('T') else {
+        return parse_date(value).map(|d| (d, None));
+    };
+    if p.param("VALUE") == Some("DATE") {
+        return None;
+    }
+    let date = parse_date(date)?;
+    let (time, utc) = match time.strip_suffix('Z') {
+        Some(t) => (t, true),
+        None => (time, false),
+    };
+    if time.len() != 6 || !time.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| time[i..i + 2].parse::<u8>().ok();
+    let (hour, minute, second) = (field(0)?, field(2)?, field(4)?);
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    if utc {
+        issues.push(p.issue(IssueKind::Approximated("UTC time read as local time")));
+    } else if p.param("TZID").is_some() {
+        issues.push(p.issue(IssueKind::Approximated("time zone ignored")));
+    }
+    if second != 0 {
+        issues.push(p.issue(IssueKind::Approximated("seconds dropped")));
+    }
+    Some((date, Some(Time::new(hour, minute))))
+}
+
+/// Properties of VCALENDAR itself that need no field.
+fn is_calendar_header(name: &str) -> bool {
+    matches!(name, "VERSION" | "PRODID" | "CALSCALE" | "METHOD")
+}
+
+/// Read the VEVENTs and VTODOs in `input`. Imported items get IDs from
+/// `next_id` on, which is advanced past them.
+pub fn import(input: &str, next_id: &mut u32) -> Result<Imported, IcalError> {
+    let mut out = Imported::default();
+    for cal in parse(input)? {
+        for p in cal.props.iter().filter(|p| !is_calendar_header(&p.name)) {
+            out.issues.push(p.issue(IssueKind::Unsupported));
+        }
+        for comp in cal.children.iter() {
+            match comp.name.as_str() {
+                "VEVENT" => {
+                    if let Some(mut ev) = event_from(comp, &mut out.issues) {
+                        ev.id = *next_id;
+                        *next_id += 1;
+                        out.events.push(ev);
+                    }
+                }
+                "VTODO" => {
+                    if let Some(mut task) = task_from(comp, &mut out.issues) {
+                        task.id = *next_id;
+                        *next_id += 1;
+                        out.tasks.push(task);
+                    }
+                }
+                _ => out.issues.push(comp.issue(IssueKind::Unsupported)),
+            }
+        }
+    }
+    out.issues.sort_by_key(|i| i.line);
+    Ok(out)
+}
+
+fn event_from(comp: &Component, issues: &mut Vec<Issue>) -> Option<Event> {
+    let mut summary = None;
+    let mut start = None;
+    let mut end = None;
+    let mut priority = Priority::Normal;
+    let mut uid = Uid::NIL;
+    for p in comp.props.iter() {
+        match p.name.as_str() {
+            "SUMMARY" => summary = Some(p),
+            "DTSTART" => match parse_when(p, issues) {
+                Some(when) => start = Some(when),
+                None => issues.push(p.issue(IssueKind::Invalid)),
+            },
+            "DTEND" => end = Some(p),
+            "PRIORITY" => read_priority(p, &mut priority, issues),
+            "UID" => uid = uid_from_ical(&p.value),
+            "DTSTAMP" => {}
+            _ => issues.push(p.issue(IssueKind::Unsupported)),
+        }
+    }
+    for child in comp.children.iter() {
+        issues.push(child.issue(IssueKind::Unsupported));
+    }
+    let Some((date, time)) = start else {
+        issues.push(comp.issue(IssueKind::Invalid));
+        return None;
+    };
+    let title = title_from(comp, summary, EVENT_TITLE_MAX, issues)?;
+    if let Some(p) = end {
+        let mut scratch = Vec::new();
+        let plain_all_day = time.is_none() && parse_when(p, &mut scratch) == Some((date.next_day(), None));
+        if !plain_all_day {
+            issues.push(p.issue(IssueKind::Approximated("end dropped; events have only a start")));
+        }
+    }
+    let mut ev = Event::new(0, date, title);
+    ev.uid = uid;
+    ev.time = time;
+    ev.priority = priority;
+    Some(ev)
+}
+
+/// The SUMMARY as a title the planner can store. If it's missing or
+/// blank, the item is invalid.
+fn title_from(comp: &Component, summary: Option<&Property>, max: usize, issues: &mut Vec<Issue>) -> Option<String> {
+    let Some((title, shortened)) = summary.and_then(|p| fit_title(&unescape(&p.value), max)) else {
+        if let Some(p) = summary {
+            issues.push(p.issue(IssueKind::Invalid));
+        }
+        issues.push(comp.issue(IssueKind::Invalid));
+        return None;
+    };
+    if shortened {
+        issues.push(summary.unwrap().issue(IssueKind::Approximated("title shortened")));
+    }
+    Some(title)
+}
+
+fn read_priority(p: &Property, priority: &mut Priority, issues: &mut Vec<Issue>) {
+    match p.value.trim().parse::<u8>() {
+        Ok(n) if n <= 9 => *priority = priority_from_ical(n),
+        _ => issues.push(p.issue(IssueKind::Invalid)),
+    }
+}
+
+/// A VTODO with a usable title always yields a task; at worst an open
+/// one.
+fn task_from(comp: &Component, issues: &mut Vec<Issue>) -> Option<Task> {
+    let mut task = Task::new(0, String::new());
+    let mut summary = None;
+    let mut status = None;
+    let mut finished = false;
+    for p in comp.props.iter() {
+        match p.name.as_str() {
+            "SUMMARY" => summary = Some(p),
+            "STATUS" => match p.value.trim().to_ascii_uppercase().as_str() {
+                "NEEDS-ACTION" => status = Some(false),
+                "COMPLETED" => status = Some(true),
+                "IN-PROCESS" => {
+                    issues.push(p.issue(IssueKind::Approximated("in progress read as not done")));
+                    status = Some(false);
+                }
+                "CANCELLED" => {
+                    issues.push(p.issue(IssueKind::Approximated("cancelled read as done")));
+                    status = Some(true);
+                }
+                _ => issues.push(p.issue(IssueKind::Invalid)),
+            },
+            "COMPLETED" => {
+                finished = true;
+                task.extra.push(p.raw.clone());
+            }
+            "PERCENT-COMPLETE" => match p.value.trim().parse::<u8>() {
+                Ok(n) if n <= 100 => {
+                    finished |= n == 100;
+                    task.extra.push(p.raw.clone());
+                }
+                _ => issues.push(p.issue(IssueKind::Invalid)),
+            },
+            "PRIORITY" => read_priority(p, &mut task.priority, issues),
+            "UID" => task.uid = uid_from_ical(&p.value),
+            "DTSTAMP" => {}
+            _ => task.extra.push(p.raw.clone()),
+        }
+    }
+    for child in comp.children.iter() {
+        child.write_lines(&mut task.extra);
+    }
+    task.title = title_from(comp, summary, TASK_TITLE_MAX, issues)?;
+    task.done = status.unwrap_or(finished);
+    Some(task)
+}
@@ -0,0 +1,29 @@
+//! Day Planner without the device: the model, the screen state machine,
+//! storage and every import/export format.
+//!
+//! Nothing here talks to Xous. Storage goes through a `Backend` (PDDB on
+//! device, `MemBackend` in tests), the UI is `PlannerApp` fed key by key,
+//! and drawing is left to whoever holds the app. The planner binary is a
+//! thin shell over this crate: GAM drawing, PDDB, the TRNG and IPC.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod app;
+pub mod backend;
+pub mod backup;
+pub mod calendar;
+pub mod codec;
+pub mod csv;
+pub mod dedup;
+pub mod ical;
+pub mod org;
+pub mod planner;
+pub mod query;
+pub mod schema;
+pub mod seal;
+pub mod storage;
+pub mod todotxt;
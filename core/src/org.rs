@@ -0,0 +1,92 @@
+//! Org-mode export for Day Planner.
+//!
+//! ```text
+//! * Events
+//! ** [#A] Stand-up
+//! :PROPERTIES:
+//! :ID:       0f8fad5b-d9cb-469f-a165-70867728950e
+//! :END:
+//! <2026-03-04 Wed 09:00>
+//! ** Holiday
+//! <2026-03-05 Thu>
+//! * Tasks
+//! ** TODO [#A] Call Bob
+//! ** DONE Buy milk
+//! ```
+//!
+//! Events are headings with an active timestamp (date only when all-day),
+//! in date and time order. Tasks are TODO/DONE headings in task-list order.
+//! Each heading's `ID` property is the item's uid (left out when it has
+//! none), which Org uses for links and other tools for matching.
+//! Priority cookies follow Org's default A–C scale: High is `[#A]`, Low is
+//! `[#C]`, and Normal gets none since B is Org's default.
+
+extern crate alloc;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::planner::{sort_events, sort_tasks, Date, Event, Priority, Task, Uid};
+
+/// Export events (those from `range.0` to `range.1` inclusive, or all of
+/// them for `None`) and every task as an Org document.
+pub fn export(events: &[Event], tasks: &[Task], range: Option<(Date, Date)>) -> String {
+    let mut out = String::from("#+TITLE: Day Planner\n");
+    if let Some((from, to)) = range {
+        out.push_str(&format!("#+DATE: [{}]--[{}]\n", timestamp_date(from), timestamp_date(to)));
+    }
+
+    let mut chosen: Vec<Event> = events
+        .iter()
+        .filter(|e| range.is_none_or(|(from, to)| e.date >= from && e.date <= to))
+        .cloned()
+        .collect();
+    sort_events(&mut chosen);
+    chosen.sort_by_key(|e| e.date);
+    out.push_str("* Events\n");
+    for ev in chosen.iter() {
+        out.push_str(&format!("** {}{}\n", cookie(ev.priority), heading_text(&ev.title)));
+        push_id(&mut out, ev.uid);
+        let stamp = match ev.time {
+            Some(t) => format!("{} {:02}:{:02}", timestamp_date(ev.date), t.hour, t.minute),
+            None => timestamp_date(ev.date),
+        };
+        out.push_str(&format!("<{}>\n", stamp));
+    }
+
+    let mut sorted = tasks.to_vec();
+    sort_tasks(&mut sorted);
+    out.push_str("* Tasks\n");
+    for task in sorted.iter() {
+        let keyword = if task.done { "DONE" } else { "TODO" };
+        out.push_str(&format!("** {} {}{}\n", keyword, cookie(task.priority), heading_text(&task.title)));
+        push_id(&mut out, task.uid);
+    }
+    out
+}
+
+/// A property drawer holding the uid; it must come straight after the
+/// heading.
+fn push_id(out: &mut String, uid: Uid) {
+    if !uid.is_nil() {
+        out.push_str(&format!(":PROPERTIES:\n:ID:       {}\n:END:\n", uid));
+    }
+}
+
+/// `2026-03-04 Wed`, the inside of an Org timestamp.
+fn timestamp_date(d: Date) -> String {
+    format!("{} {}", d.display(), d.weekday_name())
+}
+
+fn cookie(p: Priority) -> &'static str {
+    match p {
+        Priority::High => "[#A] ",
+        Priority::Normal => "",
+        Priority::Low => "[#C] ",
+    }
+}
+
+/// Headings are one line.
+fn heading_text(title: &str) -> String {
+    title.split(['\r', '\n']).filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
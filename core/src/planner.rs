@@ -0,0 +1,615 @@
+//! Data models for the Day Planner.
+//!
+//! Events have a date, optional time, and description.
+//! Tasks have a description and done/not-done status.
+//! Dates are stored as (year, month, day) tuples — no floating point needed.
+//! `agenda_markdown` and `agenda_text` render a date range for pasting elsewhere.
+//!
+//! Every event and task has two identifiers: `id`, a small local handle
+//! from the app's counter, and `uid`, a random 128-bit id that stays with
+//! the item across devices, exports and imports. Anything matching items
+//! from elsewhere goes by `uid`; `id` is only unique on one device.
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use serde::{Deserialize, Serialize};
+
+/// A calendar date (year, month, day).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Self {
+        Self { year, month, day }
+    }
+
+    pub fn display(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    pub fn short_display(&self) -> String {
+        format!("{:02}/{:02}", self.month, self.day)
+    }
+
+    /// Parse a `YYYY-MM-DD` string (the `display()` format).
+    pub fn parse(s: &str) -> Option<Date> {
+        let mut parts = s.trim().splitn(3, '-');
+        let year = parts.next()?.parse::<u16>().ok()?;
+        let month = parts.next()?.parse::<u8>().ok()?;
+        let day = parts.next()?.parse::<u8>().ok()?;
+        let date = Date::new(year, month, day);
+        date.is_valid().then_some(date)
+    }
+
+    /// A real day: month 1–12, day within the month.
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month) && self.day >= 1 && self.day <= Date::days_in_month(self.year, self.month)
+    }
+
+    /// Days since 1970-01-01 (negative before). Howard Hinnant's civil algorithm.
+    pub fn to_days(&self) -> i32 {
+        let y = self.year as i32 - if self.month <= 2 { 1 } else { 0 };
+        let era = if y >= 0 { y } else { y - 399 } / 400;
+        let yoe = y - era * 400;
+        let m = self.month as i32;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i32 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    /// Inverse of `to_days`.
+    pub fn from_days(days: i32) -> Date {
+        let z = days + 719468;
+        let era = if z >= 0 { z } else { z - 146096 } / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as u16;
+        Date::new(year, month, day)
+    }
+
+    /// Move forward (or back, if negative) by `n` days.
+    pub fn add_days(&self, n: i32) -> Date {
+        Date::from_days(self.to_days() + n)
+    }
+
+    /// Days in the given month (handles leap years).
+    pub fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 => {
+                if (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400) {
+                    29
+                } else {
+                    28
+                }
+            }
+            _ => 30,
+        }
+    }
+
+    /// Advance by one day.
+    pub fn next_day(&self) -> Date {
+        let dim = Date::days_in_month(self.year, self.month);
+        if self.day < dim {
+            Date::new(self.year, self.month, self.day + 1)
+        } else if self.month < 12 {
+            Date::new(self.year, self.month + 1, 1)
+        } else {
+            Date::new(self.year + 1, 1, 1)
+        }
+    }
+
+    /// Go back one day.
+    pub fn prev_day(&self) -> Date {
+        if self.day > 1 {
+            Date::new(self.year, self.month, self.day - 1)
+        } else if self.month > 1 {
+            let prev_month = self.month - 1;
+            let dim = Date::days_in_month(self.year, prev_month);
+            Date::new(self.year, prev_month, dim)
+        } else {
+            Date::new(self.year - 1, 12, 31)
+        }
+    }
+
+    /// Day of week (0=Sunday, 6=Saturday) — Zeller's formula.
+    pub fn weekday(&self) -> u8 {
+        let mut y = self.year as i32;
+        let mut m = self.month as i32;
+        if m < 3 {
+            m += 12;
+            y -= 1;
+        }
+        let q = self.day as i32;
+        let k = y % 100;
+        let j = y / 100;
+        let h = (q + (13 * (m + 1)) / 5 + k + k / 4 + j / 4 - 2 * j) % 7;
+        ((h + 7) % 7) as u8 // 0=Sat in Zeller, convert: (h+6)%7 for 0=Mon... actually:
+        // Zeller: 0=Sat, 1=Sun, 2=Mon... Let's convert to 0=Sun:
+        // (h + 6) % 7 gives 0=Sun
+    }
+
+    /// Day of week with 0=Sunday, using Tomohiko Sakamoto's algorithm.
+    pub fn day_of_week(&self) -> u8 {
+        let t = [0i32, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let mut y = self.year as i32;
+        if self.month < 3 {
+            y -= 1;
+        }
+        let dow = (y + y / 4 - y / 100 + y / 400 + t[(self.month - 1) as usize] + self.day as i32) % 7;
+        dow as u8 // 0=Sunday
+    }
+
+    pub fn weekday_name(&self) -> &'static str {
+        match self.day_of_week() {
+            0 => "Sun",
+            1 => "Mon",
+            2 => "Tue",
+            3 => "Wed",
+            4 => "Thu",
+            5 => "Fri",
+            6 => "Sat",
+            _ => "???",
+        }
+    }
+
+    pub fn month_name(month: u8) -> &'static str {
+        match month {
+            1 => "January",
+            2 => "February",
+            3 => "March",
+            4 => "April",
+            5 => "May",
+            6 => "June",
+            7 => "July",
+            8 => "August",
+            9 => "September",
+            10 => "October",
+            11 => "November",
+            12 => "December",
+            _ => "???",
+        }
+    }
+}
+
+/// A time of day (hour, minute) in 24h format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl Time {
+    pub fn new(hour: u8, minute: u8) -> Self {
+        Self {
+            hour: hour.min(23),
+            minute: minute.min(59),
+        }
+    }
+
+    /// Within 00:00–23:59. `new` clamps; values from elsewhere may not be.
+    pub fn is_valid(&self) -> bool {
+        self.hour < 24 && self.minute < 60
+    }
+
+    pub fn display(&self) -> String {
+        let (h12, ampm) = if self.hour == 0 {
+            (12, "AM")
+        } else if self.hour < 12 {
+            (self.hour, "AM")
+        } else if self.hour == 12 {
+            (12, "PM")
+        } else {
+            (self.hour - 12, "PM")
+        };
+        format!("{}:{:02}{}", h12, self.minute, ampm)
+    }
+}
+
+/// Priority level for events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Priority::Low => " ",
+            Priority::Normal => "*",
+            Priority::High => "!",
+        }
+    }
+
+    /// Ordering rank: Low=0, Normal=1, High=2.
+    pub fn level(&self) -> u8 {
+        match self {
+            Priority::Low => 0,
+            Priority::Normal => 1,
+            Priority::High => 2,
+        }
+    }
+
+    pub fn cycle(&self) -> Priority {
+        match self {
+            Priority::Low => Priority::Normal,
+            Priority::Normal => Priority::High,
+            Priority::High => Priority::Low,
+        }
+    }
+}
+
+/// Identifies a calendar in the registry (see `calendar.rs`).
+pub type CalendarId = u8;
+
+/// A globally unique, stable item id: 128 random bits laid out as a
+/// version-4 UUID. Shown and stored in text as
+/// `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`; binary encodings keep the 16
+/// raw bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Uid(pub [u8; 16]);
+
+impl Uid {
+    /// Not yet assigned. Items read from older data or from imports
+    /// without ids start out nil and are given one by the app.
+    pub const NIL: Uid = Uid([0; 16]);
+
+    pub fn is_nil(&self) -> bool {
+        *self == Uid::NIL
+    }
+
+    /// Parse the hyphenated form, in either case.
+    pub fn parse(s: &str) -> Option<Uid> {
+        let s = s.trim();
+        let hex: Vec<u8> = s.bytes().filter(|&b| b != b'-').collect();
+        if s.len() != 36
+            || hex.len() != 32
+            || !hex.iter().all(u8::is_ascii_hexdigit)
+            || [8, 13, 18, 23].iter().any(|&i| s.as_bytes()[i] != b'-')
+        {
+            return None;
+        }
+        let mut bytes = [0u8; 16];
+        for (i, pair) in hex.chunks(2).enumerate() {
+            let text = core::str::from_utf8(pair).ok()?;
+            bytes[i] = u8::from_str_radix(text, 16).ok()?;
+        }
+        Some(Uid(bytes))
+    }
+
+    /// A uid derived from a name, for items from elsewhere that carry some
+    /// other kind of identifier (an iCalendar UID, say): the same name
+    /// always gives the same uid. FNV-1a, marked as a version-8 UUID so it
+    /// can't clash with a random one.
+    pub fn from_name(name: &str) -> Uid {
+        const OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+        const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+        let mut hash = OFFSET;
+        for b in name.bytes() {
+            hash ^= b as u128;
+            hash = hash.wrapping_mul(PRIME);
+        }
+        let mut bytes = hash.to_be_bytes();
+        bytes[6] = (bytes[6] & 0x0f) | 0x80;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uid(bytes)
+    }
+}
+
+impl core::fmt::Display for Uid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Uid {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(self)
+        } else {
+            self.0.serialize(s)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Uid {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            let text = String::deserialize(d)?;
+            Uid::parse(&text).ok_or_else(|| serde::de::Error::custom("bad uid"))
+        } else {
+            <[u8; 16]>::deserialize(d).map(Uid)
+        }
+    }
+}
+
+/// Hands out random uids. Seeded once from the TRNG at startup, then
+/// xoroshiro128++; cheap enough to call for every new item.
+#[derive(Debug, Clone)]
+pub struct UidGen {
+    s0: u64,
+    s1: u64,
+}
+
+impl UidGen {
+    pub fn new(seed: [u8; 16]) -> Self {
+        let mut half = [0u8; 8];
+        half.copy_from_slice(&seed[..8]);
+        let s0 = u64::from_le_bytes(half);
+        half.copy_from_slice(&seed[8..]);
+        let s1 = u64::from_le_bytes(half);
+        // An all-zero state would only ever produce zeros.
+        if s0 == 0 && s1 == 0 {
+            return Self { s0: 0x9e37_79b9_7f4a_7c15, s1: 0xbf58_476d_1ce4_e5b9 };
+        }
+        Self { s0, s1 }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let (s0, mut s1) = (self.s0, self.s1);
+        let out = s0.wrapping_add(s1).rotate_left(17).wrapping_add(s0);
+        s1 ^= s0;
+        self.s0 = s0.rotate_left(49) ^ s1 ^ (s1 << 21);
+        self.s1 = s1.rotate_left(28);
+        out
+    }
+
+    pub fn generate(&mut self) -> Uid {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uid(bytes)
+    }
+}
+
+/// A scheduled event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: u32,
+    /// Nil in data from before uids existed; the app fills it in on load.
+    #[serde(default)]
+    pub uid: Uid,
+    pub date: Date,
+    pub time: Option<Time>,
+    pub title: String,
+    pub priority: Priority,
+    /// Which calendar the event belongs to. Not stored in the record: it's
+    /// implied by the dictionary the record is in.
+    #[serde(skip)]
+    pub calendar: CalendarId,
+}
+
+impl Event {
+    pub fn new(id: u32, date: Date, title: String) -> Self {
+        Self {
+            id,
+            uid: Uid::NIL,
+            date,
+            time: None,
+            title,
+            priority: Priority::Normal,
+            calendar: 0,
+        }
+    }
+
+    pub fn time_display(&self) -> String {
+        match &self.time {
+            Some(t) => t.display(),
+            None => String::from("All day"),
+        }
+    }
+}
+
+/// A task/to-do item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: u32,
+    #[serde(default)]
+    pub uid: Uid,
+    pub title: String,
+    pub done: bool,
+    pub priority: Priority,
+    /// iCalendar properties the planner has no field for (DESCRIPTION,
+    /// DUE, ...), as unfolded content lines, so exporting the task writes
+    /// them back.
+    #[serde(default)]
+    pub extra: Vec<String>,
+}
+
+impl Task {
+    pub fn new(id: u32, title: String) -> Self {
+        Self {
+            id,
+            uid: Uid::NIL,
+            title,
+            done: false,
+            priority: Priority::Normal,
+            extra: Vec::new(),
+        }
+    }
+}
+
+/// Longest titles the entry screens take, and so the longest that fit
+/// where they're shown.
+pub const EVENT_TITLE_MAX: usize = 40;
+pub const TASK_TITLE_MAX: usize = 50;
+
+/// A title as it would be stored: trimmed, non-empty, no longer than
+/// `max` characters and free of control characters.
+pub fn clean_title(title: &str, max: usize) -> Option<String> {
+    let title = title.trim();
+    if title.is_empty() || title.chars().count() > max || title.chars().any(char::is_control) {
+        return None;
+    }
+    Some(String::from(title))
+}
+
+/// An imported title made storable: each run of line breaks or other
+/// control characters becomes a space, and anything past `max` characters
+/// is cut off. `None` if it's blank; the flag says whether it was cut.
+pub fn fit_title(title: &str, max: usize) -> Option<(String, bool)> {
+    let mut flat = String::with_capacity(title.len());
+    let mut chars = title.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_control() {
+            flat.push(c);
+        } else if !chars.peek().is_some_and(|n| n.is_control()) {
+            flat.push(' ');
+        }
+    }
+    let flat = flat.trim();
+    if flat.is_empty() {
+        return None;
+    }
+    let shortened = flat.chars().count() > max;
+    let cut: String = flat.chars().take(max).collect();
+    Some((String::from(cut.trim_end()), shortened))
+}
+
+/// Sort events by time (all-day first, then by hour:minute).
+pub fn sort_events(events: &mut [Event]) {
+    events.sort_by(|a, b| {
+        let time_a = a.time.map(|t| (t.hour as u16) * 60 + t.minute as u16).unwrap_or(0);
+        let time_b = b.time.map(|t| (t.hour as u16) * 60 + t.minute as u16).unwrap_or(0);
+        let has_time_a = a.time.is_some() as u8;
+        let has_time_b = b.time.is_some() as u8;
+        has_time_a.cmp(&has_time_b).then(time_a.cmp(&time_b))
+    });
+}
+
+/// Sort tasks: incomplete first, then by priority (high first).
+pub fn sort_tasks(tasks: &mut [Task]) {
+    tasks.sort_by(|a, b| {
+        a.done.cmp(&b.done).then_with(|| {
+            let pa = match a.priority {
+                Priority::High => 0,
+                Priority::Normal => 1,
+                Priority::Low => 2,
+            };
+            let pb = match b.priority {
+                Priority::High => 0,
+                Priority::Normal => 1,
+                Priority::Low => 2,
+            };
+            pa.cmp(&pb)
+        })
+    });
+}
+
+/// Render an agenda from `from` to `to` (inclusive) as Markdown: a heading
+/// per day with its events, then a checklist of every task.
+pub fn agenda_markdown(events: &[Event], tasks: &[Task], from: Date, to: Date) -> String {
+    let mut out = format!("# Agenda {} – {}\n", from.display(), to.display());
+    for_each_day(events, from, to, |date, day| {
+        out.push_str(&format!("\n## {} {}\n\n", date.weekday_name(), date.display()));
+        if day.is_empty() {
+            out.push_str("_Nothing scheduled_\n");
+        }
+        for ev in day {
+            out.push_str(&format!("- {}{} {}\n", ev.time_display(), md_marker(ev.priority), md_escape(&ev.title)));
+        }
+    });
+    out.push_str("\n## Tasks\n\n");
+    if tasks.is_empty() {
+        out.push_str("_No tasks_\n");
+    }
+    for task in sorted_tasks(tasks) {
+        let check = if task.done { "x" } else { " " };
+        out.push_str(&format!("- [{}]{} {}\n", check, md_marker(task.priority), md_escape(&task.title)));
+    }
+    out
+}
+
+/// The same agenda as plain text, indented rather than marked up.
+pub fn agenda_text(events: &[Event], tasks: &[Task], from: Date, to: Date) -> String {
+    let mut out = format!("Agenda {} - {}\n", from.display(), to.display());
+    for_each_day(events, from, to, |date, day| {
+        out.push_str(&format!("\n{} {}\n", date.weekday_name(), date.display()));
+        if day.is_empty() {
+            out.push_str("  (nothing scheduled)\n");
+        }
+        for ev in day {
+            out.push_str(&format!("  {:>7} {} {}\n", ev.time_display(), ev.priority.marker(), one_line(&ev.title)));
+        }
+    });
+    out.push_str("\nTasks\n");
+    if tasks.is_empty() {
+        out.push_str("  (none)\n");
+    }
+    for task in sorted_tasks(tasks) {
+        let check = if task.done { "x" } else { " " };
+        out.push_str(&format!("  [{}] {} {}\n", check, task.priority.marker(), one_line(&task.title)));
+    }
+    out
+}
+
+/// Call `f` with each date in the range and its events, in time order.
+fn for_each_day(events: &[Event], from: Date, to: Date, mut f: impl FnMut(Date, &[Event])) {
+    let mut date = from;
+    while date <= to {
+        let mut day: Vec<Event> = events.iter().filter(|e| e.date == date).cloned().collect();
+        sort_events(&mut day);
+        f(date, &day);
+        date = date.next_day();
+    }
+}
+
+fn sorted_tasks(tasks: &[Task]) -> Vec<Task> {
+    let mut sorted = tasks.to_vec();
+    sort_tasks(&mut sorted);
+    sorted
+}
+
+fn one_line(text: &str) -> String {
+    text.split(['\r', '\n']).filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Priority marker for Markdown: nothing for Low, escaped otherwise.
+fn md_marker(p: Priority) -> &'static str {
+    match p {
+        Priority::Low => "",
+        Priority::Normal => " \\*",
+        Priority::High => " !",
+    }
+}
+
+/// Keep a title from being read as Markdown syntax.
+fn md_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in one_line(text).chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,318 @@
+//! Saved-filter query language for Day Planner.
+//!
+//! A query is a whitespace-separated list of terms, all of which must match:
+//!
+//!   priority:high      — exact priority (also `p:`, `pri:`; `>=normal` etc.)
+//!   due:<7d            — dated within the next 7 days (also `date:`)
+//!   due:2026-03-04     — absolute date; `today`, `tomorrow`, `Nd`, `Nw` also work
+//!   done / is:pending  — completion state (events are never done)
+//!   is:event, is:task  — item kind (also `type:`)
+//!   tag:work           — title contains `#work`
+//!   lunch, "two words" — case-insensitive title substring
+//!
+//! Prefix any term with `!` (or `-`) to negate it. New fields are added by
+//! extending `Predicate` and the match in `parse_field`.
+
+extern crate alloc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::planner::{Date, Event, Priority, Task};
+
+/// A named query, persisted in PDDB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub name: String,
+    pub text: String,
+}
+
+impl SavedQuery {
+    pub fn new(name: String, text: String) -> Self {
+        Self { name, text }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    UnknownField(String),
+    BadValue(String),
+    UnterminatedQuote,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnknownField(k) => write!(f, "unknown field '{}'", k),
+            QueryError::BadValue(v) => write!(f, "bad value '{}'", v),
+            QueryError::UnterminatedQuote => write!(f, "missing closing quote"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cmp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    /// Split a leading comparison operator off a value.
+    fn split(value: &str) -> (Cmp, &str) {
+        if let Some(rest) = value.strip_prefix("<=") {
+            (Cmp::Le, rest)
+        } else if let Some(rest) = value.strip_prefix(">=") {
+            (Cmp::Ge, rest)
+        } else if let Some(rest) = value.strip_prefix('<') {
+            (Cmp::Lt, rest)
+        } else if let Some(rest) = value.strip_prefix('>') {
+            (Cmp::Gt, rest)
+        } else if let Some(rest) = value.strip_prefix('=') {
+            (Cmp::Eq, rest)
+        } else {
+            (Cmp::Eq, value)
+        }
+    }
+
+    fn test<T: Ord>(&self, a: T, b: T) -> bool {
+        match self {
+            Cmp::Eq => a == b,
+            Cmp::Lt => a < b,
+            Cmp::Le => a <= b,
+            Cmp::Gt => a > b,
+            Cmp::Ge => a >= b,
+        }
+    }
+}
+
+/// A date operand; relative dates are resolved against "today" at match time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateSpec {
+    Absolute(Date),
+    Relative(i32),
+}
+
+impl DateSpec {
+    fn resolve(&self, today: Date) -> Date {
+        match self {
+            DateSpec::Absolute(d) => *d,
+            DateSpec::Relative(n) => today.add_days(*n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Event,
+    Task,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Priority(Cmp, Priority),
+    Date(Cmp, DateSpec),
+    Done,
+    Kind(ItemKind),
+    Tag(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub negate: bool,
+    pub pred: Predicate,
+}
+
+/// A parsed query: the conjunction of its terms. An empty query matches everything.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+/// The fields a predicate can look at, borrowed from either an event or a task.
+struct Fields<'a> {
+    kind: ItemKind,
+    title: &'a str,
+    priority: Priority,
+    date: Option<Date>,
+    done: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn of_event(e: &'a Event) -> Self {
+        Self { kind: ItemKind::Event, title: &e.title, priority: e.priority, date: Some(e.date), done: false }
+    }
+
+    fn of_task(t: &'a Task) -> Self {
+        Self { kind: ItemKind::Task, title: &t.title, priority: t.priority, date: None, done: t.done }
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut terms = Vec::new();
+        for token in tokenize(text)? {
+            terms.push(parse_term(&token)?);
+        }
+        Ok(Query { terms })
+    }
+
+    pub fn matches_event(&self, event: &Event, today: Date) -> bool {
+        self.matches(&Fields::of_event(event), today)
+    }
+
+    pub fn matches_task(&self, task: &Task, today: Date) -> bool {
+        self.matches(&Fields::of_task(task), today)
+    }
+
+    fn matches(&self, f: &Fields, today: Date) -> bool {
+        self.terms.iter().all(|t| t.pred.test(f, today) != t.negate)
+    }
+}
+
+impl Predicate {
+    fn test(&self, f: &Fields, today: Date) -> bool {
+        match self {
+            Predicate::Priority(cmp, p) => cmp.test(f.priority.level(), p.level()),
+            Predicate::Date(cmp, spec) => match f.date {
+                Some(d) => {
+                    let target = spec.resolve(today);
+                    // "<7d" means the upcoming window, not everything in the past too.
+                    let upcoming = matches!(spec, DateSpec::Relative(n) if *n > 0)
+                        && matches!(cmp, Cmp::Lt | Cmp::Le);
+                    cmp.test(d, target) && (!upcoming || d >= today)
+                }
+                None => false,
+            },
+            Predicate::Done => f.done,
+            Predicate::Kind(k) => f.kind == *k,
+            Predicate::Tag(tag) => f
+                .title
+                .split_whitespace()
+                .any(|w| w.strip_prefix('#').is_some_and(|t| t.eq_ignore_ascii_case(tag))),
+            Predicate::Text(needle) => f.title.to_ascii_lowercase().contains(needle.as_str()),
+        }
+    }
+}
+
+/// One matching item in query results.
+#[derive(Debug, Clone, Copy)]
+pub enum Hit<'a> {
+    Event(&'a Event),
+    Task(&'a Task),
+}
+
+/// Run a query over all events and tasks: events first (by date), then tasks.
+pub fn run<'a>(query: &Query, events: &'a [Event], tasks: &'a [Task], today: Date) -> Vec<Hit<'a>> {
+    let mut dated: Vec<&Event> = events.iter().filter(|e| query.matches_event(e, today)).collect();
+    dated.sort_by(|a, b| a.date.cmp(&b.date).then(a.time.cmp(&b.time)));
+    let mut hits: Vec<Hit> = dated.into_iter().map(Hit::Event).collect();
+    hits.extend(tasks.iter().filter(|t| query.matches_task(t, today)).map(Hit::Task));
+    hits
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let mut in_quote = false;
+    for c in text.chars() {
+        match c {
+            '"' => in_quote = !in_quote,
+            c if c.is_whitespace() && !in_quote => {
+                if !cur.is_empty() {
+                    tokens.push(core::mem::take(&mut cur));
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if in_quote {
+        return Err(QueryError::UnterminatedQuote);
+    }
+    if !cur.is_empty() {
+        tokens.push(cur);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(token: &str) -> Result<Term, QueryError> {
+    let (negate, body) = match token.strip_prefix('!').or_else(|| token.strip_prefix('-')) {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+    let pred = match body.split_once(':') {
+        Some((key, value))
+            if key.eq_ignore_ascii_case("is")
+                && (value.eq_ignore_ascii_case("pending") || value.eq_ignore_ascii_case("open")) =>
+        {
+            return Ok(Term { negate: !negate, pred: Predicate::Done });
+        }
+        Some((key, value)) => parse_field(&key.to_ascii_lowercase(), value)?,
+        None if body.eq_ignore_ascii_case("done") => Predicate::Done,
+        None => Predicate::Text(body.to_ascii_lowercase()),
+    };
+    Ok(Term { negate, pred })
+}
+
+fn parse_field(key: &str, value: &str) -> Result<Predicate, QueryError> {
+    let bad = || QueryError::BadValue(value.to_string());
+    let lower = value.to_ascii_lowercase();
+    match key {
+        "priority" | "pri" | "p" => {
+            let (cmp, rest) = Cmp::split(&lower);
+            Ok(Predicate::Priority(cmp, parse_priority(rest).ok_or_else(bad)?))
+        }
+        "due" | "date" => {
+            let (cmp, rest) = Cmp::split(&lower);
+            Ok(Predicate::Date(cmp, parse_date_spec(rest).ok_or_else(bad)?))
+        }
+        "is" => match lower.as_str() {
+            "done" | "complete" | "completed" => Ok(Predicate::Done),
+            "event" => Ok(Predicate::Kind(ItemKind::Event)),
+            "task" => Ok(Predicate::Kind(ItemKind::Task)),
+            _ => Err(bad()),
+        },
+        "type" => match lower.as_str() {
+            "event" => Ok(Predicate::Kind(ItemKind::Event)),
+            "task" => Ok(Predicate::Kind(ItemKind::Task)),
+            _ => Err(bad()),
+        },
+        "tag" if !value.is_empty() => Ok(Predicate::Tag(value.trim_start_matches('#').to_string())),
+        "title" | "text" if !value.is_empty() => Ok(Predicate::Text(lower)),
+        "tag" | "title" | "text" => Err(bad()),
+        _ => Err(QueryError::UnknownField(key.to_string())),
+    }
+}
+
+fn parse_priority(s: &str) -> Option<Priority> {
+    match s {
+        "high" | "h" | "!" => Some(Priority::High),
+        "normal" | "n" | "med" | "medium" => Some(Priority::Normal),
+        "low" | "l" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Furthest a relative date may reach, either way: about 100 years.
+const MAX_RELATIVE_DAYS: i32 = 36_525;
+
+fn parse_date_spec(s: &str) -> Option<DateSpec> {
+    match s {
+        "today" => return Some(DateSpec::Relative(0)),
+        "tomorrow" => return Some(DateSpec::Relative(1)),
+        "yesterday" => return Some(DateSpec::Relative(-1)),
+        _ => {}
+    }
+    let days = if let Some(n) = s.strip_suffix('d') {
+        n.parse::<i32>().ok()
+    } else if let Some(n) = s.strip_suffix('w') {
+        n.parse::<i32>().ok().and_then(|w| w.checked_mul(7))
+    } else {
+        return Date::parse(s).map(DateSpec::Absolute);
+    };
+    days.filter(|n| n.abs() <= MAX_RELATIVE_DAYS).map(DateSpec::Relative)
+}
@@ -0,0 +1,243 @@
+//! Stored-data schema versions and migrations.
+//!
+//! Versions:
+//!   0 — bare JSON arrays under `events`/`tasks`/`queries`, no `schema` key
+//!   1 — each collection wrapped in an `Envelope { version, items }`
+//!   2 — events and tasks split into one `Record { version, item }` per key,
+//!       listed by an `index.*` envelope of IDs; queries stay an envelope
+//!   3 — tasks carry the `extra` iCalendar properties they were imported
+//!       with; empty for older ones
+//!   4 — events and tasks carry a `uid`; records upgraded from earlier
+//!       versions have a nil one until the app assigns it on load
+//!
+//! To change `Event` or `Task` incompatibly: bump `SCHEMA_VERSION`, add a
+//! `migrate_vN_to_vN+1` working on `serde_json::Value`, and append it to
+//! `MIGRATIONS`. Migrations must be pure so they can run on a host.
+//! Layout changes (which keys exist) are applied by `Storage::upgrade_schema`
+//! on top of these.
+//!
+//! Envelopes and records go through `codec`, so they may be JSON or binary;
+//! that's orthogonal to the schema version. Data from before v2 is always
+//! JSON, so `MIGRATIONS` only need to handle that. From v2 on it may be
+//! binary, which can't be read without the type it was written from: keep
+//! the old shape as a `*Vn` struct and add a case to `upgrade_event` /
+//! `upgrade_task`. Envelopes whose item type didn't change are `restamp`ed.
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::codec;
+use crate::planner::{Date, Event, Priority, Task, Time};
+
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// First version with one key per event/task.
+pub const PER_RECORD_VERSION: u32 = 2;
+
+/// A versioned collection as stored under one key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub items: Vec<T>,
+}
+
+/// Borrowing twin of `Envelope`, so saving doesn't clone the collection.
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T> {
+    version: u32,
+    items: &'a [T],
+}
+
+/// A single versioned event or task as stored under one key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record<T> {
+    pub version: u32,
+    pub item: T,
+}
+
+#[derive(Serialize)]
+struct RecordRef<'a, T> {
+    version: u32,
+    item: &'a T,
+}
+
+/// Serialize a collection in the current envelope format.
+pub fn encode<T: Serialize>(items: &[T]) -> Vec<u8> {
+    codec::encode(&EnvelopeRef { version: SCHEMA_VERSION, items })
+}
+
+/// Parse a current-format envelope.
+pub fn decode<T: DeserializeOwned>(blob: &[u8]) -> Option<Vec<T>> {
+    let env: Envelope<T> = codec::decode(blob)?;
+    if env.version != SCHEMA_VERSION {
+        return None;
+    }
+    Some(env.items)
+}
+
+/// Serialize one record in the current format.
+pub fn encode_record<T: Serialize>(item: &T) -> Vec<u8> {
+    codec::encode(&RecordRef { version: SCHEMA_VERSION, item })
+}
+
+/// Parse a current-format record.
+pub fn decode_record<T: DeserializeOwned>(blob: &[u8]) -> Option<T> {
+    read_record(blob, SCHEMA_VERSION)
+}
+
+fn read_record<T: DeserializeOwned>(blob: &[u8], version: u32) -> Option<T> {
+    let rec: Record<T> = codec::decode(blob)?;
+    (rec.version == version).then_some(rec.item)
+}
+
+/// `Event` as stored at v2 and v3, before `uid`.
+#[derive(Deserialize)]
+struct EventV3 {
+    id: u32,
+    date: Date,
+    time: Option<Time>,
+    title: String,
+    priority: Priority,
+}
+
+impl From<EventV3> for Event {
+    fn from(old: EventV3) -> Event {
+        let mut ev = Event::new(old.id, old.date, old.title);
+        ev.time = old.time;
+        ev.priority = old.priority;
+        ev
+    }
+}
+
+/// `Task` as stored at v2, before `extra` and `uid`.
+#[derive(Deserialize)]
+struct TaskV2 {
+    id: u32,
+    title: String,
+    done: bool,
+    priority: Priority,
+}
+
+impl From<TaskV2> for Task {
+    fn from(old: TaskV2) -> Task {
+        let mut task = Task::new(old.id, old.title);
+        task.done = old.done;
+        task.priority = old.priority;
+        task
+    }
+}
+
+/// `Task` as stored at v3, before `uid`.
+#[derive(Deserialize)]
+struct TaskV3 {
+    id: u32,
+    title: String,
+    done: bool,
+    priority: Priority,
+    extra: Vec<String>,
+}
+
+impl From<TaskV3> for Task {
+    fn from(old: TaskV3) -> Task {
+        let mut task = Task::new(old.id, old.title);
+        task.done = old.done;
+        task.priority = old.priority;
+        task.extra = old.extra;
+        task
+    }
+}
+
+/// Re-encode an event record written at per-record version `from` in the
+/// current format. `None` if it isn't a readable record of that version.
+pub fn upgrade_event(blob: &[u8], from: u32) -> Option<Vec<u8>> {
+    let event: Event = match from {
+        2 | 3 => read_record::<EventV3>(blob, from)?.into(),
+        _ => return None,
+    };
+    Some(encode_record(&event))
+}
+
+/// Task counterpart of `upgrade_event`.
+pub fn upgrade_task(blob: &[u8], from: u32) -> Option<Vec<u8>> {
+    let task: Task = match from {
+        2 => read_record::<TaskV2>(blob, from)?.into(),
+        3 => read_record::<TaskV3>(blob, from)?.into(),
+        _ => return None,
+    };
+    Some(encode_record(&task))
+}
+
+/// Move an envelope written at `from` to the current version, for
+/// collections whose item type hasn't changed since. Unlike `migrate` this
+/// reads binary envelopes too.
+pub fn restamp<T: Serialize + DeserializeOwned>(blob: &[u8], from: u32) -> Option<Vec<u8>> {
+    let env: Envelope<T> = codec::decode(blob)?;
+    (env.version == from).then(|| encode(&env.items))
+}
+
+type Migration = fn(Value) -> Option<Value>;
+
+/// `MIGRATIONS[n]` upgrades a collection from version `n` to `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3, migrate_v3_to_v4];
+
+/// v0 stored the bare array; v1 wraps it.
+fn migrate_v0_to_v1(value: Value) -> Option<Value> {
+    if !value.is_array() {
+        return None;
+    }
+    let mut env = Map::new();
+    env.insert("version".into(), Value::from(1u32));
+    env.insert("items".into(), value);
+    Some(Value::Object(env))
+}
+
+/// v2 only changes layout; the envelope itself just gets the new number.
+fn migrate_v1_to_v2(value: Value) -> Option<Value> {
+    renumber(value, 2)
+}
+
+/// Tasks from before v3 have no `extra`, which reads as empty.
+fn migrate_v2_to_v3(value: Value) -> Option<Value> {
+    renumber(value, 3)
+}
+
+/// Events and tasks from before v4 have no `uid`, which reads as nil.
+fn migrate_v3_to_v4(value: Value) -> Option<Value> {
+    renumber(value, 4)
+}
+
+fn renumber(mut value: Value, version: u32) -> Option<Value> {
+    let env = value.as_object_mut()?;
+    if !env.get("items")?.is_array() {
+        return None;
+    }
+    env.insert("version".into(), Value::from(version));
+    Some(value)
+}
+
+/// Upgrade one stored collection blob from version `from` to `SCHEMA_VERSION`.
+/// Returns `None` if the blob isn't valid JSON or a migration step rejects it.
+pub fn migrate(blob: &[u8], from: u32) -> Option<Vec<u8>> {
+    let mut value: Value = serde_json::from_slice(codec::json_body(blob)?).ok()?;
+    for step in MIGRATIONS.iter().skip(from as usize) {
+        value = step(value)?;
+    }
+    Some(codec::encode_as(&value, codec::Format::Json))
+}
+
+/// Split a current-format collection envelope into `(id, record blob)` pairs.
+/// Works on raw JSON so it doesn't depend on today's `Event`/`Task` shape.
+pub fn split_envelope(blob: &[u8]) -> Option<Vec<(u32, Vec<u8>)>> {
+    let env: Envelope<Value> = serde_json::from_slice(codec::json_body(blob)?).ok()?;
+    let mut out = Vec::with_capacity(env.items.len());
+    for item in env.items {
+        let id = u32::try_from(item.get("id")?.as_u64()?).ok()?;
+        let rec = Record { version: SCHEMA_VERSION, item };
+        out.push((id, codec::encode_as(&rec, codec::Format::Json)));
+    }
+    Some(out)
+}
@@ -0,0 +1,175 @@
+//! Passphrase encryption for exports leaving the device.
+//!
+//! PDDB encrypts data at rest, but an export copied off the device would
+//! otherwise be plaintext. `seal` wraps any export (a backup bundle, CSV,
+//! iCalendar...) so only the passphrase opens it:
+//!
+//!   key    — Argon2id over the passphrase with a random 16-byte salt
+//!   cipher — XChaCha20-Poly1305 with a random 24-byte nonce
+//!
+//! Layout, all integers little-endian:
+//!   magic       8  `DPLANSEA`
+//!   version     1  `SEAL_VERSION`
+//!   memory_kib  4  Argon2 memory cost
+//!   iterations  4  Argon2 time cost
+//!   lanes       4  Argon2 parallelism
+//!   salt       16
+//!   nonce      24
+//!   check       8  derived from the passphrase alongside the key
+//!   ciphertext  …  with its 16-byte tag; the header above is associated data
+//!
+//! The check value lets `open` tell a wrong passphrase from a damaged or
+//! altered file. It reveals nothing a guesser couldn't learn by trying the
+//! tag, and every guess still costs a full Argon2 run.
+//!
+//! Callers supply the salt and nonce, fresh from the TRNG for every export.
+
+extern crate alloc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+const MAGIC: &[u8; 8] = b"DPLANSEA";
+pub const SEAL_VERSION: u8 = 1;
+pub const SALT_LEN: usize = 16;
+pub const NONCE_LEN: usize = 24;
+const CHECK_LEN: usize = 8;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN + CHECK_LEN;
+
+/// Refuse files asking for more than this, so a crafted header can't
+/// exhaust memory before the passphrase is even checked.
+const MAX_MEMORY_KIB: u32 = 64 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SealError {
+    /// Doesn't start with the sealed-export magic.
+    NotSealed,
+    UnsupportedVersion(u8),
+    /// Shorter than a header plus tag.
+    Truncated,
+    /// KDF parameters out of range.
+    BadParams,
+    WrongPassphrase,
+    /// The passphrase is right but the contents fail authentication.
+    Tampered,
+}
+
+impl fmt::Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SealError::NotSealed => write!(f, "not an encrypted export"),
+            SealError::UnsupportedVersion(v) => write!(f, "unsupported encryption version {}", v),
+            SealError::Truncated => write!(f, "encrypted export is truncated"),
+            SealError::BadParams => write!(f, "bad key derivation settings"),
+            SealError::WrongPassphrase => write!(f, "wrong passphrase"),
+            SealError::Tampered => write!(f, "export has been damaged or altered"),
+        }
+    }
+}
+
+/// Argon2 costs. The default takes a few seconds on a Precursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self { memory_kib: 4096, iterations: 3, lanes: 1 }
+    }
+}
+
+/// Key and passphrase check derived from one Argon2 run.
+struct Derived {
+    key: [u8; 32],
+    check: [u8; CHECK_LEN],
+}
+
+impl Drop for Derived {
+    fn drop(&mut self) {
+        self.key.fill(0);
+    }
+}
+
+fn derive(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<Derived, SealError> {
+    if params.memory_kib > MAX_MEMORY_KIB || params.iterations > MAX_ITERATIONS {
+        return Err(SealError::BadParams);
+    }
+    let p = Params::new(params.memory_kib, params.iterations, params.lanes, Some(32 + CHECK_LEN))
+        .map_err(|_| SealError::BadParams)?;
+    let mut out = [0u8; 32 + CHECK_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, p)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut out)
+        .map_err(|_| SealError::BadParams)?;
+    let mut derived = Derived { key: [0; 32], check: [0; CHECK_LEN] };
+    derived.key.copy_from_slice(&out[..32]);
+    derived.check.copy_from_slice(&out[32..]);
+    out.fill(0);
+    Ok(derived)
+}
+
+/// Encrypt `plaintext` under `passphrase`.
+pub fn seal(
+    plaintext: &[u8],
+    passphrase: &str,
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+) -> Result<Vec<u8>, SealError> {
+    let derived = derive(passphrase, &salt, params)?;
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.push(SEAL_VERSION);
+    out.extend_from_slice(&params.memory_kib.to_le_bytes());
+    out.extend_from_slice(&params.iterations.to_le_bytes());
+    out.extend_from_slice(&params.lanes.to_le_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&derived.check);
+    let cipher = XChaCha20Poly1305::new((&derived.key).into());
+    let sealed = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: &out })
+        .map_err(|_| SealError::BadParams)?;
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Whether `data` looks like a sealed export, without decrypting it.
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Decrypt a sealed export.
+pub fn open(data: &[u8], passphrase: &str) -> Result<Vec<u8>, SealError> {
+    if !is_sealed(data) {
+        return Err(SealError::NotSealed);
+    }
+    let version = *data.get(MAGIC.len()).ok_or(SealError::Truncated)?;
+    if version != SEAL_VERSION {
+        return Err(SealError::UnsupportedVersion(version));
+    }
+    if data.len() < HEADER_LEN + 16 {
+        return Err(SealError::Truncated);
+    }
+    let (header, body) = data.split_at(HEADER_LEN);
+    let word = |at: usize| u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]);
+    let params = KdfParams { memory_kib: word(9), iterations: word(13), lanes: word(17) };
+    let salt = &header[21..21 + SALT_LEN];
+    let nonce = &header[21 + SALT_LEN..21 + SALT_LEN + NONCE_LEN];
+    let check = &header[HEADER_LEN - CHECK_LEN..];
+
+    let derived = derive(passphrase, salt, params)?;
+    if derived.check[..] != check[..] {
+        return Err(SealError::WrongPassphrase);
+    }
+    let cipher = XChaCha20Poly1305::new((&derived.key).into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: body, aad: header })
+        .map_err(|_| SealError::Tampered)
+}
@@ -0,0 +1,978 @@
+//! PDDB storage for Day Planner.
+//!
+//! Dictionary: planner.data, plus one per extra calendar (see `calendar.rs`)
+//! holding only that calendar's events, index and schema. These all live
+//! in the `.System` basis. Each unlocked secret basis may also hold a
+//! planner.secret dictionary with that basis's own calendar.
+//! Keys:
+//!   schema        — stored schema version (see `schema.rs`)
+//!   index.events  — envelope of every event ID
+//!   index.tasks   — envelope of every task ID
+//!   event.<id>    — one Event record
+//!   task.<id>     — one Task record
+//!   next_id       — next unique ID counter
+//!   queries       — envelope of SavedQuery structs
+//!   calendars     — envelope of Calendar structs (the registry)
+//!   calendar      — planner.secret only: that basis's Calendar
+//!   journal       — pending multi-key transaction (see below)
+//!   snapshot.<YYYY-MM-DD>.<seq> — full copy of the planner state
+//!   backup.v<N>.<key> — pre-migration copy of a collection or record
+//!   recovery.<key>    — quarantined copy of a blob that failed to parse
+//!
+//! Everything except `schema` (plain text, so any build can read it) is
+//! written through `codec` with a format tag: JSON, or postcard when built
+//! with the `binary-storage` feature.
+//!
+//! Schema v0/v1 kept all events under `events` and all tasks under `tasks`;
+//! `upgrade_schema` splits those into records. Later upgrades rewrite each
+//! record in place.
+//!
+//! `Storage` is generic over a `Backend`; the planner binary supplies PDDB.
+//! Unreadable data is never silently replaced: loads return `StorageError`
+//! and the caller decides whether to retry or start fresh.
+//!
+//! Changes that touch more than one key (a new record and its index, a
+//! full save, a migration) are journaled: the complete set of writes is
+//! stored under `journal` and synced, then applied, then `journal` is
+//! deleted. `recover_journal` runs before anything is loaded; it finishes
+//! an intact journal and discards a torn one, so each change lands
+//! entirely or not at all.
+//!
+//! Snapshots are whole-state copies in a single value, so restoring one
+//! doesn't depend on any other key. The newest `SNAPSHOT_KEEP` are kept;
+//! taking another prunes the oldest in the same transaction. Snapshots
+//! aren't migrated, so ones from an older schema stop being listed.
+//!
+//! Loading skips indexed IDs whose record is missing, and rebuilds a
+//! missing or unreadable index from the key list.
+//!
+//! The app doesn't save per keystroke: it marks what changed and hands it
+//! over as a `Batch`, which `save_batch` writes in one transaction.
+//! `StorageStats` counts the writes, deletes and syncs that reach the
+//! backend.
+
+extern crate alloc;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{Backend, BackendError};
+use crate::calendar::{self, Calendar};
+use crate::codec;
+use crate::planner::{CalendarId, Date, Event, Task};
+use crate::query::SavedQuery;
+use crate::schema::{self, PER_RECORD_VERSION, SCHEMA_VERSION};
+
+pub const DICT: &str = "planner.data";
+/// Dictionary for a secret basis's calendar, inside that basis.
+pub const BASIS_DICT: &str = "planner.secret";
+/// Files waiting to be imported, one per key, named as they were (e.g.
+/// `team.ics`). The day view's I)mport reviews the first; applying the
+/// review removes it.
+pub const INBOX_DICT: &str = "planner.inbox";
+/// Everything that isn't pinned to a secret basis is pinned here. Without
+/// an explicit basis PDDB writes to the most recently unlocked one, which
+/// would strand ordinary data in a secret basis.
+pub const SYSTEM_BASIS: &str = ".System";
+const KEY_EVENTS: &str = "events";
+const KEY_TASKS: &str = "tasks";
+const KEY_NEXT_ID: &str = "next_id";
+const KEY_QUERIES: &str = "queries";
+const KEY_CALENDARS: &str = "calendars";
+const KEY_CALENDAR: &str = "calendar";
+const KEY_SCHEMA: &str = "schema";
+const KEY_JOURNAL: &str = "journal";
+const RECOVERY_PREFIX: &str = "recovery.";
+const SNAPSHOT_PREFIX: &str = "snapshot.";
+
+/// Snapshots kept before the oldest are pruned.
+pub const SNAPSHOT_KEEP: usize = 7;
+
+/// Collections that live (or, before v2, lived) in a versioned envelope.
+const VERSIONED_KEYS: [&str; 4] = [KEY_EVENTS, KEY_TASKS, KEY_QUERIES, KEY_CALENDARS];
+
+/// Which per-record collection an operation is on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RecordKind {
+    Events,
+    Tasks,
+}
+
+impl RecordKind {
+    /// The kind a pre-v2 whole-collection key held, if any.
+    fn from_legacy(key: &str) -> Option<RecordKind> {
+        match key {
+            KEY_EVENTS => Some(RecordKind::Events),
+            KEY_TASKS => Some(RecordKind::Tasks),
+            _ => None,
+        }
+    }
+
+    fn index(&self) -> &'static str {
+        match self {
+            RecordKind::Events => "index.events",
+            RecordKind::Tasks => "index.tasks",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            RecordKind::Events => "event.",
+            RecordKind::Tasks => "task.",
+        }
+    }
+
+    fn key(&self, id: u32) -> String {
+        format!("{}{}", self.prefix(), id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageError {
+    Backend(BackendError),
+    /// The key holds bytes that don't parse; they were copied to `recovery.<key>`.
+    Corrupt(String),
+    /// Stored data was written by a newer build.
+    NewerSchema(u32),
+}
+
+impl From<BackendError> for StorageError {
+    fn from(e: BackendError) -> Self {
+        StorageError::Backend(e)
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Backend(e) => write!(f, "storage unavailable ({:?})", e),
+            StorageError::Corrupt(key) => write!(f, "'{}' is unreadable", key),
+            StorageError::NewerSchema(v) => {
+                write!(f, "data is schema v{}, this build reads v{}", v, SCHEMA_VERSION)
+            }
+        }
+    }
+}
+
+/// Why a snapshot was taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SnapshotReason {
+    Daily,
+    Manual,
+    /// Automatically, just before the named bulk change.
+    Before(String),
+}
+
+impl fmt::Display for SnapshotReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotReason::Daily => write!(f, "daily"),
+            SnapshotReason::Manual => write!(f, "manual"),
+            SnapshotReason::Before(op) => write!(f, "before {}", op),
+        }
+    }
+}
+
+/// A full copy of the planner state as stored under `snapshot.*`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub taken: Date,
+    pub reason: SnapshotReason,
+    pub events: Vec<Event>,
+    pub tasks: Vec<Task>,
+    pub next_id: u32,
+    pub queries: Vec<SavedQuery>,
+    /// Events outside the default calendar, as (event ID, calendar).
+    #[serde(default)]
+    pub event_calendars: Vec<(u32, CalendarId)>,
+}
+
+/// Borrowing twin of `Snapshot`, so taking one doesn't clone the state.
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    taken: Date,
+    reason: &'a SnapshotReason,
+    events: &'a [&'a Event],
+    tasks: &'a [Task],
+    next_id: u32,
+    queries: &'a [SavedQuery],
+    event_calendars: Vec<(u32, CalendarId)>,
+}
+
+/// One row of the snapshot list.
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    pub key: String,
+    pub taken: Date,
+    pub reason: SnapshotReason,
+    pub events: usize,
+    pub tasks: usize,
+}
+
+/// One key change in a transaction; `data: None` deletes the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalOp {
+    key: String,
+    data: Option<Vec<u8>>,
+}
+
+/// A transaction as written to the `journal` key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Journal {
+    ops: Vec<JournalOp>,
+    /// Detects a journal that was itself only partly written.
+    check: u32,
+}
+
+impl Journal {
+    /// FNV-1a over every key and payload.
+    fn checksum(ops: &[JournalOp]) -> u32 {
+        let mut h: u32 = 0x811c_9dc5;
+        let mut feed = |bytes: &[u8]| {
+            for &b in bytes {
+                h ^= b as u32;
+                h = h.wrapping_mul(0x0100_0193);
+            }
+        };
+        for op in ops {
+            feed(op.key.as_bytes());
+            match op.data {
+                Some(ref d) => {
+                    feed(&[1]);
+                    feed(d);
+                }
+                None => feed(&[0]),
+            }
+        }
+        h
+    }
+}
+
+/// A set of key changes that `Storage::commit` applies all-or-nothing.
+#[derive(Debug, Default)]
+struct Txn {
+    ops: Vec<JournalOp>,
+}
+
+impl Txn {
+    fn put(&mut self, key: String, data: Vec<u8>) {
+        self.ops.push(JournalOp { key, data: Some(data) });
+    }
+
+    fn delete(&mut self, key: String) {
+        self.ops.push(JournalOp { key, data: None });
+    }
+}
+
+/// Changes to write together with `Storage::save_batch`.
+#[derive(Debug, Default)]
+pub struct Batch<'a> {
+    pub events: Vec<&'a Event>,
+    pub deleted_events: Vec<u32>,
+    pub tasks: Vec<&'a Task>,
+    pub deleted_tasks: Vec<u32>,
+    pub next_id: Option<u32>,
+    pub queries: Option<&'a [SavedQuery]>,
+    pub calendars: Option<&'a [Calendar]>,
+    pub own_calendar: Option<&'a Calendar>,
+}
+
+impl Batch<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+            && self.deleted_events.is_empty()
+            && self.tasks.is_empty()
+            && self.deleted_tasks.is_empty()
+            && self.next_id.is_none()
+            && self.queries.is_none()
+            && self.calendars.is_none()
+            && self.own_calendar.is_none()
+    }
+}
+
+/// What a `Storage` has actually done to the medium since it was opened.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StorageStats {
+    pub writes: u32,
+    pub deletes: u32,
+    pub syncs: u32,
+}
+
+pub struct Storage<B: Backend> {
+    backend: B,
+    event_ids: BTreeSet<u32>,
+    task_ids: BTreeSet<u32>,
+    /// A commit failed partway through applying; finish it before the next.
+    journal_pending: bool,
+    stats: StorageStats,
+}
+
+impl<B: Backend> Storage<B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            event_ids: BTreeSet::new(),
+            task_ids: BTreeSet::new(),
+            journal_pending: false,
+            stats: StorageStats::default(),
+        }
+    }
+
+    pub fn backend(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn stats(&self) -> StorageStats {
+        self.stats
+    }
+
+    fn read_key(&mut self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.backend.read(key)?)
+    }
+
+    /// Write without syncing; public mutators sync once when they're done.
+    fn write_key(&mut self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.stats.writes += 1;
+        Ok(self.backend.write(key, data)?)
+    }
+
+    fn delete_key(&mut self, key: &str) -> Result<(), StorageError> {
+        self.stats.deletes += 1;
+        Ok(self.backend.delete(key)?)
+    }
+
+    fn sync(&mut self) -> Result<(), StorageError> {
+        self.stats.syncs += 1;
+        Ok(self.backend.sync()?)
+    }
+
+    fn apply(&mut self, ops: &[JournalOp]) -> Result<(), StorageError> {
+        for op in ops {
+            match op.data {
+                Some(ref data) => self.write_key(&op.key, data)?,
+                None => self.delete_key(&op.key)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply a transaction atomically. Single-key transactions skip the
+    /// journal since one PDDB key write is already all-or-nothing.
+    fn commit(&mut self, txn: Txn) -> Result<(), StorageError> {
+        if txn.ops.len() <= 1 {
+            self.apply(&txn.ops)?;
+            return self.sync();
+        }
+        let journal = Journal { check: Journal::checksum(&txn.ops), ops: txn.ops };
+        self.write_key(KEY_JOURNAL, &codec::encode(&journal))?;
+        self.sync()?;
+        if let Err(e) = self.apply(&journal.ops).and_then(|_| self.sync()) {
+            self.journal_pending = true;
+            return Err(e);
+        }
+        self.delete_key(KEY_JOURNAL)?;
+        self.sync()
+    }
+
+    /// Finish a commit that failed earlier in this session, so new changes
+    /// are staged against the ID sets it left behind.
+    fn settle(&mut self) -> Result<(), StorageError> {
+        if self.journal_pending {
+            self.recover_journal()?;
+        }
+        Ok(())
+    }
+
+    /// Finish or discard a transaction left behind by a crash or a failed
+    /// commit. Call before loading anything.
+    pub fn recover_journal(&mut self) -> Result<(), StorageError> {
+        let Some(buf) = self.read_key(KEY_JOURNAL)? else {
+            self.journal_pending = false;
+            return Ok(());
+        };
+        match codec::decode::<Journal>(&buf) {
+            Some(j) if j.check == Journal::checksum(&j.ops) => {
+                log::warn!("completing interrupted save of {} keys", j.ops.len());
+                self.apply(&j.ops)?;
+                self.sync()?;
+                self.adopt_indexes(&j.ops);
+            }
+            _ => log::warn!("discarding torn journal; last save rolled back"),
+        }
+        self.delete_key(KEY_JOURNAL)?;
+        self.sync()?;
+        self.journal_pending = false;
+        Ok(())
+    }
+
+    /// Track the ID sets a rolled-forward journal just wrote.
+    fn adopt_indexes(&mut self, ops: &[JournalOp]) {
+        for kind in [RecordKind::Events, RecordKind::Tasks] {
+            let written = ops.iter().rev().find(|op| op.key == kind.index());
+            if let Some(ids) = written.and_then(|op| schema::decode::<u32>(op.data.as_deref()?)) {
+                *self.ids_mut(kind) = ids.into_iter().collect();
+            }
+        }
+    }
+
+    /// Copy an unparseable blob aside and report it as corrupt.
+    fn quarantine(&mut self, key: &str, blob: &[u8]) -> StorageError {
+        log::warn!("'{}' failed to parse; quarantining {} bytes", key, blob.len());
+        let copied = self
+            .write_key(&format!("{}{}", RECOVERY_PREFIX, key), blob)
+            .and_then(|_| self.sync());
+        if let Err(e) = copied {
+            return e;
+        }
+        StorageError::Corrupt(String::from(key))
+    }
+
+    fn load_collection<T: DeserializeOwned>(&mut self, key: &str) -> Result<Vec<T>, StorageError> {
+        match self.read_key(key)? {
+            None => Ok(Vec::new()),
+            Some(buf) => match schema::decode(&buf) {
+                Some(items) => Ok(items),
+                None => Err(self.quarantine(key, &buf)),
+            },
+        }
+    }
+
+    fn save_collection<T: Serialize>(&mut self, key: &str, items: &[T]) -> Result<(), StorageError> {
+        self.settle()?;
+        let data = schema::encode(items);
+        self.write_key(key, &data)?;
+        self.sync()
+    }
+
+    fn ids(&self, kind: RecordKind) -> &BTreeSet<u32> {
+        match kind {
+            RecordKind::Events => &self.event_ids,
+            RecordKind::Tasks => &self.task_ids,
+        }
+    }
+
+    fn ids_mut(&mut self, kind: RecordKind) -> &mut BTreeSet<u32> {
+        match kind {
+            RecordKind::Events => &mut self.event_ids,
+            RecordKind::Tasks => &mut self.task_ids,
+        }
+    }
+
+    /// IDs listed by an index, or rebuilt from the key list if the index is
+    /// missing or unreadable.
+    fn load_index(&mut self, kind: RecordKind) -> Result<BTreeSet<u32>, StorageError> {
+        if let Some(buf) = self.read_key(kind.index())? {
+            if let Some(ids) = schema::decode::<u32>(&buf) {
+                return Ok(ids.into_iter().collect());
+            }
+            log::warn!("'{}' unreadable; rebuilding from keys", kind.index());
+        }
+        let ids: BTreeSet<u32> = self
+            .backend
+            .list()?
+            .iter()
+            .filter_map(|k| k.strip_prefix(kind.prefix())?.parse::<u32>().ok())
+            .collect();
+        if !ids.is_empty() {
+            let mut txn = Txn::default();
+            stage_index(&mut txn, kind, &ids);
+            self.commit(txn)?;
+        }
+        Ok(ids)
+    }
+
+    fn load_records<T: DeserializeOwned>(&mut self, kind: RecordKind) -> Result<Vec<T>, StorageError> {
+        let mut ids = self.load_index(kind)?;
+        let mut items = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        for &id in ids.iter() {
+            let key = kind.key(id);
+            match self.read_key(&key)? {
+                None => missing.push(id),
+                Some(buf) => match schema::decode_record(&buf) {
+                    Some(item) => items.push(item),
+                    None => return Err(self.quarantine(&key, &buf)),
+                },
+            }
+        }
+        for id in missing {
+            log::warn!("{}{} is indexed but missing; dropping", kind.prefix(), id);
+            ids.remove(&id);
+        }
+        *self.ids_mut(kind) = ids;
+        Ok(items)
+    }
+
+    fn put_record<T: Serialize>(&mut self, kind: RecordKind, id: u32, item: &T) -> Result<(), StorageError> {
+        self.settle()?;
+        let mut txn = Txn::default();
+        let mut grown = None;
+        if !self.ids(kind).contains(&id) {
+            let mut ids = self.ids(kind).clone();
+            ids.insert(id);
+            stage_index(&mut txn, kind, &ids);
+            grown = Some(ids);
+        }
+        txn.put(kind.key(id), schema::encode_record(item));
+        self.commit(txn)?;
+        if let Some(ids) = grown {
+            *self.ids_mut(kind) = ids;
+        }
+        Ok(())
+    }
+
+    fn remove_record(&mut self, kind: RecordKind, id: u32) -> Result<(), StorageError> {
+        self.settle()?;
+        let mut txn = Txn::default();
+        txn.delete(kind.key(id));
+        let mut ids = self.ids(kind).clone();
+        if ids.remove(&id) {
+            stage_index(&mut txn, kind, &ids);
+        }
+        self.commit(txn)?;
+        *self.ids_mut(kind) = ids;
+        Ok(())
+    }
+
+    /// Stage replacing every record of one kind: write `items`, delete the
+    /// rest. Returns the new ID set, to install once the commit succeeds.
+    fn stage_replace<T: Serialize>(&self, txn: &mut Txn, kind: RecordKind, items: &[(u32, &T)]) -> BTreeSet<u32> {
+        let keep: BTreeSet<u32> = items.iter().map(|(id, _)| *id).collect();
+        for (id, item) in items {
+            txn.put(kind.key(*id), schema::encode_record(*item));
+        }
+        for id in self.ids(kind).difference(&keep) {
+            txn.delete(kind.key(*id));
+        }
+        stage_index(txn, kind, &keep);
+        keep
+    }
+
+    /// Stored schema version; data written before versioning reads as 0.
+    pub fn load_schema_version(&mut self) -> Result<u32, StorageError> {
+        match self.read_key(KEY_SCHEMA)? {
+            None => Ok(0),
+            Some(buf) => core::str::from_utf8(&buf)
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+                .ok_or_else(|| self.quarantine(KEY_SCHEMA, &buf)),
+        }
+    }
+
+    /// Bring stored data up to `SCHEMA_VERSION` in one transaction. Each
+    /// collection is copied to `backup.v<N>.<key>` alongside. A collection
+    /// that can't be migrated is quarantined and nothing is changed.
+    pub fn upgrade_schema(&mut self) -> Result<(), StorageError> {
+        let stored = self.load_schema_version()?;
+        if stored == SCHEMA_VERSION {
+            return Ok(());
+        }
+        if stored > SCHEMA_VERSION {
+            return Err(StorageError::NewerSchema(stored));
+        }
+        let mut txn = Txn::default();
+        for key in VERSIONED_KEYS.iter() {
+            let Some(blob) = self.read_key(key)? else { continue };
+            let upgraded = match *key {
+                KEY_QUERIES if stored >= PER_RECORD_VERSION => schema::restamp::<SavedQuery>(&blob, stored),
+                KEY_CALENDARS if stored >= PER_RECORD_VERSION => schema::restamp::<Calendar>(&blob, stored),
+                _ => schema::migrate(&blob, stored),
+            };
+            let Some(upgraded) = upgraded else {
+                log::warn!("couldn't migrate '{}' from schema v{}", key, stored);
+                return Err(self.quarantine(key, &blob));
+            };
+            txn.put(format!("backup.v{}.{}", stored, key), blob.clone());
+            match RecordKind::from_legacy(key).filter(|_| stored < PER_RECORD_VERSION) {
+                Some(kind) => {
+                    // Split the whole-collection key into records + index.
+                    let Some(records) = schema::split_envelope(&upgraded) else {
+                        return Err(self.quarantine(key, &blob));
+                    };
+                    let ids: BTreeSet<u32> = records.iter().map(|(id, _)| *id).collect();
+                    for (id, rec) in records {
+                        txn.put(kind.key(id), rec);
+                    }
+                    stage_index(&mut txn, kind, &ids);
+                    txn.delete(String::from(*key));
+                }
+                None => txn.put(String::from(*key), upgraded),
+            }
+        }
+        if stored >= PER_RECORD_VERSION {
+            self.stage_record_upgrade(&mut txn, RecordKind::Events, stored)?;
+            self.stage_record_upgrade(&mut txn, RecordKind::Tasks, stored)?;
+        }
+        txn.put(String::from(KEY_SCHEMA), format!("{}", SCHEMA_VERSION).into_bytes());
+        self.commit(txn)?;
+        log::info!("migrated planner data from schema v{} to v{}", stored, SCHEMA_VERSION);
+        Ok(())
+    }
+
+    /// Stage rewriting every record of `kind` from per-record version
+    /// `from`, each backed up to `backup.v<from>.<key>`, and an index
+    /// rebuilt from the keys found.
+    fn stage_record_upgrade(&mut self, txn: &mut Txn, kind: RecordKind, from: u32) -> Result<(), StorageError> {
+        let ids: BTreeSet<u32> = self
+            .backend
+            .list()?
+            .iter()
+            .filter_map(|k| k.strip_prefix(kind.prefix())?.parse::<u32>().ok())
+            .collect();
+        for &id in ids.iter() {
+            let key = kind.key(id);
+            let Some(blob) = self.read_key(&key)? else { continue };
+            let upgraded = match kind {
+                RecordKind::Events => schema::upgrade_event(&blob, from),
+                RecordKind::Tasks => schema::upgrade_task(&blob, from),
+            };
+            let Some(upgraded) = upgraded else {
+                log::warn!("couldn't migrate '{}' from schema v{}", key, from);
+                return Err(self.quarantine(&key, &blob));
+            };
+            txn.put(format!("backup.v{}.{}", from, key), blob);
+            txn.put(key, upgraded);
+        }
+        stage_index(txn, kind, &ids);
+        Ok(())
+    }
+
+    /// Raw bytes quarantined for `key`, if any.
+    pub fn recovery_blob(&mut self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        self.read_key(&format!("{}{}", RECOVERY_PREFIX, key))
+    }
+
+    /// Give up on whatever `err` complained about so loading can proceed.
+    /// The affected blobs stay available under their `recovery.` keys.
+    pub fn start_fresh(&mut self, err: &StorageError) -> Result<(), StorageError> {
+        match err {
+            StorageError::Backend(_) => Ok(()),
+            StorageError::Corrupt(key) => {
+                self.delete_key(key)?;
+                self.sync()
+            }
+            StorageError::NewerSchema(_) => {
+                let mut keys: Vec<String> = VERSIONED_KEYS.iter().map(|k| String::from(*k)).collect();
+                keys.extend(self.backend.list()?.into_iter().filter(|k| {
+                    [RecordKind::Events, RecordKind::Tasks]
+                        .iter()
+                        .any(|kind| k.starts_with(kind.prefix()) || k == kind.index())
+                }));
+                let mut txn = Txn::default();
+                for key in keys {
+                    if let Some(blob) = self.read_key(&key)? {
+                        txn.put(format!("{}{}", RECOVERY_PREFIX, key), blob);
+                        txn.delete(key);
+                    }
+                }
+                txn.put(String::from(KEY_SCHEMA), format!("{}", SCHEMA_VERSION).into_bytes());
+                self.commit(txn)
+            }
+        }
+    }
+
+    pub fn load_events(&mut self) -> Result<Vec<Event>, StorageError> {
+        self.load_records(RecordKind::Events)
+    }
+
+    /// Write one event (new or changed).
+    pub fn save_event(&mut self, event: &Event) -> Result<(), StorageError> {
+        self.put_record(RecordKind::Events, event.id, event)
+    }
+
+    pub fn delete_event(&mut self, id: u32) -> Result<(), StorageError> {
+        self.remove_record(RecordKind::Events, id)
+    }
+
+    pub fn load_tasks(&mut self) -> Result<Vec<Task>, StorageError> {
+        self.load_records(RecordKind::Tasks)
+    }
+
+    /// Write one task (new or changed).
+    pub fn save_task(&mut self, task: &Task) -> Result<(), StorageError> {
+        self.put_record(RecordKind::Tasks, task.id, task)
+    }
+
+    pub fn delete_task(&mut self, id: u32) -> Result<(), StorageError> {
+        self.remove_record(RecordKind::Tasks, id)
+    }
+
+    /// Write a set of changes in one transaction (one sync, or three when
+    /// it's journaled) instead of one per change.
+    pub fn save_batch(&mut self, batch: &Batch) -> Result<(), StorageError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.settle()?;
+        let mut txn = Txn::default();
+        let mut event_ids = self.event_ids.clone();
+        let mut task_ids = self.task_ids.clone();
+        for ev in batch.events.iter() {
+            txn.put(RecordKind::Events.key(ev.id), schema::encode_record(*ev));
+            event_ids.insert(ev.id);
+        }
+        for &id in batch.deleted_events.iter() {
+            txn.delete(RecordKind::Events.key(id));
+            event_ids.remove(&id);
+        }
+        for t in batch.tasks.iter() {
+            txn.put(RecordKind::Tasks.key(t.id), schema::encode_record(*t));
+            task_ids.insert(t.id);
+        }
+        for &id in batch.deleted_tasks.iter() {
+            txn.delete(RecordKind::Tasks.key(id));
+            task_ids.remove(&id);
+        }
+        if event_ids != self.event_ids {
+            stage_index(&mut txn, RecordKind::Events, &event_ids);
+        }
+        if task_ids != self.task_ids {
+            stage_index(&mut txn, RecordKind::Tasks, &task_ids);
+        }
+        if let Some(next_id) = batch.next_id {
+            txn.put(String::from(KEY_NEXT_ID), codec::encode(&next_id));
+        }
+        if let Some(queries) = batch.queries {
+            txn.put(String::from(KEY_QUERIES), schema::encode(queries));
+        }
+        if let Some(calendars) = batch.calendars {
+            txn.put(String::from(KEY_CALENDARS), schema::encode(calendars));
+        }
+        if let Some(cal) = batch.own_calendar {
+            txn.put(String::from(KEY_CALENDAR), codec::encode(cal));
+        }
+        self.commit(txn)?;
+        self.event_ids = event_ids;
+        self.task_ids = task_ids;
+        Ok(())
+    }
+
+    /// Replace all events in one transaction; for calendar dictionaries,
+    /// which hold nothing else.
+    pub fn save_events(&mut self, events: &[&Event]) -> Result<(), StorageError> {
+        self.settle()?;
+        let mut txn = Txn::default();
+        let ev: Vec<(u32, &Event)> = events.iter().map(|e| (e.id, *e)).collect();
+        let ids = self.stage_replace(&mut txn, RecordKind::Events, &ev);
+        self.commit(txn)?;
+        self.event_ids = ids;
+        Ok(())
+    }
+
+    /// Replace all events, tasks and the ID counter in one transaction.
+    pub fn save_all(&mut self, events: &[&Event], tasks: &[Task], next_id: u32) -> Result<(), StorageError> {
+        self.settle()?;
+        let mut txn = Txn::default();
+        let ids = self.stage_all(&mut txn, events, tasks, next_id);
+        self.commit(txn)?;
+        (self.event_ids, self.task_ids) = ids;
+        Ok(())
+    }
+
+    fn stage_all(
+        &self,
+        txn: &mut Txn,
+        events: &[&Event],
+        tasks: &[Task],
+        next_id: u32,
+    ) -> (BTreeSet<u32>, BTreeSet<u32>) {
+        let ev: Vec<(u32, &Event)> = events.iter().map(|e| (e.id, *e)).collect();
+        let tk: Vec<(u32, &Task)> = tasks.iter().map(|t| (t.id, t)).collect();
+        let event_ids = self.stage_replace(txn, RecordKind::Events, &ev);
+        let task_ids = self.stage_replace(txn, RecordKind::Tasks, &tk);
+        txn.put(String::from(KEY_NEXT_ID), codec::encode(&next_id));
+        (event_ids, task_ids)
+    }
+
+    /// Stored ID counter. Missing or unparseable reads as 1; callers should
+    /// also keep it above every ID actually in use.
+    pub fn load_next_id(&mut self) -> Result<u32, StorageError> {
+        // Untagged text like "42" from older builds decodes as legacy JSON.
+        Ok(self
+            .read_key(KEY_NEXT_ID)?
+            .and_then(|buf| codec::decode::<u32>(&buf))
+            .unwrap_or(1))
+    }
+
+    pub fn save_next_id(&mut self, id: u32) -> Result<(), StorageError> {
+        self.settle()?;
+        self.write_key(KEY_NEXT_ID, &codec::encode(&id))?;
+        self.sync()
+    }
+
+    pub fn load_queries(&mut self) -> Result<Vec<SavedQuery>, StorageError> {
+        self.load_collection(KEY_QUERIES)
+    }
+
+    pub fn save_queries(&mut self, queries: &[SavedQuery]) -> Result<(), StorageError> {
+        self.save_collection(KEY_QUERIES, queries)
+    }
+
+    /// The calendar registry, or the built-in calendars if none was saved.
+    pub fn load_calendars(&mut self) -> Result<Vec<Calendar>, StorageError> {
+        let cals = self.load_collection(KEY_CALENDARS)?;
+        Ok(if cals.is_empty() { calendar::default_calendars() } else { cals })
+    }
+
+    pub fn save_calendars(&mut self, calendars: &[Calendar]) -> Result<(), StorageError> {
+        self.save_collection(KEY_CALENDARS, calendars)
+    }
+
+    /// The calendar a secret basis's dictionary describes, if it's been
+    /// saved yet.
+    pub fn load_own_calendar(&mut self) -> Result<Option<Calendar>, StorageError> {
+        match self.read_key(KEY_CALENDAR)? {
+            None => Ok(None),
+            Some(buf) => match codec::decode(&buf) {
+                Some(cal) => Ok(Some(cal)),
+                None => Err(self.quarantine(KEY_CALENDAR, &buf)),
+            },
+        }
+    }
+
+    pub fn save_own_calendar(&mut self, calendar: &Calendar) -> Result<(), StorageError> {
+        self.settle()?;
+        self.write_key(KEY_CALENDAR, &codec::encode(calendar))?;
+        self.sync()
+    }
+
+    /// Snapshot keys, oldest first. The date-then-sequence key format sorts
+    /// chronologically.
+    fn snapshot_keys(&mut self) -> Result<Vec<String>, StorageError> {
+        let mut keys: Vec<String> = self
+            .backend
+            .list()?
+            .into_iter()
+            .filter(|k| k.starts_with(SNAPSHOT_PREFIX))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Store a copy of the given state and prune down to `SNAPSHOT_KEEP`.
+    /// Returns the new snapshot's key.
+    pub fn take_snapshot(
+        &mut self,
+        taken: Date,
+        reason: SnapshotReason,
+        events: &[&Event],
+        tasks: &[Task],
+        next_id: u32,
+        queries: &[SavedQuery],
+    ) -> Result<String, StorageError> {
+        self.settle()?;
+        let keys = self.snapshot_keys()?;
+        let day = format!("{}{}.", SNAPSHOT_PREFIX, taken.display());
+        let seq = keys
+            .iter()
+            .filter_map(|k| k.strip_prefix(day.as_str())?.parse::<u32>().ok())
+            .max()
+            .map_or(0, |s| s + 1);
+        let key = format!("{}{:03}", day, seq);
+        let snap = SnapshotRef {
+            version: SCHEMA_VERSION,
+            taken,
+            reason: &reason,
+            events,
+            tasks,
+            next_id,
+            queries,
+            event_calendars: events
+                .iter()
+                .filter(|e| e.calendar != calendar::DEFAULT_CALENDAR)
+                .map(|e| (e.id, e.calendar))
+                .collect(),
+        };
+        let mut txn = Txn::default();
+        txn.put(key.clone(), codec::encode(&snap));
+        let excess = (keys.len() + 1).saturating_sub(SNAPSHOT_KEEP);
+        for old in keys.into_iter().take(excess) {
+            txn.delete(old);
+        }
+        self.commit(txn)?;
+        log::info!("took {} snapshot {}", reason, key);
+        Ok(key)
+    }
+
+    /// Date of the newest snapshot, from its key alone.
+    pub fn latest_snapshot_date(&mut self) -> Result<Option<Date>, StorageError> {
+        Ok(self.snapshot_keys()?.iter().rev().find_map(|k| {
+            let rest = k.strip_prefix(SNAPSHOT_PREFIX)?;
+            Date::parse(rest.split('.').next()?)
+        }))
+    }
+
+    /// Readable snapshots, newest first.
+    pub fn snapshots(&mut self) -> Result<Vec<SnapshotInfo>, StorageError> {
+        let mut out = Vec::new();
+        for key in self.snapshot_keys()?.into_iter().rev() {
+            match self.load_snapshot(&key) {
+                Ok(snap) => out.push(SnapshotInfo {
+                    key,
+                    taken: snap.taken,
+                    reason: snap.reason,
+                    events: snap.events.len(),
+                    tasks: snap.tasks.len(),
+                }),
+                Err(StorageError::Corrupt(_)) => log::warn!("skipping unreadable snapshot {}", key),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn load_snapshot(&mut self, key: &str) -> Result<Snapshot, StorageError> {
+        let mut snap = self
+            .read_key(key)?
+            .and_then(|buf| codec::decode::<Snapshot>(&buf))
+            .filter(|snap| snap.version == SCHEMA_VERSION)
+            .ok_or_else(|| StorageError::Corrupt(String::from(key)))?;
+        for &(id, cal) in snap.event_calendars.iter() {
+            if let Some(ev) = snap.events.iter_mut().find(|e| e.id == id) {
+                ev.calendar = cal;
+            }
+        }
+        Ok(snap)
+    }
+
+    /// Replace events, tasks, the ID counter and filters in one transaction,
+    /// e.g. from a snapshot. Only pass the events that belong in this
+    /// dictionary.
+    pub fn restore(
+        &mut self,
+        events: &[&Event],
+        tasks: &[Task],
+        next_id: u32,
+        queries: &[SavedQuery],
+    ) -> Result<(), StorageError> {
+        self.settle()?;
+        let mut txn = Txn::default();
+        let ids = self.stage_all(&mut txn, events, tasks, next_id);
+        txn.put(String::from(KEY_QUERIES), schema::encode(queries));
+        self.commit(txn)?;
+        (self.event_ids, self.task_ids) = ids;
+        Ok(())
+    }
+
+    pub fn delete_snapshot(&mut self, key: &str) -> Result<(), StorageError> {
+        self.settle()?;
+        self.delete_key(key)?;
+        self.sync()
+    }
+
+    /// Flush anything the backend has buffered.
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        self.sync()
+    }
+}
+
+fn stage_index(txn: &mut Txn, kind: RecordKind, ids: &BTreeSet<u32>) {
+    let list: Vec<u32> = ids.iter().copied().collect();
+    txn.put(String::from(kind.index()), schema::encode(&list));
+}
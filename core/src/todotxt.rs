@@ -0,0 +1,214 @@
+//! todo.txt format for Day Planner tasks.
+//!
+//! One task per line:
+//!
+//!   x 2026-03-04 2026-03-01 Call Bob +work @phone due:2026-03-05
+//!   (A) 2026-03-01 Buy milk @shop
+//!
+//!   x          — done; may be followed by the completion date
+//!   (A)        — priority on open tasks: A is High, B Normal, C Low, and
+//!                D–Z read as Low. No letter is Normal too.
+//!   YYYY-MM-DD — creation date, after the priority
+//!   +project, @context — stay in the title (like `#tag`s) and are listed
+//!                by `TodoItem::projects`/`contexts`
+//!   key:value  — likewise stays in the title, where it was, and is listed
+//!                by `TodoItem::extras`. The key is ASCII letters and
+//!                digits, starting with a letter, so "3:30" or a URL isn't
+//!                a pair
+//!   uid:…      — the task's stable id, taken out of the title; written
+//!                for tasks that have one
+//!
+//! The title is what's left, checked as other imports check it
+//! (`fit_title`): one longer than `TASK_TITLE_MAX` characters is shortened
+//! and marked as such, and a line with no title at all is skipped.
+//!
+//! Done tasks don't carry `(A)`; their priority travels as `pri:A`, as most
+//! todo.txt clients do.
+//!
+//! A `TodoItem` keeps what `Task` has no field for (dates, the exact
+//! letter), so a parsed line is written back the same unless the task was
+//! changed in between.
+
+extern crate alloc;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::planner::{fit_title, Date, Priority, Task, Uid, TASK_TITLE_MAX};
+
+/// A task plus the todo.txt details `Task` doesn't hold.
+#[derive(Debug, Clone)]
+pub struct TodoItem {
+    pub task: Task,
+    /// The priority letter as written, if any.
+    pub letter: Option<char>,
+    pub completed: Option<Date>,
+    pub created: Option<Date>,
+    /// The title was too long to store and was cut when parsed.
+    pub shortened: bool,
+}
+
+impl TodoItem {
+    pub fn from_task(task: &Task) -> Self {
+        Self { task: task.clone(), letter: None, completed: None, created: None, shortened: false }
+    }
+
+    /// `+project` names in the title, without the `+`.
+    pub fn projects(&self) -> Vec<&str> {
+        tokens_with(&self.task.title, '+')
+    }
+
+    /// `@context` names in the title, without the `@`.
+    pub fn contexts(&self) -> Vec<&str> {
+        tokens_with(&self.task.title, '@')
+    }
+
+    /// `key:value` pairs in the title, in order.
+    pub fn extras(&self) -> Vec<(&str, &str)> {
+        self.task.title.split_whitespace().filter_map(parse_pair).collect()
+    }
+
+    /// The letter to write for the task's current priority: the original
+    /// one if it still means the same, otherwise A, B or C (none for an
+    /// unmarked Normal task).
+    fn priority_letter(&self) -> Option<char> {
+        match self.letter {
+            Some(l) if priority_from_letter(l) == self.task.priority => Some(l),
+            None if self.task.priority == Priority::Normal => None,
+            _ => Some(letter_for(self.task.priority)),
+        }
+    }
+}
+
+fn tokens_with(title: &str, sigil: char) -> Vec<&str> {
+    title
+        .split_whitespace()
+        .filter_map(|w| w.strip_prefix(sigil))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn priority_from_letter(l: char) -> Priority {
+    match l {
+        'A' => Priority::High,
+        'B' => Priority::Normal,
+        _ => Priority::Low,
+    }
+}
+
+fn letter_for(p: Priority) -> char {
+    match p {
+        Priority::High => 'A',
+        Priority::Normal => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+fn is_letter(s: &str) -> bool {
+    s.len() == 1 && s.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// `(A)` style priority.
+fn parse_letter(word: &str) -> Option<char> {
+    let l = word.strip_prefix('(')?.strip_suffix(')')?;
+    is_letter(l).then(|| l.chars().next()).flatten()
+}
+
+/// A `key:value` word. URLs (`https://…`) and times aren't pairs.
+fn parse_pair(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let valid_key =
+        key.starts_with(|c: char| c.is_ascii_alphabetic()) && key.chars().all(|c| c.is_ascii_alphanumeric());
+    if !valid_key || value.is_empty() || value.starts_with("//") {
+        return None;
+    }
+    Some((key, value))
+}
+
+/// Parse one line; blank lines and lines without a title give `None`. The
+/// task's ID is 0 for the caller to assign, and so is its uid if the line
+/// has none.
+pub fn parse_line(line: &str) -> Option<TodoItem> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let mut words = line.split(' ').filter(|w| !w.is_empty()).peekable();
+    let mut item = TodoItem::from_task(&Task::new(0, String::new()));
+    if words.peek() == Some(&"x") {
+        words.next();
+        item.task.done = true;
+        item.completed = words.peek().and_then(|w| Date::parse(w));
+        if item.completed.is_some() {
+            words.next();
+        }
+    } else if let Some(l) = words.peek().and_then(|w| parse_letter(w)) {
+        words.next();
+        item.letter = Some(l);
+        item.task.priority = priority_from_letter(l);
+    }
+    item.created = words.peek().and_then(|w| Date::parse(w));
+    if item.created.is_some() {
+        words.next();
+    }
+    let mut title: Vec<&str> = Vec::new();
+    for word in words {
+        match parse_pair(word) {
+            Some(("pri", v)) if item.task.done && item.letter.is_none() && is_letter(v) => {
+                let l = v.chars().next().unwrap_or('B');
+                item.letter = Some(l);
+                item.task.priority = priority_from_letter(l);
+            }
+            Some(("uid", v)) if item.task.uid.is_nil() && Uid::parse(v).is_some() => {
+                item.task.uid = Uid::parse(v).unwrap_or(Uid::NIL);
+            }
+            _ => title.push(word),
+        }
+    }
+    (item.task.title, item.shortened) = fit_title(&title.join(" "), TASK_TITLE_MAX)?;
+    Some(item)
+}
+
+/// Write one line, without a line break.
+pub fn to_line(item: &TodoItem) -> String {
+    let mut words: Vec<String> = Vec::new();
+    let letter = item.priority_letter();
+    if item.task.done {
+        words.push(String::from("x"));
+        if let Some(d) = item.completed {
+            words.push(d.display());
+        }
+    } else if let Some(l) = letter {
+        words.push(format!("({})", l));
+    }
+    if let Some(d) = item.created {
+        words.push(d.display());
+    }
+    if !item.task.title.is_empty() {
+        words.push(item.task.title.clone());
+    }
+    if item.task.done {
+        if let Some(l) = letter {
+            words.push(format!("pri:{}", l));
+        }
+    }
+    if !item.task.uid.is_nil() {
+        words.push(format!("uid:{}", item.task.uid));
+    }
+    words.join(" ")
+}
+
+/// Parse a whole todo.txt file.
+pub fn parse(text: &str) -> Vec<TodoItem> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+/// Write a whole todo.txt file, one line per item.
+pub fn serialize<'a>(items: impl IntoIterator<Item = &'a TodoItem>) -> String {
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&to_line(item));
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,103 @@
+//! The Markdown and plain-text agendas, byte for byte.
+
+mod common;
+
+use common::{event, task};
+use planner_core::planner::{agenda_markdown, agenda_text, Date, Event, Priority, Task, Time};
+
+/// Wednesday to Friday, with a day off in the middle and events either
+/// side of the range.
+fn week() -> (Vec<Event>, Vec<Task>) {
+    let events = vec![
+        event(3, None, "Before", Priority::High),
+        event(4, Some(Time::new(14, 30)), "Review", Priority::High),
+        event(4, None, "Holiday", Priority::Low),
+        event(4, Some(Time::new(9, 5)), "Standup *notes*", Priority::Normal),
+        event(6, Some(Time::new(17, 0)), "Ship [v2]", Priority::Low),
+        event(7, None, "After", Priority::Normal),
+    ];
+    let tasks = vec![
+        task("Pay rent", true, Priority::High),
+        task("Call _Sam_", false, Priority::Low),
+        task("Buy milk", false, Priority::High),
+        task("Taxes", true, Priority::Normal),
+    ];
+    (events, tasks)
+}
+
+#[test]
+fn markdown() {
+    let (events, tasks) = week();
+    let expected = "\
+# Agenda 2026-03-04 – 2026-03-06
+
+## Wed 2026-03-04
+
+- All day Holiday
+- 9:05AM \\* Standup \\*notes\\*
+- 2:30PM ! Review
+
+## Thu 2026-03-05
+
+_Nothing scheduled_
+
+## Fri 2026-03-06
+
+- 5:00PM Ship \\[v2\\]
+
+## Tasks
+
+- [ ] ! Buy milk
+- [ ] Call \\_Sam\\_
+- [x] ! Pay rent
+- [x] \\* Taxes
+";
+    assert_eq!(agenda_markdown(&events, &tasks, Date::new(2026, 3, 4), Date::new(2026, 3, 6)), expected);
+}
+
+#[test]
+fn text() {
+    let (events, tasks) = week();
+    let expected = "\
+Agenda 2026-03-04 - 2026-03-06
+
+Wed 2026-03-04
+  All day   Holiday
+   9:05AM * Standup *notes*
+   2:30PM ! Review
+
+Thu 2026-03-05
+  (nothing scheduled)
+
+Fri 2026-03-06
+   5:00PM   Ship [v2]
+
+Tasks
+  [ ] ! Buy milk
+  [ ]   Call _Sam_
+  [x] ! Pay rent
+  [x] * Taxes
+";
+    assert_eq!(agenda_text(&events, &tasks, Date::new(2026, 3, 4), Date::new(2026, 3, 6)), expected);
+}
+
+#[test]
+fn nothing_at_all() {
+    let day = Date::new(2026, 3, 4);
+    assert_eq!(
+        agenda_markdown(&[], &[], day, day),
+        "# Agenda 2026-03-04 – 2026-03-04\n\n## Wed 2026-03-04\n\n_Nothing scheduled_\n\n## Tasks\n\n_No tasks_\n"
+    );
+    assert_eq!(
+        agenda_text(&[], &[], day, day),
+        "Agenda 2026-03-04 - 2026-03-04\n\nWed 2026-03-04\n  (nothing scheduled)\n\nTasks\n  (none)\n"
+    );
+}
+
+#[test]
+fn line_breaks_are_flattened() {
+    let day = Date::new(2026, 3, 4);
+    let events = [event(4, None, "two\r\nlines", Priority::Low)];
+    assert!(agenda_markdown(&events, &[], day, day).contains("- All day two lines\n"));
+    assert!(agenda_text(&events, &[], day, day).contains("  All day   two lines\n"));
+}
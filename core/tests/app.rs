@@ -0,0 +1,156 @@
+//! `PlannerApp` driven the way the device drives it: keys in, state and
+//! storage out, over `MemBackend`.
+
+mod common;
+
+use common::{add_event, app, day, type_text};
+use planner_core::app::{EditError, EventChanges, PlannerApp, TaskChanges, KEY_ENTER};
+use planner_core::backend::MemBackend;
+use planner_core::planner::{Date, Priority, Time, Uid, EVENT_TITLE_MAX, TASK_TITLE_MAX};
+use planner_core::storage::Storage;
+
+#[test]
+fn keyed_edits_survive_reload() {
+    let mut app = app();
+    app.handle_key('a');
+    type_text(&mut app, "Standup");
+    app.handle_key(KEY_ENTER);
+    app.handle_key('t');
+    app.handle_key('a');
+    type_text(&mut app, "Pay rent");
+    app.handle_key(KEY_ENTER);
+    app.flush();
+
+    let mem = app.storage().unwrap().backend().clone();
+    let mut again: PlannerApp<MemBackend> = PlannerApp::new(day());
+    again.attach_storage(Storage::with_backend(mem));
+    assert_eq!(again.events.len(), 1);
+    assert_eq!(again.events[0].title, "Standup");
+    assert_eq!(again.events[0].uid, app.events[0].uid);
+    assert_eq!(again.tasks.len(), 1);
+    assert_eq!(again.tasks[0].title, "Pay rent");
+}
+
+#[test]
+fn edit_api_checks_input() {
+    let mut app = app();
+    let date = Date::new(2026, 3, 4);
+    assert_eq!(
+        app.create_event(date, None, "  ", Priority::Normal, None),
+        Err(EditError::BadTitle)
+    );
+    assert_eq!(
+        app.create_event(
+            Date::new(2026, 2, 30),
+            None,
+            "Dentist",
+            Priority::Normal,
+            None
+        ),
+        Err(EditError::BadDate)
+    );
+    assert_eq!(
+        app.create_event(date, None, "Dentist", Priority::Normal, Some(42)),
+        Err(EditError::NoSuchCalendar)
+    );
+    app.create_event(date, None, "Dentist", Priority::High, None)
+        .unwrap();
+    assert_eq!(app.events_for_date().len(), 1);
+
+    let uid = app.create_task("Call back", Priority::Normal).unwrap();
+    app.complete_task(uid).unwrap();
+    assert!(app.tasks.iter().any(|t| t.uid == uid && t.done));
+    assert_eq!(
+        app.update_task(Uid([1; 16]), TaskChanges::default()),
+        Err(EditError::NotFound)
+    );
+    assert!(app.save_pending());
+}
+
+#[test]
+fn secret_events_stay_in_their_basis() {
+    let mut app = app();
+    app.attach_basis_storage("secret", Storage::with_backend(MemBackend::new()));
+    let sid = app.calendars.iter().find(|c| c.basis.is_some()).unwrap().id;
+    app.take_changes();
+
+    add_event(&mut app, "hidden", Some(sid));
+    app.flush();
+
+    assert_eq!(app.events.len(), 1);
+    assert_eq!(app.shareable_events().count(), 0);
+    assert!(app.take_changes().is_empty());
+    assert!(!app.export_backup().contains("hidden"));
+    let main = app.storage().unwrap().backend().clone();
+    assert!(!main
+        .map
+        .values()
+        .any(|v| v.windows(6).any(|w| w == b"hidden")));
+
+    app.detach_basis("secret");
+    assert!(app.events.is_empty());
+}
+
+#[test]
+fn api_edits_survive_reload() {
+    let mut app = app();
+    let date = Date::new(2026, 3, 4);
+    let long = "x".repeat(EVENT_TITLE_MAX + 1);
+    assert_eq!(
+        app.create_event(date, None, &long, Priority::Normal, None),
+        Err(EditError::BadTitle)
+    );
+    assert_eq!(
+        app.create_task(&"x".repeat(TASK_TITLE_MAX + 1), Priority::Normal),
+        Err(EditError::BadTitle)
+    );
+    let event = app
+        .create_event(
+            date,
+            Some(Time::new(9, 0)),
+            "Standup",
+            Priority::Normal,
+            None,
+        )
+        .unwrap();
+    let changes = EventChanges {
+        title: Some("Standup, moved".into()),
+        time: Some(None),
+        ..EventChanges::default()
+    };
+    app.update_event(event, changes).unwrap();
+    assert_eq!(
+        app.update_event(Uid([1; 16]), EventChanges::default()),
+        Err(EditError::NotFound)
+    );
+    let task = app.create_task("Pay rent", Priority::Low).unwrap();
+    let changes = TaskChanges {
+        priority: Some(Priority::High),
+        ..TaskChanges::default()
+    };
+    app.update_task(task, changes).unwrap();
+    app.complete_task(task).unwrap();
+    app.flush();
+
+    let mem = app.storage().unwrap().backend().clone();
+    let mut again: PlannerApp<MemBackend> = PlannerApp::new(date);
+    again.attach_storage(Storage::with_backend(mem));
+    let ev = &again.events[0];
+    assert_eq!(again.events.len(), 1);
+    assert_eq!(
+        (ev.uid, ev.title.as_str(), ev.time),
+        (event, "Standup, moved", None)
+    );
+    let t = &again.tasks[0];
+    assert_eq!(again.tasks.len(), 1);
+    assert_eq!((t.uid, t.priority, t.done), (task, Priority::High, true));
+}
+
+#[test]
+fn edits_revise_kept_ical_properties() {
+    let mut app = app();
+    let task = app.create_task("Pay rent", Priority::Low).unwrap();
+    app.tasks[0].extra = vec!["LAST-MODIFIED:20260228T090000Z".into(), "SEQUENCE:2".into()];
+    app.complete_task(task).unwrap();
+    assert_eq!(app.tasks[0].extra, ["SEQUENCE:3"]);
+}
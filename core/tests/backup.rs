@@ -0,0 +1,162 @@
+//! Backup bundles: merging, replacing, and the ID remapping both share.
+
+mod common;
+
+use common::{add_event, app, day};
+use planner_core::app::PlannerApp;
+use planner_core::backend::MemBackend;
+use planner_core::backup::{Backup, ImportMode, ImportReport};
+use planner_core::planner::{CalendarId, Event, Priority, Task, Uid};
+use planner_core::query::SavedQuery;
+use planner_core::storage::{SnapshotReason, Storage};
+
+fn event(id: u32, uid: &str, title: &str) -> Event {
+    let mut e = Event::new(id, day(), title.into());
+    e.uid = Uid::from_name(uid);
+    e
+}
+
+fn task(id: u32, uid: &str, title: &str) -> Task {
+    let mut t = Task::new(id, title.into());
+    t.uid = Uid::from_name(uid);
+    t
+}
+
+fn bundle(events: &[Event], tasks: &[Task], next_id: u32, queries: &[SavedQuery]) -> String {
+    let events: Vec<&Event> = events.iter().collect();
+    Backup::new(day(), &events, tasks, next_id, queries, &[]).to_json()
+}
+
+fn ids(app: &PlannerApp<MemBackend>) -> Vec<u32> {
+    let mut ids: Vec<u32> = app.events.iter().map(|e| e.id).chain(app.tasks.iter().map(|t| t.id)).collect();
+    ids.sort();
+    ids
+}
+
+fn query(name: &str) -> SavedQuery {
+    SavedQuery { name: name.into(), text: "is:pending".into() }
+}
+
+/// Unlock "secret" and add an event to its calendar through the form.
+fn secret_event(app: &mut PlannerApp<MemBackend>, title: &str) -> CalendarId {
+    app.attach_basis_storage("secret", Storage::with_backend(MemBackend::new()));
+    let id = app.calendars.iter().find(|c| c.basis.is_some()).unwrap().id;
+    add_event(app, title, Some(id));
+    app.flush();
+    id
+}
+
+#[test]
+fn round_trip() {
+    let mut from = app();
+    from.create_event(day(), None, "Standup", Priority::High, None).unwrap();
+    from.create_task("Report", Priority::Low).unwrap();
+    let json = from.export_backup();
+
+    let mut to = app();
+    let report = to.import_backup(&json, ImportMode::Merge).unwrap();
+    assert_eq!(report, ImportReport { events_added: 1, tasks_added: 1, ..ImportReport::default() });
+    assert_eq!((to.events[0].uid, to.tasks[0].uid), (from.events[0].uid, from.tasks[0].uid));
+    assert_eq!(to.import_backup(&json, ImportMode::Merge).unwrap().unchanged, 2, "nothing added twice");
+}
+
+#[test]
+fn merge_remaps_taken_ids() {
+    let mut app = app();
+    app.import_backup(&bundle(&[event(1, "a", "mine")], &[task(2, "t", "task")], 3, &[]), ImportMode::Merge).unwrap();
+    assert_eq!(app.next_id, 3);
+
+    let mut changed = event(1, "a", "mine, edited");
+    changed.priority = Priority::High;
+    let incoming = bundle(
+        &[changed, event(1, "b", "theirs"), event(9, "c", "free")],
+        &[task(2, "t", "task"), task(2, "u", "other task")],
+        10,
+        &[query("Todo")],
+    );
+    let report = app.import_backup(&incoming, ImportMode::Merge).unwrap();
+    assert_eq!(
+        report,
+        ImportReport { events_added: 2, tasks_added: 1, remapped: 2, unchanged: 1, conflicts: 1, queries_added: 1 }
+    );
+    assert_eq!(
+        app.events.iter().find(|e| e.uid == Uid::from_name("a")).unwrap().title,
+        "mine",
+        "kept, not overwritten"
+    );
+    assert_eq!(app.events.iter().find(|e| e.uid == Uid::from_name("b")).unwrap().id, 3);
+    assert_eq!(app.events.iter().find(|e| e.uid == Uid::from_name("c")).unwrap().id, 9);
+    assert_eq!(app.tasks.iter().find(|t| t.uid == Uid::from_name("u")).unwrap().id, 10);
+    assert_eq!(ids(&app), [1, 2, 3, 9, 10]);
+    assert_eq!(app.next_id, 11);
+}
+
+#[test]
+fn large_ids_move_the_counter() {
+    let mut app = app();
+    let big = u32::MAX - 2;
+    app.import_backup(&bundle(&[event(big, "a", "far")], &[], 1, &[]), ImportMode::Merge).unwrap();
+    assert_eq!(app.next_id, big + 1);
+    let report = app.import_backup(&bundle(&[event(big, "b", "clash")], &[], 1, &[]), ImportMode::Merge).unwrap();
+    assert_eq!(report.remapped, 1);
+    assert_eq!(ids(&app), [big, big + 1]);
+    assert_eq!(app.next_id, big + 2);
+}
+
+#[test]
+fn replace() {
+    let mut app = app();
+    app.create_event(day(), None, "old", Priority::Normal, None).unwrap();
+    app.create_task("old task", Priority::Normal).unwrap();
+    let old_next = app.next_id;
+    let mut anonymous = Event::new(1, day(), "no uid".into());
+    anonymous.uid = Uid::NIL;
+
+    let report = app
+        .import_backup(
+            &bundle(&[anonymous, event(7, "b", "new")], &[task(8, "t", "new task")], 2, &[query("Todo")]),
+            ImportMode::Replace,
+        )
+        .unwrap();
+    assert_eq!(report, ImportReport { events_added: 2, tasks_added: 1, queries_added: 1, ..ImportReport::default() });
+    assert_eq!(app.events.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), ["no uid", "new"]);
+    assert!(app.events.iter().all(|e| !e.uid.is_nil()));
+    assert_eq!(app.tasks[0].title, "new task");
+    assert_eq!(ids(&app), [1, 7, 8]);
+    assert_eq!(app.next_id, old_next.max(9));
+    let snapshots = app.storage().unwrap().snapshots().unwrap();
+    assert_eq!((&snapshots[0].reason, snapshots[0].events), (&SnapshotReason::Before("import".into()), 1));
+
+    let mut reopened = PlannerApp::new(day());
+    reopened.attach_storage(Storage::with_backend(app.storage().unwrap().backend().clone()));
+    assert_eq!(ids(&reopened), [1, 7, 8]);
+    assert_eq!(reopened.next_id, app.next_id);
+}
+
+#[test]
+fn replace_keeps_secret_ids() {
+    let mut app = app();
+    app.create_event(day(), None, "open", Priority::Normal, None).unwrap();
+    let cal = secret_event(&mut app, "hidden");
+    let secret_id = app.events.iter().find(|e| e.calendar == cal).unwrap().id;
+    assert_eq!((secret_id, app.next_id), (2, 3));
+
+    // The backup came from elsewhere, where 2 and 3 meant something else.
+    let incoming = bundle(&[event(1, "a", "one"), event(2, "b", "two")], &[task(3, "t", "three")], 4, &[]);
+    let report = app.import_backup(&incoming, ImportMode::Replace).unwrap();
+    assert_eq!((report.events_added, report.tasks_added, report.remapped), (2, 1, 2));
+
+    let hidden = app.events.iter().find(|e| e.title == "hidden").unwrap();
+    assert_eq!((hidden.id, hidden.calendar), (secret_id, cal));
+    assert_eq!(ids(&app), [1, 2, 3, 4]);
+    assert!(app.next_id > 4);
+
+    // Each record is where it belongs, under its own ID.
+    let main = app.storage().unwrap().backend().clone();
+    let secret = app.calendar_storage(cal).unwrap().backend().clone();
+    let mut reopened = PlannerApp::new(day());
+    reopened.attach_storage(Storage::with_backend(main));
+    reopened.attach_basis_storage("secret", Storage::with_backend(secret));
+    assert_eq!(ids(&reopened), [1, 2, 3, 4]);
+    assert_eq!(reopened.events.iter().find(|e| e.id == secret_id).unwrap().title, "hidden");
+}
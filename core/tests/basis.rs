@@ -0,0 +1,134 @@
+//! Calendars pinned to secret bases: their events exist only while the
+//! basis is attached, and nothing about them is written outside it.
+
+mod common;
+
+use common::add_event;
+use planner_core::app::{AppState, PlannerApp, KEY_DOWN, KEY_ENTER, KEY_MENU};
+use planner_core::backend::MemBackend;
+use planner_core::calendar::BASIS_CALENDAR_BASE;
+use planner_core::planner::CalendarId;
+use planner_core::storage::Storage;
+
+fn app() -> PlannerApp<MemBackend> {
+    let mut app = common::app();
+    add_event(&mut app, "open", None);
+    app
+}
+
+fn secret_id(app: &PlannerApp<MemBackend>) -> CalendarId {
+    app.calendars.iter().find(|c| c.basis.is_some()).unwrap().id
+}
+
+fn mentions(mem: &MemBackend, text: &str) -> bool {
+    mem.map.values().any(|v| v.windows(text.len()).any(|w| w == text.as_bytes()))
+}
+
+/// Unlock "secret" and add an event to its calendar, whose ID is returned.
+fn with_secret_event(app: &mut PlannerApp<MemBackend>) -> CalendarId {
+    app.attach_basis_storage("secret", Storage::with_backend(MemBackend::new()));
+    let id = secret_id(app);
+    add_event(app, "hidden", Some(id));
+    app.flush();
+    id
+}
+
+#[test]
+fn events_stay_inside_the_basis() {
+    let mut app = app();
+    let id = with_secret_event(&mut app);
+    assert!(id >= BASIS_CALENDAR_BASE);
+    assert_eq!(app.attached_bases(), ["secret"]);
+    assert_eq!(app.events_for_date().len(), 2);
+
+    let secret = app.calendar_storage(id).unwrap().backend().clone();
+    assert_eq!(secret.map.keys().filter(|k| k.starts_with("event.")).count(), 1);
+    assert!(mentions(&secret, "hidden"));
+    let main = app.storage().unwrap().backend().clone();
+    assert!(!mentions(&main, "hidden"));
+    assert!(!mentions(&main, "secret"), "not even the calendar's name");
+}
+
+#[test]
+fn lock_and_unlock() {
+    let mut app = app();
+    let id = with_secret_event(&mut app);
+    let secret = app.calendar_storage(id).unwrap().backend().clone();
+
+    app.detach_basis("secret");
+    assert_eq!(app.events.len(), 1);
+    assert!(app.calendars.iter().all(|c| c.basis.is_none()));
+    app.reload_storage();
+    assert_eq!(app.state, AppState::DayView);
+    assert_eq!(app.events.len(), 1);
+
+    app.attach_basis_storage("secret", Storage::with_backend(secret));
+    assert_eq!(app.events.len(), 2);
+    app.reload_storage();
+    assert_eq!(app.events.len(), 2, "reloads keep attached bases");
+}
+
+#[test]
+fn locking_mid_edit_leaves_the_form() {
+    let mut app = app();
+    let id = with_secret_event(&mut app);
+    let row = app.events_for_date().iter().position(|e| e.calendar == id).unwrap();
+    for _ in 0..row {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key('e');
+    assert_eq!(app.state, AppState::EditEvent);
+    app.detach_basis("secret");
+    assert_eq!(app.state, AppState::DayView);
+    assert_ne!(app.form_calendar, id);
+}
+
+#[test]
+fn hiding_is_saved_inside_the_basis() {
+    let mut app = app();
+    let id = with_secret_event(&mut app);
+    app.handle_key('c');
+    while app.calendars[app.calendar_cursor].id != id {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key(KEY_ENTER);
+    app.handle_key(KEY_MENU);
+    app.save_state();
+    assert_eq!(app.events_for_date().len(), 1);
+    let secret = app.calendar_storage(id).unwrap().backend().clone();
+    assert!(secret.map.contains_key("calendar"));
+    assert!(!mentions(app.storage().unwrap().backend(), "secret"));
+
+    app.detach_basis("secret");
+    app.attach_basis_storage("secret", Storage::with_backend(secret));
+    let cal = app.calendars.iter().find(|c| c.basis.is_some()).unwrap();
+    assert!(!cal.visible);
+}
+
+#[test]
+fn snapshots_leave_secrets_out_and_alone() {
+    let mut app = app();
+    let id = with_secret_event(&mut app);
+    app.handle_key('s');
+    app.handle_key('n');
+    assert!(app.snapshots.iter().all(|s| s.events == 1));
+    assert!(!mentions(app.storage().unwrap().backend(), "hidden"));
+
+    // Restoring doesn't touch the basis calendar.
+    app.handle_key('r');
+    app.handle_key('r');
+    assert_eq!(app.events.len(), 2, "{:?}", app.snapshot_status);
+    assert!(app.calendar_storage(id).unwrap().backend().map.keys().any(|k| k.starts_with("event.")));
+}
+
+#[test]
+fn unreadable_basis_is_skipped() {
+    let mut app = app();
+    let mut broken = MemBackend::new();
+    broken.map.insert("schema".into(), b"4".to_vec());
+    broken.map.insert("index.events".into(), br#"{"version":4,"items":[1]}"#.to_vec());
+    broken.map.insert("event.1".into(), b"{".to_vec());
+    app.attach_basis_storage("secret", Storage::with_backend(broken));
+    assert!(app.attached_bases().is_empty());
+    assert_eq!(app.state, AppState::DayView);
+}
@@ -0,0 +1,143 @@
+//! Work, Personal and Shared calendars, each in its own dictionary.
+
+mod common;
+
+use common::{add_event, day};
+use planner_core::app::{PlannerApp, KEY_DOWN, KEY_ENTER, KEY_MENU, KEY_RIGHT};
+use planner_core::backend::MemBackend;
+use planner_core::calendar::DEFAULT_CALENDAR;
+use planner_core::planner::CalendarId;
+use planner_core::storage::{SnapshotReason, Storage};
+
+const WORK: CalendarId = 1;
+const SHARED: CalendarId = 2;
+
+fn open(main: MemBackend, work: MemBackend, shared: MemBackend) -> PlannerApp<MemBackend> {
+    let mut app = PlannerApp::new(day());
+    app.attach_calendar_storage(WORK, Storage::with_backend(work));
+    app.attach_calendar_storage(SHARED, Storage::with_backend(shared));
+    app.attach_storage(Storage::with_backend(main));
+    app
+}
+
+fn fresh() -> PlannerApp<MemBackend> {
+    open(MemBackend::new(), MemBackend::new(), MemBackend::new())
+}
+
+fn backends(app: &mut PlannerApp<MemBackend>) -> (MemBackend, MemBackend, MemBackend) {
+    app.flush();
+    (
+        app.storage().unwrap().backend().clone(),
+        app.calendar_storage(WORK).unwrap().backend().clone(),
+        app.calendar_storage(SHARED).unwrap().backend().clone(),
+    )
+}
+
+fn event_keys(mem: &MemBackend) -> usize {
+    mem.map.keys().filter(|k| k.starts_with("event.")).count()
+}
+
+/// Flip the visibility of the calendar at `row` on the Calendars screen.
+fn toggle(app: &mut PlannerApp<MemBackend>, row: usize) {
+    app.handle_key('c');
+    for _ in 0..row {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key(KEY_ENTER);
+    app.handle_key(KEY_MENU);
+}
+
+#[test]
+fn each_calendar_has_its_own_dictionary() {
+    let mut app = fresh();
+    assert_eq!(app.calendars.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["Personal", "Work", "Shared"]);
+    add_event(&mut app, "home", None);
+    add_event(&mut app, "job", Some(WORK));
+    add_event(&mut app, "party", Some(SHARED));
+    assert_eq!(app.events.iter().map(|e| e.calendar).collect::<Vec<_>>(), [DEFAULT_CALENDAR, WORK, SHARED]);
+
+    let (main, work, shared) = backends(&mut app);
+    assert_eq!((event_keys(&main), event_keys(&work), event_keys(&shared)), (1, 1, 1));
+    // The ID counter (and the registry, once changed) live in the main
+    // dictionary only.
+    assert!(main.map.contains_key("next_id"));
+    assert!(!work.map.contains_key("calendars") && !work.map.contains_key("next_id"));
+
+    let reopened = open(main, work, shared);
+    let job = reopened.events.iter().find(|e| e.title == "job").unwrap();
+    assert_eq!(job.calendar, WORK);
+    let mut ids: Vec<u32> = reopened.events.iter().map(|e| e.id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 3, "IDs are unique across calendars");
+}
+
+#[test]
+fn hidden_calendars_drop_out_of_views() {
+    let mut app = fresh();
+    add_event(&mut app, "home", None);
+    add_event(&mut app, "job", Some(WORK));
+    assert_eq!(app.events_for_date().len(), 2);
+    assert_eq!(app.calendar_markers_for(day()), "PW");
+
+    toggle(&mut app, 1);
+    assert!(!app.calendar_visible(WORK));
+    assert_eq!(app.events_for_date().len(), 1);
+    assert_eq!(app.event_count_for(day()), 1);
+    assert_eq!(app.calendar_markers_for(day()), "P");
+
+    // Visibility is saved with the registry.
+    let (main, work, shared) = backends(&mut app);
+    let mut reopened = open(main, work, shared);
+    assert!(!reopened.calendar_visible(WORK));
+    assert_eq!(reopened.events.len(), 2);
+    toggle(&mut reopened, 1);
+    assert_eq!(reopened.events_for_date().len(), 2);
+}
+
+#[test]
+fn new_events_skip_a_hidden_default() {
+    let mut app = fresh();
+    toggle(&mut app, 0);
+    app.handle_key('a');
+    assert_eq!(app.form_calendar, WORK);
+}
+
+#[test]
+fn moving_an_event_moves_its_record() {
+    let mut app = fresh();
+    add_event(&mut app, "job", Some(WORK));
+    app.handle_key('e');
+    for _ in 0..4 {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key(KEY_RIGHT);
+    app.handle_key(KEY_ENTER);
+    assert_eq!(app.events[0].calendar, SHARED);
+    let (_, work, shared) = backends(&mut app);
+    assert_eq!((event_keys(&work), event_keys(&shared)), (0, 1));
+}
+
+#[test]
+fn snapshots_remember_calendars() {
+    let mut app = fresh();
+    add_event(&mut app, "job", Some(WORK));
+    app.handle_key('s');
+    app.handle_key('n');
+    app.handle_key(KEY_MENU);
+    app.handle_key('d');
+    app.handle_key('y');
+    assert!(app.events.is_empty());
+
+    app.handle_key('s');
+    let manual = app.snapshots.iter().position(|s| s.reason == SnapshotReason::Manual).unwrap();
+    for _ in 0..manual {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key('r');
+    app.handle_key('r');
+    assert_eq!(app.events.len(), 1, "{:?}", app.snapshot_status);
+    assert_eq!(app.events[0].calendar, WORK);
+    let (main, work, _) = backends(&mut app);
+    assert_eq!((event_keys(&main), event_keys(&work)), (0, 1));
+}
@@ -0,0 +1,95 @@
+//! Change notices: each edit is reported once, folded into where the item
+//! ended up, and never again once taken.
+
+use std::collections::BTreeMap;
+
+mod common;
+
+use planner_core::app::{ChangeKind, Changes, EventChanges, PlannerApp, TaskChanges, KEY_DOWN, KEY_ENTER};
+use planner_core::backend::MemBackend;
+use planner_core::backup::ImportMode;
+use planner_core::planner::{Date, Priority, Uid};
+
+fn app() -> PlannerApp<MemBackend> {
+    let mut app = common::app();
+    assert!(app.take_changes().reset, "loading is a reset");
+    app
+}
+
+fn tasks(list: &[(Uid, ChangeKind)]) -> Changes {
+    Changes { tasks: list.iter().copied().collect(), ..Changes::default() }
+}
+
+fn retitle(title: &str) -> TaskChanges {
+    TaskChanges { title: Some(title.into()), ..TaskChanges::default() }
+}
+
+#[test]
+fn each_change_once() {
+    let mut app = app();
+    let uid = app.create_task("Pay rent", Priority::Normal).unwrap();
+    assert_eq!(app.take_changes(), tasks(&[(uid, ChangeKind::Added)]));
+    assert!(app.take_changes().is_empty(), "already told");
+
+    app.update_task(uid, retitle("Pay the rent")).unwrap();
+    app.update_task(uid, retitle("Pay rent today")).unwrap();
+    app.complete_task(uid).unwrap();
+    assert_eq!(app.take_changes(), tasks(&[(uid, ChangeKind::Changed)]));
+    assert!(app.take_changes().is_empty());
+
+    // A refused edit isn't a change.
+    assert!(app.update_task(uid, retitle("")).is_err());
+    assert!(app.take_changes().is_empty());
+}
+
+#[test]
+fn changes_fold() {
+    let mut app = app();
+    let kept = app.create_task("Kept", Priority::Normal).unwrap();
+    let event = app.create_event(Date::new(2026, 3, 4), None, "Standup", Priority::Normal, None).unwrap();
+    app.take_changes();
+
+    // Added then changed is added; added then removed is nothing.
+    let fresh = app.create_task("Fresh", Priority::Low).unwrap();
+    app.update_task(fresh, retitle("Fresh, renamed")).unwrap();
+    let gone = app.create_task("Gone", Priority::Low).unwrap();
+    app.update_task(kept, retitle("Kept, renamed")).unwrap();
+    app.handle_key('t');
+    while app.tasks[app.task_cursor].uid != gone {
+        app.handle_key(KEY_DOWN);
+    }
+    app.handle_key('d');
+    app.handle_key('y');
+    app.update_event(event, EventChanges { priority: Some(Priority::High), ..EventChanges::default() }).unwrap();
+
+    let changes = app.take_changes();
+    assert_eq!(changes.tasks, BTreeMap::from([(kept, ChangeKind::Changed), (fresh, ChangeKind::Added)]));
+    assert_eq!(changes.events, BTreeMap::from([(event, ChangeKind::Changed)]));
+    assert!(!changes.reset);
+}
+
+#[test]
+fn keyed_edits_are_reported() {
+    let mut app = app();
+    let uid = app.create_task("Pay rent", Priority::Normal).unwrap();
+    app.take_changes();
+    app.handle_key('t');
+    app.handle_key(KEY_ENTER);
+    assert!(app.tasks[0].done);
+    assert_eq!(app.take_changes(), tasks(&[(uid, ChangeKind::Changed)]));
+    app.handle_key('d');
+    app.handle_key('y');
+    assert_eq!(app.take_changes(), tasks(&[(uid, ChangeKind::Removed)]));
+    assert!(app.take_changes().is_empty());
+}
+
+#[test]
+fn imports_reset() {
+    let mut app = app();
+    let json = app.export_backup();
+    app.create_task("Lost in the reset", Priority::Normal).unwrap();
+    app.import_backup(&json, ImportMode::Replace).unwrap();
+    let changes = app.take_changes();
+    assert!(changes.reset && changes.tasks.is_empty() && changes.events.is_empty());
+    assert!(app.take_changes().is_empty());
+}
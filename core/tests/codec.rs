@@ -0,0 +1,85 @@
+//! Format tags on stored values. The postcard cases need
+//! `cargo test --features binary-storage`.
+
+use planner_core::backend::MemBackend;
+use planner_core::codec::{self, Format};
+use planner_core::planner::{Date, Event, Priority, Time};
+use planner_core::schema::{self, Record, SCHEMA_VERSION};
+use planner_core::storage::Storage;
+
+fn event() -> Event {
+    let mut e = Event::new(12, Date::new(2026, 3, 4), "Standup".into());
+    e.time = Some(Time::new(9, 30));
+    e.priority = Priority::High;
+    e
+}
+
+fn same(a: &Event, b: &Event) -> bool {
+    (a.id, a.uid, a.date, a.time, &a.title, a.priority) == (b.id, b.uid, b.date, b.time, &b.title, b.priority)
+}
+
+#[test]
+fn json_is_tagged() {
+    let blob = codec::encode_as(&event(), Format::Json);
+    assert_eq!(blob[0], 0x01);
+    assert_eq!(Format::of(&blob), Format::Json);
+    assert_eq!(codec::json_body(&blob), Some(serde_json::to_vec(&event()).unwrap().as_slice()));
+    assert!(same(&codec::decode(&blob).unwrap(), &event()));
+}
+
+#[test]
+fn untagged_json_still_reads() {
+    let blob = serde_json::to_vec(&event()).unwrap();
+    assert_eq!(Format::of(&blob), Format::LegacyJson);
+    assert_eq!(codec::json_body(&blob), Some(blob.as_slice()));
+    assert!(same(&codec::decode(&blob).unwrap(), &event()));
+    assert_eq!(codec::decode::<u32>(b"42"), Some(42));
+}
+
+#[test]
+fn garbage_does_not_decode() {
+    assert!(codec::decode::<Event>(b"").is_none());
+    assert!(codec::decode::<Event>(&[0x01, b'{']).is_none());
+    assert!(codec::decode::<Event>(b"{\"id\":1}").is_none());
+}
+
+#[test]
+fn mixed_formats_load_together() {
+    let mut old = event();
+    old.id = 1;
+    let record = |e: &Event, format| codec::encode_as(&Record { version: SCHEMA_VERSION, item: e.clone() }, format);
+    let mut mem = MemBackend::new();
+    mem.map.insert("schema".into(), SCHEMA_VERSION.to_string().into_bytes());
+    mem.map.insert("event.1".into(), record(&old, Format::LegacyJson));
+    mem.map.insert("event.12".into(), record(&event(), Format::preferred()));
+    mem.map.insert("index.events".into(), schema::encode(&[1u32, 12]));
+    let mut st = Storage::with_backend(mem);
+    let loaded = st.load_events().unwrap();
+    assert_eq!(loaded.len(), 2);
+
+    // Rewriting a record converts it to the preferred format.
+    st.save_event(&loaded[0]).unwrap();
+    assert_eq!(Format::of(&st.backend().map["event.1"]), Format::preferred());
+}
+
+#[cfg(not(feature = "binary-storage"))]
+#[test]
+fn json_build_refuses_binary() {
+    assert_eq!(Format::preferred(), Format::Json);
+    // Asking for postcard falls back to JSON rather than writing something
+    // this build couldn't read back.
+    assert_eq!(Format::of(&codec::encode_as(&event(), Format::Postcard)), Format::Json);
+    assert_eq!(codec::decode::<u32>(&[0x02, 42]), None);
+    assert_eq!(codec::json_body(&[0x02, 42]), None);
+}
+
+#[cfg(feature = "binary-storage")]
+#[test]
+fn postcard_round_trip() {
+    assert_eq!(Format::preferred(), Format::Postcard);
+    let blob = codec::encode(&event());
+    assert_eq!(blob[0], 0x02);
+    assert_eq!(codec::json_body(&blob), None);
+    assert!(same(&codec::decode(&blob).unwrap(), &event()));
+    assert!(blob.len() < codec::encode_as(&event(), Format::Json).len() / 2);
+}
@@ -0,0 +1,67 @@
+//! Builders shared by the integration tests. Each test file uses some of
+//! them.
+#![allow(dead_code)]
+
+use planner_core::app::{EventField, PlannerApp, KEY_DOWN, KEY_ENTER, KEY_RIGHT};
+use planner_core::backend::MemBackend;
+use planner_core::planner::{CalendarId, Date, Event, Priority, Task, Time};
+use planner_core::storage::Storage;
+
+/// "Today" for every test planner.
+pub fn day() -> Date {
+    Date::new(2026, 3, 4)
+}
+
+/// A planner over empty storage.
+pub fn app() -> PlannerApp<MemBackend> {
+    app_with(MemBackend::new())
+}
+
+/// A planner over what's in `mem`.
+pub fn app_with(mem: MemBackend) -> PlannerApp<MemBackend> {
+    let mut app = PlannerApp::new(day());
+    app.attach_storage(Storage::with_backend(mem));
+    app
+}
+
+pub fn type_text(app: &mut PlannerApp<MemBackend>, text: &str) {
+    for c in text.chars() {
+        app.handle_key(c);
+    }
+}
+
+/// Add an event through the form, picking `calendar` if given; otherwise
+/// it goes where new events go.
+pub fn add_event(app: &mut PlannerApp<MemBackend>, title: &str, calendar: Option<CalendarId>) {
+    app.handle_key('a');
+    type_text(app, title);
+    if let Some(id) = calendar {
+        for _ in 0..4 {
+            app.handle_key(KEY_DOWN);
+        }
+        assert_eq!(app.form_field, EventField::Calendar);
+        for _ in 0..app.calendars.len() {
+            if app.form_calendar == id {
+                break;
+            }
+            app.handle_key(KEY_RIGHT);
+        }
+        assert_eq!(app.form_calendar, id, "calendar not offered");
+    }
+    app.handle_key(KEY_ENTER);
+}
+
+/// An event in March 2026.
+pub fn event(day: u8, time: Option<Time>, title: &str, priority: Priority) -> Event {
+    let mut e = Event::new(0, Date::new(2026, 3, day), title.into());
+    e.time = time;
+    e.priority = priority;
+    e
+}
+
+pub fn task(title: &str, done: bool, priority: Priority) -> Task {
+    let mut t = Task::new(0, title.into());
+    t.done = done;
+    t.priority = priority;
+    t
+}
@@ -0,0 +1,174 @@
+//! CSV export, and the dry-run import report.
+
+use planner_core::csv::{self, CsvError, Rejected, Warning};
+use planner_core::planner::{Date, Event, Priority, Task, Time, Uid};
+
+fn event(date: Date, time: Option<Time>, title: &str) -> Event {
+    let mut e = Event::new(0, date, title.into());
+    e.time = time;
+    e
+}
+
+fn fields(e: &Event) -> (Date, Option<Time>, &str, Priority, Uid) {
+    (e.date, e.time, e.title.as_str(), e.priority, e.uid)
+}
+
+fn rejected(row: usize, reason: &str) -> Rejected {
+    Rejected { row, reason: reason.into() }
+}
+
+#[test]
+fn export_quotes_what_needs_it() {
+    let mut standup = event(Date::new(2026, 3, 4), Some(Time::new(9, 5)), "Standup, then \"coffee\"");
+    standup.priority = Priority::High;
+    standup.uid = Uid::parse("0f8fad5b-d9cb-469f-a165-70867728950e").unwrap();
+    let notes = event(Date::new(2026, 3, 5), None, " two\nlines");
+    assert_eq!(
+        csv::write_events([&standup, &notes]),
+        "date,time,title,priority,uid\r\n\
+         2026-03-04,09:05,\"Standup, then \"\"coffee\"\"\",High,0f8fad5b-d9cb-469f-a165-70867728950e\r\n\
+         2026-03-05,,\" two\nlines\",Normal,\r\n"
+    );
+
+    let mut task = Task::new(1, "Pay rent".into());
+    task.done = true;
+    assert_eq!(csv::write_tasks([&task]), "title,done,priority,uid\r\nPay rent,yes,Normal,\r\n");
+}
+
+#[test]
+fn events_round_trip() {
+    let mut a = event(Date::new(2026, 1, 31), Some(Time::new(0, 0)), "a, \"b\"; c");
+    a.priority = Priority::Low;
+    a.uid = Uid::from_name("a");
+    let b = event(Date::new(2026, 12, 31), None, "all day");
+    let report = csv::read_events(&csv::write_events([&a, &b]), &[]).unwrap();
+    assert!(report.rejected.is_empty(), "{:?}", report.rejected);
+    assert_eq!(report.accepted.iter().map(fields).collect::<Vec<_>>(), [fields(&a), fields(&b)]);
+}
+
+#[test]
+fn tasks_round_trip() {
+    let mut a = Task::new(1, "Call Sam, then \"Jo\"".into());
+    a.done = true;
+    a.priority = Priority::High;
+    let b = Task::new(2, "Plain".into());
+    let report = csv::read_tasks(&csv::write_tasks([&a, &b]), &[]).unwrap();
+    assert_eq!(
+        report.accepted.iter().map(|t| (t.title.as_str(), t.done, t.priority)).collect::<Vec<_>>(),
+        [("Call Sam, then \"Jo\"", true, Priority::High), ("Plain", false, Priority::Normal)]
+    );
+}
+
+#[test]
+fn display_formats_are_read() {
+    let t = Time::new(14, 30);
+    let d = Date::new(2026, 3, 4);
+    let input = format!(
+        "Title,Date,Time\n\
+         Lunch,{},{}\n\
+         Late,{},{}\n\
+         Breakfast,{},08:15\n\
+         Holiday,{},All day\n",
+        d.display(),
+        t.display(),
+        d.display(),
+        Time::new(0, 5).display(),
+        d.display(),
+        d.display(),
+    );
+    let report = csv::read_events(&input, &[]).unwrap();
+    assert!(report.rejected.is_empty(), "{:?}", report.rejected);
+    assert_eq!(
+        report.accepted.iter().map(|e| (e.date, e.time)).collect::<Vec<_>>(),
+        [(d, Some(t)), (d, Some(Time::new(0, 5))), (d, Some(Time::new(8, 15))), (d, None)]
+    );
+}
+
+#[test]
+fn quoted_fields() {
+    // Quotes doubled, commas and a line break inside quotes; rows are
+    // numbered by the line they start on.
+    let input = "title,done\r\n\
+                 \"Say \"\"hi\"\"\",yes\r\n\
+                 \"a, b\",no\r\n\
+                 \"two\r\nlines\",no\r\n\
+                 \" padded \",x\r\n";
+    let report = csv::read_tasks(input, &[]).unwrap();
+    assert_eq!(
+        report.accepted.iter().map(|t| (t.title.as_str(), t.done)).collect::<Vec<_>>(),
+        [("Say \"hi\"", true), ("a, b", false), ("two lines", false), ("padded", true)]
+    );
+    assert!(report.rejected.is_empty() && report.warnings.is_empty());
+
+    assert_eq!(csv::read_tasks("title\n\"open\n", &[]).unwrap_err(), CsvError::UnterminatedQuote(2));
+    assert_eq!(csv::read_tasks("", &[]).unwrap_err(), CsvError::Empty);
+    assert_eq!(csv::read_events("title\nx\n", &[]).unwrap_err(), CsvError::MissingColumn("date"));
+}
+
+#[test]
+fn report_counts() {
+    let day = Date::new(2026, 3, 4);
+    let mut known = event(day, Some(Time::new(9, 0)), "Standup");
+    known.uid = Uid::from_name("known");
+    let input = format!(
+        "date,time,title,priority,uid,room\n\
+         2026-03-04,9:00AM,Standup,,,4\n\
+         2026-03-05,,Review,high,{},\n\
+         2026-03-06,,Lunch,,,\n\
+         2026-03-06,,Lunch,,,\n\
+         2026-13-01,,Bad month,,,\n\
+         2026-03-07,25:00,Bad time,,,\n\
+         2026-03-07,,Bad priority,urgent,,\n\
+         2026-03-07,,,,,\n\
+         2026-03-07,,{},,,\n\
+         2026-03-07,,Too,many,,,,\n\
+         2026-03-08,,Fine,low,,\n",
+        known.uid,
+        "x".repeat(41),
+    );
+    let report = csv::read_events(&input, &[known]).unwrap();
+    assert_eq!(report.ignored_columns, ["room"]);
+    let forty = "x".repeat(40);
+    assert_eq!(report.accepted.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), ["Lunch", &forty, "Fine"]);
+    assert_eq!(report.warnings, [Warning { row: 10, message: "title shortened to 40 characters".into() }]);
+    assert_eq!(report.duplicates, [2, 3, 5], "same contents, same uid, repeated row");
+    assert_eq!(
+        report.rejected,
+        [
+            rejected(6, "bad date '2026-13-01'"),
+            rejected(7, "bad time '25:00'"),
+            rejected(8, "bad priority 'urgent'"),
+            rejected(9, "no title"),
+            rejected(11, "8 fields, header has 6"),
+        ]
+    );
+    assert_eq!(report.summary(), "3 to add, 5 rejected, 3 duplicates, 1 warnings");
+}
+
+#[test]
+fn task_report_counts() {
+    let input = format!("title,done\nRent,no\nrent,no\nRent,yes\nNew,maybe\n{},\n", "y".repeat(51));
+    let report = csv::read_tasks(&input, &[Task::new(1, "Rent".into())]).unwrap();
+    let fifty = "y".repeat(50);
+    assert_eq!(report.accepted.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), ["rent", &fifty]);
+    assert_eq!(report.duplicates, [2, 4]);
+    assert_eq!(report.rejected, [rejected(5, "bad done value 'maybe'")]);
+    assert_eq!(report.warnings, [Warning { row: 6, message: "title shortened to 50 characters".into() }]);
+}
+
+#[test]
+fn formulas_are_escaped() {
+    let titles = ["=SUM(A1:A9)", "+1 call", "-2 days", "@team", "a=b", "'quoted"];
+    let tasks: Vec<Task> = titles.iter().map(|t| Task::new(0, (*t).into())).collect();
+    let out = csv::write_tasks(&tasks);
+    assert_eq!(
+        out,
+        "title,done,priority,uid\r\n'=SUM(A1:A9),no,Normal,\r\n'+1 call,no,Normal,\r\n'-2 days,no,Normal,\r\n\
+         '@team,no,Normal,\r\na=b,no,Normal,\r\n'quoted,no,Normal,\r\n"
+    );
+    let back = csv::read_tasks(&out, &[]).unwrap();
+    assert_eq!(back.accepted.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), titles);
+
+    let standup = event(Date::new(2026, 3, 4), None, "=HYPERLINK(\"x\")");
+    assert!(csv::write_events([&standup]).contains(",\"'=HYPERLINK(\"\"x\"\")\","));
+}
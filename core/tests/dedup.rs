@@ -0,0 +1,209 @@
+//! Import review: matching incoming items to the planner's, the choices
+//! made on each, and the review screen fed from the import inbox.
+
+mod common;
+
+use common::day;
+use planner_core::app::{AppState, PlannerApp, KEY_DOWN, KEY_ENTER, KEY_MENU};
+use planner_core::backend::{Backend, MemBackend};
+use planner_core::dedup::{normalize_title, Choice, Class, Field, Review};
+use planner_core::planner::{Date, Event, Priority, Task, Time, Uid};
+
+fn event(id: u32, uid: &str, day: u8, time: Option<Time>, title: &str, priority: Priority) -> Event {
+    let mut e = Event::new(id, Date::new(2026, 3, day), title.into());
+    e.uid = if uid.is_empty() { Uid::NIL } else { Uid::from_name(uid) };
+    e.time = time;
+    e.priority = priority;
+    e
+}
+
+fn mine() -> Vec<Event> {
+    vec![
+        event(1, "standup", 4, Some(Time::new(9, 0)), "Standup", Priority::High),
+        event(2, "holiday", 5, None, "Holiday", Priority::Normal),
+    ]
+}
+
+fn incoming() -> Vec<Event> {
+    let nine = Some(Time::new(9, 0));
+    vec![
+        event(0, "standup", 4, nine, "Standup", Priority::High),
+        event(0, "", 4, nine, "standup.", Priority::High),
+        event(0, "elsewhere", 5, None, "  HOLIDAY ", Priority::High),
+        event(0, "", 6, None, "Lunch", Priority::Normal),
+        event(0, "", 6, None, "lunch!", Priority::Low),
+        event(0, "standup", 11, nine, "Standup", Priority::Low),
+    ]
+}
+
+#[test]
+fn normalized_titles() {
+    assert_eq!(normalize_title("  Call\t Bob  about  IT?!  "), "call bob about it");
+    assert_eq!(normalize_title("v2.0"), "v2.0");
+    assert_eq!(normalize_title("..."), "");
+}
+
+#[test]
+fn classify_events() {
+    let review = Review::classify(incoming(), &mine());
+    let classes: Vec<&Class> = review.entries.iter().map(|e| &e.class).collect();
+    assert_eq!(
+        classes,
+        [
+            &Class::Identical(1),
+            &Class::Conflicting(1, vec![Field::Title]),
+            &Class::Conflicting(2, vec![Field::Title, Field::Priority]),
+            &Class::New,
+            &Class::Repeated(3),
+            &Class::Conflicting(1, vec![Field::Date, Field::Priority]),
+        ]
+    );
+    let choices: Vec<&Choice> = review.entries.iter().map(|e| &e.choice).collect();
+    assert_eq!(choices, [&Choice::Skip, &Choice::Skip, &Choice::Skip, &Choice::Accept, &Choice::Skip, &Choice::Skip]);
+    assert_eq!(review.entries[2].existing.as_ref().unwrap().id, 2);
+    assert!(review.entries[3].existing.is_none());
+    assert_eq!(review.summary(), "1 new, 1 identical, 3 conflicting, 1 repeated");
+}
+
+#[test]
+fn classify_tasks() {
+    let mut rent = Task::new(1, "Pay rent".into());
+    rent.uid = Uid::from_name("rent");
+    let mut done = Task::new(0, "pay rent.".into());
+    done.done = true;
+    let review = Review::classify(vec![done, Task::new(0, "Call Bob".into())], &[rent]);
+    assert_eq!(review.entries[0].class, Class::Conflicting(1, vec![Field::Title, Field::Done]));
+    assert_eq!(review.entries[1].class, Class::New);
+}
+
+#[test]
+fn untouched_review_adds_only_new_items() {
+    let review = Review::classify(incoming(), &mine());
+    let resolved = review.resolve(&mine());
+    assert_eq!(resolved.added.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), ["Lunch"]);
+    assert!(resolved.updated.is_empty());
+}
+
+#[test]
+fn choices() {
+    let mut review = Review::classify(incoming(), &mine());
+    let entries = &mut review.entries;
+
+    // Accepting a conflict takes every differing field; toggling one
+    // turns it into a merge of the rest.
+    entries[2].choice = Choice::Accept;
+    assert!(entries[2].takes(Field::Title) && entries[2].takes(Field::Priority));
+    entries[2].toggle(Field::Title);
+    assert_eq!(entries[2].choice, Choice::Merge(vec![Field::Priority]));
+    entries[2].toggle(Field::Date);
+    assert_eq!(entries[2].choice, Choice::Merge(vec![Field::Priority]), "not a differing field");
+    entries[2].toggle(Field::Title);
+    assert_eq!(entries[2].choice, Choice::Merge(vec![Field::Title, Field::Priority]));
+
+    // Two entries for the same item fold into one update.
+    entries[1].choice = Choice::Accept;
+    entries[5].toggle(Field::Date);
+    assert_eq!(entries[5].choice, Choice::Merge(vec![Field::Date]));
+
+    // A repeat accepted anyway is added without the uid.
+    entries[4].choice = Choice::Accept;
+    entries[4].item.uid = Uid::from_name("copy");
+    entries[3].choice = Choice::Skip;
+
+    let resolved = review.resolve(&mine());
+    assert_eq!(resolved.added.len(), 1);
+    assert_eq!((resolved.added[0].title.as_str(), resolved.added[0].uid), ("lunch!", Uid::NIL));
+    assert_eq!(resolved.updated.len(), 2);
+    let standup = resolved.updated.iter().find(|e| e.id == 1).unwrap();
+    assert_eq!(
+        (standup.title.as_str(), standup.date, standup.priority, standup.uid),
+        ("standup.", Date::new(2026, 3, 11), Priority::High, Uid::from_name("standup"))
+    );
+    let holiday = resolved.updated.iter().find(|e| e.id == 2).unwrap();
+    assert_eq!((holiday.title.as_str(), holiday.priority), ("  HOLIDAY ", Priority::High));
+    assert_eq!(holiday.uid, Uid::from_name("holiday"), "keeps its own uid");
+}
+
+const ICS: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:standup\r\nDTSTART:20260304T090000\r\n\
+SUMMARY:Standup\r\nPRIORITY:1\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nUID:lunch\r\nDTSTART;VALUE=DATE:20260306\r\n\
+SUMMARY:Lunch\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+fn app_with_inbox(files: &[(&str, &str)]) -> PlannerApp<MemBackend> {
+    let mut app = common::app();
+    app.create_event(day(), Some(Time::new(9, 0)), "Standup", Priority::Normal, None).unwrap();
+    let mut inbox = MemBackend::new();
+    for (name, text) in files {
+        inbox.write(name, text.as_bytes()).unwrap();
+    }
+    app.attach_inbox(inbox);
+    app
+}
+
+#[test]
+fn inbox_files_are_reviewed_in_order() {
+    let mut app = app_with_inbox(&[("b.csv", "title,done\nPay rent,no\n,no\n"), ("a.ics", ICS)]);
+
+    app.handle_key('i');
+    assert_eq!(app.state, AppState::ImportReview);
+    assert_eq!(app.review_file.as_deref(), Some("a.ics"));
+    assert_eq!(app.review_status.as_deref(), Some("a.ics: 0 issues"));
+    let classes: Vec<&Class> = app.event_review.entries.iter().map(|e| &e.class).collect();
+    assert_eq!(classes, [&Class::Conflicting(1, vec![Field::Priority]), &Class::New]);
+
+    // Merge the priority, add the lunch.
+    app.handle_key('m');
+    app.handle_key(KEY_ENTER);
+    app.handle_key(KEY_MENU);
+    app.handle_key(KEY_ENTER);
+    assert_eq!(app.state, AppState::DayView);
+    let titles: Vec<(&str, Priority)> = app.events.iter().map(|e| (e.title.as_str(), e.priority)).collect();
+    assert_eq!(titles, [("Standup", Priority::High), ("Lunch", Priority::Normal)]);
+
+    // Applied files leave the inbox; CSV without dates holds tasks.
+    app.handle_key('i');
+    assert_eq!(app.review_file.as_deref(), Some("b.csv"));
+    assert_eq!(app.review_status.as_deref(), Some("b.csv: 1 rows rejected"));
+    assert_eq!(app.task_review.entries.len(), 1);
+
+    // Backing out keeps the file for later.
+    app.handle_key(KEY_MENU);
+    assert_eq!(app.state, AppState::DayView);
+    assert!(app.tasks.is_empty());
+    app.handle_key('i');
+    assert_eq!(app.review_file.as_deref(), Some("b.csv"));
+    app.handle_key(KEY_DOWN);
+    app.handle_key(KEY_ENTER);
+    assert_eq!(app.tasks[0].title, "Pay rent");
+
+    app.handle_key('i');
+    assert_eq!(app.review_status.as_deref(), Some("Import inbox is empty"));
+    assert!(app.event_review.is_empty() && app.task_review.is_empty());
+}
+
+#[test]
+fn unreadable_inbox_files() {
+    for (name, text, status) in [
+        ("notes.txt", "hello", "notes.txt: not a .ics or .csv file"),
+        ("x.ics", "hello", "x.ics: line 1: not a content line"),
+        ("x.csv", "", "x.csv: empty file"),
+    ] {
+        let mut app = app_with_inbox(&[(name, text), ("z.ics", ICS)]);
+        app.handle_key('i');
+        assert_eq!(app.state, AppState::ImportReview);
+        assert_eq!(app.review_status.as_deref(), Some(status));
+        assert!(app.event_review.is_empty() && app.task_review.is_empty());
+
+        // Backing out leaves it; Enter drops it so the next file comes up.
+        app.handle_key(KEY_MENU);
+        app.handle_key('i');
+        assert_eq!(app.review_file.as_deref(), Some(name));
+        app.handle_key(KEY_ENTER);
+        assert_eq!(app.events.len(), 1);
+        app.handle_key('i');
+        assert_eq!(app.review_file.as_deref(), Some("z.ics"));
+    }
+
+    let mut app = PlannerApp::<MemBackend>::new(day());
+    app.handle_key('i');
+    assert_eq!(app.review_status.as_deref(), Some("No import inbox"));
+}
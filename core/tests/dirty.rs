@@ -0,0 +1,112 @@
+//! Dirty tracking: edits are queued, coalesced, and written in one batch
+//! per dictionary when the debounce timer fires or the app flushes.
+
+mod common;
+
+use common::day;
+use planner_core::app::{EventChanges, PlannerApp, TaskChanges};
+use planner_core::backend::{FaultBackend, MemBackend};
+use planner_core::planner::Priority;
+use planner_core::storage::Storage;
+
+fn app() -> PlannerApp<FaultBackend<MemBackend>> {
+    let mut app = PlannerApp::new(day());
+    app.attach_storage(Storage::with_backend(FaultBackend::new(MemBackend::new(), None)));
+    app
+}
+
+fn stored(app: &mut PlannerApp<FaultBackend<MemBackend>>) -> &MemBackend {
+    &app.storage().unwrap().backend().inner
+}
+
+fn records(mem: &MemBackend) -> usize {
+    mem.map.keys().filter(|k| k.starts_with("event.") || k.starts_with("task.")).count()
+}
+
+fn retitle(title: &str) -> EventChanges {
+    EventChanges { title: Some(title.into()), ..EventChanges::default() }
+}
+
+#[test]
+fn edits_wait_for_the_timer() {
+    let mut app = app();
+    assert!(!app.save_pending());
+    let before = app.io_stats();
+    app.create_event(day(), None, "Standup", Priority::Normal, None).unwrap();
+    app.create_task("Report", Priority::High).unwrap();
+    assert!(app.save_pending());
+    assert_eq!(records(stored(&mut app)), 0);
+    assert_eq!(app.io_stats().writes, before.writes);
+
+    app.save_dirty();
+    assert!(!app.save_pending());
+    assert_eq!(records(stored(&mut app)), 2);
+    assert!(stored(&mut app).map.contains_key("next_id"));
+    assert_eq!(app.save_stats.batches, 1);
+
+    // Nothing dirty, nothing written.
+    let after = app.io_stats();
+    app.save_dirty();
+    assert_eq!(app.io_stats().writes, after.writes);
+    assert_eq!(app.save_stats.batches, 1);
+}
+
+#[test]
+fn repeated_edits_coalesce() {
+    let mut app = app();
+    let uid = app.create_event(day(), None, "a", Priority::Normal, None).unwrap();
+    app.save_dirty();
+    let before = app.io_stats();
+    let stats = app.save_stats;
+
+    for title in ["b", "c", "d"] {
+        app.update_event(uid, retitle(title)).unwrap();
+    }
+    assert_eq!(app.save_stats.changes - stats.changes, 3);
+    assert_eq!(app.save_stats.coalesced - stats.coalesced, 2);
+    app.save_dirty();
+    assert_eq!(app.io_stats().writes - before.writes, 1, "one record, written once");
+
+    let mut reopened: PlannerApp<MemBackend> = PlannerApp::new(day());
+    reopened.attach_storage(Storage::with_backend(stored(&mut app).clone()));
+    assert_eq!(reopened.events[0].title, "d");
+}
+
+#[test]
+fn deleting_cancels_a_pending_write() {
+    let mut app = app();
+    let uid = app.create_task("t", Priority::Normal).unwrap();
+    app.update_task(uid, TaskChanges { done: Some(true), ..TaskChanges::default() }).unwrap();
+    app.handle_key('t');
+    app.handle_key('d');
+    app.handle_key('y');
+    assert!(app.tasks.is_empty());
+    app.save_dirty();
+    assert_eq!(records(stored(&mut app)), 0);
+}
+
+#[test]
+fn failed_saves_stay_dirty() {
+    let mut app = app();
+    app.create_event(day(), None, "Standup", Priority::Normal, None).unwrap();
+    app.storage().unwrap().backend().writes_left = Some(0);
+    app.save_dirty();
+    assert!(app.save_pending());
+    assert_eq!(app.save_stats.batches, 0);
+
+    app.storage().unwrap().backend().writes_left = None;
+    app.save_dirty();
+    assert!(!app.save_pending());
+    assert_eq!(records(stored(&mut app)), 1);
+}
+
+#[test]
+fn flush_writes_and_syncs() {
+    let mut app = app();
+    app.create_event(day(), None, "Standup", Priority::Normal, None).unwrap();
+    let syncs = app.io_stats().syncs;
+    app.flush();
+    assert!(!app.save_pending());
+    assert_eq!(records(stored(&mut app)), 1);
+    assert!(app.io_stats().syncs > syncs);
+}
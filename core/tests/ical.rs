@@ -0,0 +1,265 @@
+//! iCalendar export, and import of the sample in `fixtures/team.ics`.
+
+use planner_core::ical::{self, IcalError, Issue, IssueKind};
+use planner_core::planner::{Date, Event, Priority, Task, Time, Uid};
+
+fn sample() -> String {
+    std::fs::read_to_string(format!("{}/tests/fixtures/team.ics", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+fn issue(line: usize, name: &str, kind: IssueKind) -> Issue {
+    Issue { line, name: name.into(), kind }
+}
+
+fn fields(e: &Event) -> (Uid, Date, Option<Time>, &str, Priority) {
+    (e.uid, e.date, e.time, e.title.as_str(), e.priority)
+}
+
+#[test]
+fn sample_file() {
+    let mut next_id = 10;
+    let got = ical::import(&sample(), &mut next_id).unwrap();
+    assert_eq!(
+        got.events.iter().map(fields).collect::<Vec<_>>(),
+        [
+            (Uid::from_name("offsite-2026@example.com"), Date::new(2026, 3, 10), None, "Team offsite", Priority::High),
+            (
+                Uid::parse("0f8fad5b-d9cb-469f-a165-70867728950e").unwrap(),
+                Date::new(2026, 3, 11),
+                Some(Time::new(9, 30)),
+                "Standup, daily",
+                Priority::Low,
+            ),
+            // A folded title that's too long once joined, and one with a line break.
+            (
+                Uid::from_name("review@example.com"),
+                Date::new(2026, 3, 12),
+                Some(Time::new(14, 0)),
+                "Quarterly review with the whole engineer",
+                Priority::Normal,
+            ),
+            (
+                Uid::from_name("notes@example.com"),
+                Date::new(2026, 3, 13),
+                Some(Time::new(8, 0)),
+                "Line one line two",
+                Priority::Normal,
+            ),
+        ]
+    );
+    assert_eq!(
+        got.tasks.iter().map(|t| (t.title.as_str(), t.done, t.priority)).collect::<Vec<_>>(),
+        [("Book travel", false, Priority::Normal), ("File expenses", true, Priority::Normal)]
+    );
+    assert_eq!(got.events.iter().map(|e| e.id).collect::<Vec<_>>(), [10, 11, 12, 13]);
+    assert_eq!(got.tasks.iter().map(|t| t.id).collect::<Vec<_>>(), [14, 15]);
+    assert_eq!(next_id, 16);
+
+    assert_eq!(
+        got.issues,
+        [
+            issue(4, "X-WR-CALNAME", IssueKind::Unsupported),
+            issue(5, "VTIMEZONE", IssueKind::Unsupported),
+            issue(19, "DTSTART", IssueKind::Approximated("time zone ignored")),
+            issue(20, "DTEND", IssueKind::Approximated("end dropped; events have only a start")),
+            issue(22, "LOCATION", IssueKind::Unsupported),
+            issue(24, "VALARM", IssueKind::Unsupported),
+            issue(31, "DTSTART", IssueKind::Approximated("UTC time read as local time")),
+            issue(32, "SUMMARY", IssueKind::Approximated("title shortened")),
+            issue(40, "VEVENT", IssueKind::Invalid),
+            issue(47, "STATUS", IssueKind::Approximated("in progress read as not done")),
+        ]
+    );
+    assert_eq!(got.issues[7].to_string(), "line 32: SUMMARY: title shortened");
+}
+
+#[test]
+fn importing_twice_gives_the_same_uids() {
+    let first = ical::import(&sample(), &mut 1).unwrap();
+    let second = ical::import(&sample(), &mut 1).unwrap();
+    assert_eq!(first.events[0].uid, second.events[0].uid);
+    assert_eq!(first.tasks[1].uid, second.tasks[1].uid);
+}
+
+#[test]
+fn export() {
+    let mut offsite = Event::new(5, Date::new(2026, 3, 10), "Offsite; all day".into());
+    offsite.priority = Priority::High;
+    let mut standup = Event::new(6, Date::new(2026, 3, 11), "Standup".into());
+    standup.time = Some(Time::new(9, 5));
+    standup.priority = Priority::Low;
+    standup.uid = Uid::parse("0f8fad5b-d9cb-469f-a165-70867728950e").unwrap();
+    let mut task = Task::new(7, "Report".into());
+    task.done = true;
+
+    let out = ical::export([&offsite, &standup], [&task], Date::new(2026, 3, 1));
+    let expected = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Precursor//Day Planner//EN",
+        "BEGIN:VEVENT",
+        "UID:planner-event-5@precursor",
+        "DTSTAMP:20260301T000000Z",
+        "DTSTART;VALUE=DATE:20260310",
+        "DTEND;VALUE=DATE:20260311",
+        "SUMMARY:Offsite\\; all day",
+        "PRIORITY:1",
+        "END:VEVENT",
+        "BEGIN:VEVENT",
+        "UID:0f8fad5b-d9cb-469f-a165-70867728950e",
+        "DTSTAMP:20260301T000000Z",
+        "DTSTART:20260311T090500",
+        "SUMMARY:Standup",
+        "PRIORITY:9",
+        "END:VEVENT",
+        "BEGIN:VTODO",
+        "UID:planner-task-7@precursor",
+        "DTSTAMP:20260301T000000Z",
+        "SUMMARY:Report",
+        "STATUS:COMPLETED",
+        "PRIORITY:5",
+        "END:VTODO",
+        "END:VCALENDAR",
+        "",
+    ];
+    assert_eq!(out, expected.join("\r\n"));
+}
+
+#[test]
+fn events_round_trip() {
+    let mut events = Vec::new();
+    for (n, (time, priority)) in
+        [(None, Priority::High), (Some(Time::new(0, 0)), Priority::Normal), (Some(Time::new(23, 59)), Priority::Low)]
+            .into_iter()
+            .enumerate()
+    {
+        let mut e = Event::new(n as u32 + 1, Date::new(2026, 12, 31), format!("ünïcødé, \\ {}", n));
+        e.time = time;
+        e.priority = priority;
+        e.uid = Uid::from_name(&e.title);
+        events.push(e);
+    }
+    // Long enough to be folded, with multibyte characters across the fold.
+    events[0].title = "é".repeat(40);
+
+    let out = ical::export_events(&events, Date::new(2026, 1, 1));
+    assert!(out.split("\r\n").all(|l| l.len() <= 75));
+    let back = ical::import(&out, &mut 1).unwrap();
+    assert!(back.issues.is_empty(), "{:?}", back.issues);
+    assert_eq!(back.events.iter().map(fields).collect::<Vec<_>>(), events.iter().map(fields).collect::<Vec<_>>());
+}
+
+#[test]
+fn priorities_on_import() {
+    for (n, expected) in [
+        (0, Priority::Normal),
+        (1, Priority::High),
+        (4, Priority::High),
+        (5, Priority::Normal),
+        (6, Priority::Low),
+        (9, Priority::Low),
+    ] {
+        let text = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20260101\r\nSUMMARY:x\r\nPRIORITY:{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            n
+        );
+        assert_eq!(ical::import(&text, &mut 1).unwrap().events[0].priority, expected, "PRIORITY:{}", n);
+    }
+    let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20260101\nSUMMARY:x\nPRIORITY:10\nEND:VEVENT\nEND:VCALENDAR\n";
+    let got = ical::import(text, &mut 1).unwrap();
+    assert_eq!(got.events[0].priority, Priority::Normal);
+    assert_eq!(got.issues, [issue(5, "PRIORITY", IssueKind::Invalid)]);
+}
+
+#[test]
+fn all_day_events_ending_later() {
+    let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20260101\nDTEND;VALUE=DATE:20260104\nSUMMARY:Trip\nEND:VEVENT\nEND:VCALENDAR\n";
+    let got = ical::import(text, &mut 1).unwrap();
+    assert_eq!(got.events[0].time, None);
+    assert_eq!(got.issues, [issue(4, "DTEND", IssueKind::Approximated("end dropped; events have only a start"))]);
+}
+
+#[test]
+fn not_icalendar() {
+    assert_eq!(ical::import("hello", &mut 1).unwrap_err(), IcalError::BadLine(1));
+    assert_eq!(ical::import("BEGIN:VEVENT\nEND:VEVENT\n", &mut 1).unwrap_err(), IcalError::NotCalendar);
+    assert_eq!(
+        ical::import("BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VCALENDAR\n", &mut 1).unwrap_err(),
+        IcalError::Unbalanced(3, "VCALENDAR".into())
+    );
+    assert_eq!(ical::import("BEGIN:VCALENDAR\n", &mut 1).unwrap_err(), IcalError::Unbalanced(1, "VCALENDAR".into()));
+}
+
+fn task_fields(t: &Task) -> (Uid, &str, bool, Priority, &[String]) {
+    (t.uid, t.title.as_str(), t.done, t.priority, &t.extra)
+}
+
+#[test]
+fn task_properties_are_kept() {
+    let text = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:todo@example.com\r\nSUMMARY:Expenses\r\n\
+                DESCRIPTION:Receipts from the trip\\, all\r\n  of them\r\nDUE;VALUE=DATE:20260320\r\n\
+                ATTENDEE;CN=\"Doe, Jane\":mailto:jane@example.com\r\nCOMPLETED:20260301T100000Z\r\n\
+                END:VTODO\r\nEND:VCALENDAR\r\n";
+    let got = ical::import(text, &mut 1).unwrap();
+    assert!(got.issues.is_empty(), "{:?}", got.issues);
+    assert!(got.tasks[0].done);
+    assert_eq!(
+        got.tasks[0].extra,
+        [
+            "DESCRIPTION:Receipts from the trip\\, all of them",
+            "DUE;VALUE=DATE:20260320",
+            "ATTENDEE;CN=\"Doe, Jane\":mailto:jane@example.com",
+            "COMPLETED:20260301T100000Z",
+        ]
+    );
+}
+
+/// A to-do as another app wrote it, in the order the planner writes.
+const TODO: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Precursor//Day Planner//EN\r\nBEGIN:VTODO\r\n\
+UID:0f8fad5b-d9cb-469f-a165-70867728950e\r\nDTSTAMP:20260301T000000Z\r\nSUMMARY:File expenses\r\n\
+STATUS:COMPLETED\r\nPRIORITY:1\r\nLAST-MODIFIED:20260228T090000Z\r\nSEQUENCE:2\r\n\
+COMPLETED:20260301T100000Z\r\nPERCENT-COMPLETE:100\r\nBEGIN:VALARM\r\nACTION:DISPLAY\r\n\
+TRIGGER:-PT15M\r\nDESCRIPTION:Expenses due\r\nEND:VALARM\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+
+#[test]
+fn to_dos_round_trip_unchanged() {
+    let got = ical::import(TODO, &mut 1).unwrap();
+    assert!(got.issues.is_empty(), "{:?}", got.issues);
+    assert_eq!(ical::export_tasks(&got.tasks, Date::new(2026, 3, 1)), TODO);
+}
+
+#[test]
+fn edited_to_dos_are_revised() {
+    let mut task = ical::import(TODO, &mut 1).unwrap().tasks.remove(0);
+    task.done = false;
+    ical::note_revision(&mut task.extra);
+    ical::note_revision(&mut task.extra);
+    let out = ical::export_tasks([&task], Date::new(2026, 3, 1));
+    let lines: Vec<&str> = out.split("\r\n").collect();
+    assert!(lines.contains(&"SEQUENCE:4"));
+    assert!(lines.contains(&"TRIGGER:-PT15M"));
+    for gone in ["LAST-MODIFIED", "COMPLETED:", "PERCENT-COMPLETE"] {
+        assert!(!out.contains(gone), "{} still written", gone);
+    }
+}
+
+#[test]
+fn tasks_round_trip() {
+    let mut open = Task::new(1, "Book travel; hotel too".into());
+    open.priority = Priority::High;
+    open.uid = Uid::from_name("travel");
+    open.extra = vec!["DUE:20260320T170000".into(), format!("DESCRIPTION:{}", "long notes ".repeat(12))];
+    let mut done = Task::new(2, "File expenses".into());
+    done.done = true;
+    done.priority = Priority::Low;
+    done.uid = Uid::from_name("expenses");
+
+    let out = ical::export_tasks([&open, &done], Date::new(2026, 3, 1));
+    assert!(out.split("\r\n").all(|l| l.len() <= 75));
+    let back = ical::import(&out, &mut 1).unwrap();
+    assert!(back.issues.is_empty(), "{:?}", back.issues);
+    assert_eq!(
+        back.tasks.iter().map(task_fields).collect::<Vec<_>>(),
+        [open, done].iter().map(task_fields).collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,116 @@
+//! Journaled commits under simulated power loss: cut the writes off at
+//! every point in turn, reopen, and expect all of the old state or all of
+//! the new.
+
+use planner_core::backend::{FaultBackend, MemBackend};
+use planner_core::planner::{Date, Event, Task};
+use planner_core::storage::{Batch, Storage};
+
+fn events(ids: std::ops::RangeInclusive<u32>) -> Vec<Event> {
+    ids.map(|id| Event::new(id, Date::new(2026, 3, id as u8), format!("e{}", id))).collect()
+}
+
+fn refs(events: &[Event]) -> Vec<&Event> {
+    events.iter().collect()
+}
+
+/// Two events, no tasks, next ID 3.
+fn old_state() -> MemBackend {
+    let mut st = Storage::with_backend(MemBackend::new());
+    st.save_all(&refs(&events(1..=2)), &[], 3).unwrap();
+    st.backend().clone()
+}
+
+/// What a reopened store holds: (event IDs, task IDs, next ID).
+fn reopen(mem: MemBackend) -> (Vec<u32>, Vec<u32>, u32) {
+    let mut st = Storage::with_backend(mem);
+    st.recover_journal().unwrap();
+    assert!(!st.backend().map.contains_key("journal"));
+    let mut events: Vec<u32> = st.load_events().unwrap().iter().map(|e| e.id).collect();
+    let mut tasks: Vec<u32> = st.load_tasks().unwrap().iter().map(|t| t.id).collect();
+    events.sort();
+    tasks.sort();
+    (events, tasks, st.load_next_id().unwrap())
+}
+
+/// Run `save` with the backend failing after each possible number of
+/// writes, and check every outcome is `old` or `new`. Returns how many
+/// cut-off points ended in each.
+fn crash_everywhere(
+    save: impl Fn(&mut Storage<FaultBackend<MemBackend>>) -> bool,
+    old: (Vec<u32>, Vec<u32>, u32),
+    new: (Vec<u32>, Vec<u32>, u32),
+) -> (usize, usize) {
+    let (mut rolled_back, mut rolled_forward) = (0, 0);
+    for budget in 0.. {
+        let mut st = Storage::with_backend(FaultBackend::new(old_state(), Some(budget)));
+        st.load_events().unwrap();
+        st.load_tasks().unwrap();
+        let ok = save(&mut st);
+        let after = reopen(st.backend().inner.clone());
+        if after == old {
+            assert!(!ok, "budget {}: reported success but nothing landed", budget);
+            rolled_back += 1;
+        } else {
+            assert_eq!(after, new, "budget {}", budget);
+            rolled_forward += 1;
+        }
+        if ok {
+            break;
+        }
+    }
+    (rolled_back, rolled_forward)
+}
+
+#[test]
+fn save_all_is_atomic() {
+    let (back, forward) = crash_everywhere(
+        |st| st.save_all(&refs(&events(3..=5)), &[Task::new(9, "t".into())], 10).is_ok(),
+        (vec![1, 2], vec![], 3),
+        (vec![3, 4, 5], vec![9], 10),
+    );
+    // Cut before the journal is synced: old state. After: new state,
+    // finished on reopen.
+    assert!(back >= 1 && forward >= 2, "{} {}", back, forward);
+}
+
+#[test]
+fn save_batch_is_atomic() {
+    let added = events(7..=7);
+    let task = Task::new(8, "t".into());
+    crash_everywhere(
+        |st| {
+            let batch = Batch {
+                events: refs(&added),
+                deleted_events: vec![1],
+                tasks: vec![&task],
+                next_id: Some(9),
+                ..Default::default()
+            };
+            st.save_batch(&batch).is_ok()
+        },
+        (vec![1, 2], vec![], 3),
+        (vec![2, 7], vec![8], 9),
+    );
+}
+
+#[test]
+fn torn_journal_is_discarded() {
+    let mut mem = old_state();
+    let mut st = Storage::with_backend(FaultBackend::new(mem.clone(), Some(1)));
+    st.load_events().unwrap();
+    // Only the journal makes it out; then chop its tail off.
+    assert!(st.save_all(&refs(&events(3..=5)), &[], 10).is_err());
+    let journal = st.backend().inner.map["journal"].clone();
+    mem.map.insert("journal".into(), journal[..journal.len() - 4].to_vec());
+    assert_eq!(reopen(mem), (vec![1, 2], vec![], 3));
+}
+
+#[test]
+fn failed_commit_is_finished_before_the_next() {
+    let mut st = Storage::with_backend(FaultBackend::new(MemBackend::new(), Some(3)));
+    assert!(st.save_all(&refs(&events(1..=3)), &[], 4).is_err());
+    st.backend().writes_left = None;
+    st.save_event(&Event::new(7, Date::new(2026, 1, 1), "x".into())).unwrap();
+    assert_eq!(reopen(st.backend().inner.clone()), (vec![1, 2, 3, 7], vec![], 4));
+}
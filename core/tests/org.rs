@@ -0,0 +1,85 @@
+//! Org-mode export, checked against the whole expected document.
+
+mod common;
+
+use common::{event, task};
+use planner_core::org;
+use planner_core::planner::{Date, Event, Priority, Task, Time, Uid};
+
+const UID: &str = "0f8fad5b-d9cb-469f-a165-70867728950e";
+
+fn sample() -> (Vec<Event>, Vec<Task>) {
+    let mut standup = event(4, Some(Time::new(9, 0)), "Stand-up", Priority::High);
+    standup.uid = Uid::parse(UID).unwrap();
+    let events = vec![
+        event(6, None, "Later", Priority::Low),
+        event(4, Some(Time::new(14, 5)), "Review", Priority::Normal),
+        standup,
+        event(5, None, "Holiday\nall day", Priority::Normal),
+        event(2, None, "Before", Priority::Normal),
+    ];
+    let mut call = task("Call Bob", false, Priority::High);
+    call.uid = Uid::parse(UID).unwrap();
+    let tasks = vec![task("Buy milk", true, Priority::Normal), call, task("Water plants", false, Priority::Low)];
+    (events, tasks)
+}
+
+#[test]
+fn everything() {
+    let (events, tasks) = sample();
+    let expected = format!(
+        "\
+#+TITLE: Day Planner
+* Events
+** Before
+<2026-03-02 Mon>
+** [#A] Stand-up
+:PROPERTIES:
+:ID:       {uid}
+:END:
+<2026-03-04 Wed 09:00>
+** Review
+<2026-03-04 Wed 14:05>
+** Holiday all day
+<2026-03-05 Thu>
+** [#C] Later
+<2026-03-06 Fri>
+* Tasks
+** TODO [#A] Call Bob
+:PROPERTIES:
+:ID:       {uid}
+:END:
+** TODO [#C] Water plants
+** DONE Buy milk
+",
+        uid = UID
+    );
+    assert_eq!(org::export(&events, &tasks, None), expected);
+}
+
+#[test]
+fn date_range() {
+    let (events, _) = sample();
+    let expected = "\
+#+TITLE: Day Planner
+#+DATE: [2026-03-04 Wed]--[2026-03-05 Thu]
+* Events
+** [#A] Stand-up
+:PROPERTIES:
+:ID:       0f8fad5b-d9cb-469f-a165-70867728950e
+:END:
+<2026-03-04 Wed 09:00>
+** Review
+<2026-03-04 Wed 14:05>
+** Holiday all day
+<2026-03-05 Thu>
+* Tasks
+";
+    let range = Some((Date::new(2026, 3, 4), Date::new(2026, 3, 5)));
+    assert_eq!(org::export(&events, &[], range), expected);
+}
+
+#[test]
+fn empty() {
+    assert_eq!(org::export(&[], &[], None), "#+TITLE: Day Planner\n* Events\n* Tasks\n");
+}
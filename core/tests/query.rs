@@ -0,0 +1,132 @@
+//! The saved-filter query language: parsing each term kind and matching
+//! events and tasks against a fixed "today".
+
+use planner_core::planner::{Date, Event, Priority, Task, Time};
+use planner_core::query::{self, Cmp, DateSpec, Hit, ItemKind, Predicate, Query, QueryError, Term};
+
+fn today() -> Date {
+    Date::new(2026, 3, 1)
+}
+
+fn event(id: u32, date: Date, title: &str, priority: Priority) -> Event {
+    let mut e = Event::new(id, date, title.into());
+    e.priority = priority;
+    e
+}
+
+fn task(id: u32, title: &str, priority: Priority, done: bool) -> Task {
+    let mut t = Task::new(id, title.into());
+    t.priority = priority;
+    t.done = done;
+    t
+}
+
+fn term(text: &str) -> Term {
+    let mut q = Query::parse(text).unwrap();
+    assert_eq!(q.terms.len(), 1, "{}", text);
+    q.terms.remove(0)
+}
+
+#[test]
+fn parses_each_field() {
+    assert_eq!(term("priority:high").pred, Predicate::Priority(Cmp::Eq, Priority::High));
+    assert_eq!(term("p:>=normal").pred, Predicate::Priority(Cmp::Ge, Priority::Normal));
+    assert_eq!(term("pri:<l").pred, Predicate::Priority(Cmp::Lt, Priority::Low));
+    assert_eq!(term("due:<7d").pred, Predicate::Date(Cmp::Lt, DateSpec::Relative(7)));
+    assert_eq!(term("date:>=2w").pred, Predicate::Date(Cmp::Ge, DateSpec::Relative(14)));
+    assert_eq!(term("due:tomorrow").pred, Predicate::Date(Cmp::Eq, DateSpec::Relative(1)));
+    assert_eq!(term("due:2026-03-04").pred, Predicate::Date(Cmp::Eq, DateSpec::Absolute(Date::new(2026, 3, 4))));
+    assert_eq!(term("is:event").pred, Predicate::Kind(ItemKind::Event));
+    assert_eq!(term("type:task").pred, Predicate::Kind(ItemKind::Task));
+    assert_eq!(term("is:completed").pred, Predicate::Done);
+    assert_eq!(term("DONE").pred, Predicate::Done);
+    assert_eq!(term("tag:#Work").pred, Predicate::Tag("Work".into()));
+    assert_eq!(term("title:Lunch").pred, Predicate::Text("lunch".into()));
+    assert_eq!(term("\"Two Words\"").pred, Predicate::Text("two words".into()));
+}
+
+#[test]
+fn negation() {
+    assert!(term("!done").negate);
+    assert!(term("-is:task").negate);
+    // A lone "-" or "!" is text, not an empty negated term.
+    assert_eq!(term("-"), Term { negate: false, pred: Predicate::Text("-".into()) });
+
+    // is:pending is stored as "not done", so negating it means done.
+    assert_eq!(term("is:pending"), Term { negate: true, pred: Predicate::Done });
+    assert_eq!(term("is:open"), Term { negate: true, pred: Predicate::Done });
+    assert_eq!(term("!is:pending"), Term { negate: false, pred: Predicate::Done });
+
+    let open = task(1, "a", Priority::Normal, false);
+    let closed = task(2, "b", Priority::Normal, true);
+    let pending = Query::parse("is:pending").unwrap();
+    assert!(pending.matches_task(&open, today()));
+    assert!(!pending.matches_task(&closed, today()));
+    let not_pending = Query::parse("!is:pending").unwrap();
+    assert!(!not_pending.matches_task(&open, today()));
+    assert!(not_pending.matches_task(&closed, today()));
+}
+
+#[test]
+fn rejects_bad_terms() {
+    assert_eq!(Query::parse("foo:bar"), Err(QueryError::UnknownField("foo".into())));
+    assert_eq!(Query::parse("priority:urgent"), Err(QueryError::BadValue("urgent".into())));
+    assert_eq!(Query::parse("due:soon"), Err(QueryError::BadValue("soon".into())));
+    assert_eq!(Query::parse("due:2026-02-30"), Err(QueryError::BadValue("2026-02-30".into())));
+    // Relative dates stay within about a century either way.
+    assert_eq!(Query::parse("date:<999999999w"), Err(QueryError::BadValue("<999999999w".into())));
+    assert_eq!(Query::parse("date:>2147483647d"), Err(QueryError::BadValue(">2147483647d".into())));
+    assert_eq!(Query::parse("due:-36526d"), Err(QueryError::BadValue("-36526d".into())));
+    assert_eq!(term("due:<5217w").pred, Predicate::Date(Cmp::Lt, DateSpec::Relative(36_519)));
+    assert_eq!(Query::parse("is:maybe"), Err(QueryError::BadValue("maybe".into())));
+    assert_eq!(Query::parse("tag:"), Err(QueryError::BadValue("".into())));
+    assert_eq!(Query::parse("\"a b"), Err(QueryError::UnterminatedQuote));
+    assert_eq!(Query::parse("   "), Ok(Query::default()));
+}
+
+#[test]
+fn matches_events_and_tasks() {
+    let q = Query::parse("priority:high tag:work due:<7d !done").unwrap();
+    let mut e = event(1, Date::new(2026, 3, 4), "Standup #work", Priority::High);
+    assert!(q.matches_event(&e, today()));
+    e.date = Date::new(2026, 3, 9);
+    assert!(!q.matches_event(&e, today()), "outside the 7-day window");
+    e.date = Date::new(2026, 2, 27);
+    assert!(!q.matches_event(&e, today()), "in the past");
+    e.date = Date::new(2026, 3, 4);
+    e.priority = Priority::Normal;
+    assert!(!q.matches_event(&e, today()));
+    // Tasks have no date, so a date term never matches them.
+    assert!(!q.matches_task(&task(2, "Report #work", Priority::High, false), today()));
+
+    let q = Query::parse("p:>=normal is:task").unwrap();
+    assert!(q.matches_task(&task(3, "x", Priority::Normal, false), today()));
+    assert!(q.matches_task(&task(4, "x", Priority::High, true), today()));
+    assert!(!q.matches_task(&task(5, "x", Priority::Low, false), today()));
+    assert!(!q.matches_event(&e, today()));
+
+    let q = Query::parse("due:<=2026-03-01 lunch").unwrap();
+    assert!(q.matches_event(&event(6, Date::new(2026, 2, 1), "Team LUNCH", Priority::Low), today()));
+    assert!(!q.matches_event(&event(7, Date::new(2026, 3, 2), "Lunch", Priority::Low), today()));
+    assert!(!Query::parse("tag:work").unwrap().matches_event(&event(8, today(), "homework", Priority::Low), today()));
+}
+
+#[test]
+fn run_orders_events_then_tasks() {
+    let mut late = event(1, Date::new(2026, 3, 2), "b", Priority::Normal);
+    late.time = Some(Time::new(9, 0));
+    let early = event(2, Date::new(2026, 3, 2), "a", Priority::Normal);
+    let first = event(3, Date::new(2026, 3, 1), "c", Priority::Normal);
+    let events = [late, early, first];
+    let tasks = [task(4, "t", Priority::Normal, false), task(5, "u", Priority::Normal, true)];
+
+    let hits = query::run(&Query::parse("!done").unwrap(), &events, &tasks, today());
+    let ids: Vec<u32> = hits
+        .iter()
+        .map(|h| match h {
+            Hit::Event(e) => e.id,
+            Hit::Task(t) => t.id,
+        })
+        .collect();
+    assert_eq!(ids, [3, 2, 1, 4]);
+}
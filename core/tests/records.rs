@@ -0,0 +1,88 @@
+//! One key per event and task: writes touch only what changed, and the
+//! index keys are rebuilt or pruned when they disagree with the records.
+
+use std::collections::BTreeMap;
+
+use planner_core::app::{PlannerApp, TaskChanges};
+use planner_core::backend::MemBackend;
+use planner_core::planner::{Date, Event, Priority, Task};
+use planner_core::storage::Storage;
+
+type Map = BTreeMap<String, Vec<u8>>;
+
+/// Keys added, changed or removed between two backend states.
+fn touched(before: &Map, after: &Map) -> Vec<String> {
+    let mut keys: Vec<String> =
+        after.iter().filter(|(k, v)| before.get(*k) != Some(*v)).map(|(k, _)| k.clone()).collect();
+    keys.extend(before.keys().filter(|k| !after.contains_key(*k)).cloned());
+    keys.sort();
+    keys
+}
+
+fn stored() -> Storage<MemBackend> {
+    let events: Vec<Event> =
+        (1..=3).map(|id| Event::new(id, Date::new(2026, 3, id as u8), format!("e{}", id))).collect();
+    let tasks: Vec<Task> = (4..=5).map(|id| Task::new(id, format!("t{}", id))).collect();
+    let mut st = Storage::with_backend(MemBackend::new());
+    st.save_all(&events.iter().collect::<Vec<_>>(), &tasks, 6).unwrap();
+    st
+}
+
+#[test]
+fn one_key_per_record() {
+    let mut st = stored();
+    let keys: Vec<&String> = st.backend().map.keys().collect();
+    assert_eq!(keys, ["event.1", "event.2", "event.3", "index.events", "index.tasks", "next_id", "task.4", "task.5"]);
+}
+
+#[test]
+fn edits_touch_only_their_record() {
+    let mut st = stored();
+    let before = st.backend().map.clone();
+    let mut changed = Event::new(2, Date::new(2026, 3, 9), "moved".into());
+    changed.priority = Priority::High;
+    st.save_event(&changed).unwrap();
+    assert_eq!(touched(&before, &st.backend().map), ["event.2"]);
+
+    let before = st.backend().map.clone();
+    st.save_task(&Task::new(7, "new".into())).unwrap();
+    assert_eq!(touched(&before, &st.backend().map), ["index.tasks", "task.7"]);
+
+    let before = st.backend().map.clone();
+    st.delete_event(1).unwrap();
+    assert_eq!(touched(&before, &st.backend().map), ["event.1", "index.events"]);
+    assert_eq!(st.load_events().unwrap().iter().map(|e| e.id).collect::<Vec<_>>(), [2, 3]);
+}
+
+#[test]
+fn app_saves_only_dirty_records() {
+    let mut app: PlannerApp<MemBackend> = PlannerApp::new(Date::new(2026, 3, 1));
+    app.attach_storage(stored());
+    let before = app.storage().unwrap().backend().map.clone();
+    let uid = app.tasks.iter().find(|t| t.id == 5).unwrap().uid;
+    app.update_task(uid, TaskChanges { done: Some(true), ..Default::default() }).unwrap();
+    app.save_dirty();
+    assert_eq!(touched(&before, &app.storage().unwrap().backend().map), ["task.5"]);
+}
+
+#[test]
+fn lost_index_is_rebuilt_from_keys() {
+    let mut mem = stored().backend().clone();
+    mem.map.remove("index.events");
+    let mut st = Storage::with_backend(mem);
+    assert_eq!(st.load_events().unwrap().len(), 3);
+    assert!(st.backend().map.contains_key("index.events"));
+}
+
+#[test]
+fn indexed_but_missing_records_are_dropped() {
+    let mut mem = stored().backend().clone();
+    mem.map.remove("task.4");
+    let mut st = Storage::with_backend(mem);
+    assert_eq!(st.load_tasks().unwrap().iter().map(|t| t.id).collect::<Vec<_>>(), [5]);
+    // The next write to the collection drops it from the index too.
+    st.save_task(&Task::new(8, "t8".into())).unwrap();
+    let mut reopened = Storage::with_backend(st.backend().clone());
+    assert_eq!(reopened.load_tasks().unwrap().iter().map(|t| t.id).collect::<Vec<_>>(), [5, 8]);
+    assert!(!reopened.backend().map.contains_key("task.4"));
+}
@@ -0,0 +1,108 @@
+//! Unreadable stored data: typed errors, quarantine under `recovery.<key>`,
+//! and the Recovery screen's retry, export and start-fresh keys.
+
+mod common;
+
+use planner_core::app::{AppState, PlannerApp};
+use planner_core::backend::MemBackend;
+use planner_core::storage::{Storage, StorageError};
+
+const GARBAGE: &[u8] = b"{garbage";
+
+/// Schema v4 data with one unreadable event next to a readable task.
+fn damaged() -> MemBackend {
+    let mut mem = MemBackend::new();
+    for (key, value) in [
+        ("schema", &b"4"[..]),
+        ("index.events", br#"{"version":4,"items":[1]}"#),
+        ("event.1", GARBAGE),
+        ("index.tasks", br#"{"version":4,"items":[9]}"#),
+        ("task.9", br#"{"version":4,"item":{"id":9,"title":"t","done":false,"priority":"Low"}}"#),
+    ] {
+        mem.map.insert(key.into(), value.to_vec());
+    }
+    mem
+}
+
+fn app() -> PlannerApp<MemBackend> {
+    common::app_with(damaged())
+}
+
+#[test]
+fn corrupt_record_is_quarantined_not_replaced() {
+    let mut st = Storage::with_backend(damaged());
+    assert_eq!(st.load_events().unwrap_err(), StorageError::Corrupt("event.1".into()));
+    assert_eq!(st.recovery_blob("event.1").unwrap().as_deref(), Some(GARBAGE));
+    assert_eq!(st.backend().map.get("event.1").map(Vec::as_slice), Some(GARBAGE));
+    assert_eq!(st.load_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn corrupt_collection_is_reported() {
+    let mut mem = MemBackend::new();
+    mem.map.insert("schema".into(), b"4".to_vec());
+    mem.map.insert("queries".into(), b"[1, 2".to_vec());
+    let mut st = Storage::with_backend(mem);
+    assert_eq!(st.load_queries().unwrap_err(), StorageError::Corrupt("queries".into()));
+    assert_eq!(st.recovery_blob("queries").unwrap(), Some(b"[1, 2".to_vec()));
+}
+
+#[test]
+fn saving_waits_for_recovery() {
+    let mut app = app();
+    assert_eq!(app.state, AppState::Recovery);
+    assert_eq!(app.storage_error, Some(StorageError::Corrupt("event.1".into())));
+    app.save_state();
+    app.flush();
+    let map = &app.storage().unwrap().backend().map;
+    assert_eq!(map.get("event.1").map(Vec::as_slice), Some(GARBAGE));
+    assert_eq!(map.get("task.9"), damaged().map.get("task.9"));
+}
+
+#[test]
+fn retry_after_repair() {
+    let mut app = app();
+    app.handle_key('r');
+    assert_eq!(app.state, AppState::Recovery);
+    assert_eq!(app.recovery_status.as_deref(), Some("Still unreadable"));
+
+    app.storage().unwrap().backend().map.insert(
+        "event.1".into(),
+        br#"{"version":4,"item":{"id":1,"date":{"year":2026,"month":3,"day":4},"time":null,"title":"x","priority":"High"}}"#
+            .to_vec(),
+    );
+    app.handle_key('r');
+    assert_eq!(app.state, AppState::DayView);
+    assert_eq!(app.storage_error, None);
+    assert_eq!((app.events.len(), app.tasks.len()), (1, 1));
+}
+
+#[test]
+fn export_then_start_fresh() {
+    let mut app = app();
+    app.handle_key('x');
+    assert_eq!(app.recovery_status.as_deref(), Some("Logged 8 bytes of 'event.1' as hex"));
+
+    // Starting fresh needs a second press.
+    app.handle_key('f');
+    assert_eq!(app.state, AppState::Recovery);
+    assert!(app.recovery_confirm_fresh);
+    app.handle_key('f');
+    assert_eq!(app.state, AppState::DayView);
+    assert!(app.events.is_empty());
+    assert_eq!(app.tasks.len(), 1);
+    assert_eq!(app.next_id, 10);
+    let map = &app.storage().unwrap().backend().map;
+    assert!(!map.contains_key("event.1"));
+    assert_eq!(map.get("recovery.event.1").map(Vec::as_slice), Some(GARBAGE));
+}
+
+#[test]
+fn other_keys_cancel_start_fresh() {
+    let mut app = app();
+    app.handle_key('f');
+    app.handle_key('x');
+    assert!(!app.recovery_confirm_fresh);
+    app.handle_key('f');
+    assert_eq!(app.state, AppState::Recovery);
+}
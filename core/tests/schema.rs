@@ -0,0 +1,235 @@
+//! Upgrading stored data from every past schema version, starting from
+//! the fixtures in `fixtures/schema_v<N>.json`. Each fixture maps a key to
+//! what an older build stored there; strings are stored as raw text.
+
+use std::collections::BTreeMap;
+
+use planner_core::backend::{Backend, MemBackend};
+use planner_core::planner::{Date, Priority, Time};
+use planner_core::schema::SCHEMA_VERSION;
+use planner_core::storage::{Storage, StorageError};
+use serde_json::Value;
+
+fn fixture(version: u32) -> BTreeMap<String, Vec<u8>> {
+    let path = format!("{}/tests/fixtures/schema_v{}.json", env!("CARGO_MANIFEST_DIR"), version);
+    let text = std::fs::read_to_string(path).unwrap();
+    let keys: BTreeMap<String, Value> = serde_json::from_str(&text).unwrap();
+    keys.into_iter()
+        .map(|(key, value)| {
+            let blob = match value {
+                Value::String(s) => s.into_bytes(),
+                v => serde_json::to_vec(&v).unwrap(),
+            };
+            (key, blob)
+        })
+        .collect()
+}
+
+fn storage(keys: &BTreeMap<String, Vec<u8>>) -> Storage<MemBackend> {
+    Storage::with_backend(MemBackend { map: keys.clone() })
+}
+
+/// The planner state every fixture describes, upgraded from `from`.
+fn check_migrated(st: &mut Storage<MemBackend>, from: u32) {
+    assert_eq!(st.load_schema_version().unwrap(), SCHEMA_VERSION);
+    let mut events = st.load_events().unwrap();
+    events.sort_by_key(|e| e.id);
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        (events[0].id, events[0].date, events[0].time, events[0].title.as_str(), events[0].priority),
+        (1, Date::new(2026, 3, 4), Some(Time::new(9, 30)), "Standup", Priority::High)
+    );
+    assert_eq!(
+        (events[1].id, events[1].date, events[1].time, events[1].title.as_str(), events[1].priority),
+        (2, Date::new(2026, 12, 25), None, "Holiday", Priority::Normal)
+    );
+    if from < 4 {
+        assert!(events.iter().all(|e| e.uid.is_nil()), "uids are assigned by the app, not the migration");
+    }
+    let tasks = st.load_tasks().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(
+        (tasks[0].id, tasks[0].title.as_str(), tasks[0].done, tasks[0].priority),
+        (3, "Pay rent", true, Priority::Low)
+    );
+    if from < 3 {
+        assert!(tasks[0].extra.is_empty());
+    }
+    let queries = st.load_queries().unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!((queries[0].name.as_str(), queries[0].text.as_str()), ("Urgent", "priority:high !done"));
+    assert_eq!(st.load_next_id().unwrap(), 4);
+}
+
+/// Each `(backup key, original key)` must hold the fixture's bytes as they were.
+fn check_backups(st: &mut Storage<MemBackend>, old: &BTreeMap<String, Vec<u8>>, backups: &[(&str, &str)]) {
+    let map = &st.backend().map;
+    for (backup, key) in backups {
+        assert_eq!(map.get(*backup), old.get(*key), "{}", backup);
+    }
+    let found = map.keys().filter(|k| k.starts_with("backup.")).count();
+    assert_eq!(found, backups.len(), "{:?}", map.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn from_v0() {
+    let old = fixture(0);
+    let mut st = storage(&old);
+    assert_eq!(st.load_schema_version().unwrap(), 0);
+    st.upgrade_schema().unwrap();
+    check_migrated(&mut st, 0);
+    check_backups(
+        &mut st,
+        &old,
+        &[("backup.v0.events", "events"), ("backup.v0.tasks", "tasks"), ("backup.v0.queries", "queries")],
+    );
+    let keys = st.backend().list().unwrap();
+    for gone in ["events", "tasks"] {
+        assert!(!keys.iter().any(|k| k == gone), "{} should be split into records", gone);
+    }
+    for kept in ["event.1", "event.2", "task.3", "index.events", "index.tasks"] {
+        assert!(keys.iter().any(|k| k == kept), "{} missing", kept);
+    }
+}
+
+#[test]
+fn from_v1() {
+    let old = fixture(1);
+    let mut st = storage(&old);
+    assert_eq!(st.load_schema_version().unwrap(), 1);
+    st.upgrade_schema().unwrap();
+    check_migrated(&mut st, 1);
+    check_backups(
+        &mut st,
+        &old,
+        &[("backup.v1.events", "events"), ("backup.v1.tasks", "tasks"), ("backup.v1.queries", "queries")],
+    );
+    assert!(!st.backend().map.contains_key("events"));
+}
+
+#[test]
+fn from_v2() {
+    let old = fixture(2);
+    let mut st = storage(&old);
+    assert_eq!(st.load_schema_version().unwrap(), 2);
+    st.upgrade_schema().unwrap();
+    check_migrated(&mut st, 2);
+    check_backups(
+        &mut st,
+        &old,
+        &[
+            ("backup.v2.event.1", "event.1"),
+            ("backup.v2.event.2", "event.2"),
+            ("backup.v2.task.3", "task.3"),
+            ("backup.v2.queries", "queries"),
+            ("backup.v2.calendars", "calendars"),
+        ],
+    );
+    let calendars = st.load_calendars().unwrap();
+    assert_eq!(calendars.len(), 2);
+    assert!(!calendars[1].visible);
+    assert_eq!(calendars[1].basis, None);
+}
+
+#[test]
+fn from_v3() {
+    let old = fixture(3);
+    let mut st = storage(&old);
+    st.upgrade_schema().unwrap();
+    check_migrated(&mut st, 3);
+    check_backups(
+        &mut st,
+        &old,
+        &[
+            ("backup.v3.event.1", "event.1"),
+            ("backup.v3.event.2", "event.2"),
+            ("backup.v3.task.3", "task.3"),
+            ("backup.v3.queries", "queries"),
+            ("backup.v3.calendars", "calendars"),
+        ],
+    );
+    let tasks = st.load_tasks().unwrap();
+    assert!(tasks[0].uid.is_nil());
+    assert_eq!(tasks[0].extra, ["DUE;VALUE=DATE:20260401"]);
+}
+
+#[test]
+fn current_version_is_left_alone() {
+    let mut st = storage(&fixture(0));
+    st.upgrade_schema().unwrap();
+    let migrated = st.backend().map.clone();
+    st.upgrade_schema().unwrap();
+    assert_eq!(st.backend().map, migrated);
+}
+
+#[test]
+fn unmigratable_data_changes_nothing() {
+    let mut old = fixture(0);
+    old.insert("tasks".into(), br#"{"not": "an array"}"#.to_vec());
+    let mut st = storage(&old);
+    assert_eq!(st.upgrade_schema(), Err(StorageError::Corrupt("tasks".into())));
+    let map = &st.backend().map;
+    assert_eq!(map.get("recovery.tasks"), old.get("tasks"));
+    assert!(!map.contains_key("schema"));
+    assert!(!map.keys().any(|k| k.starts_with("backup.") || k.starts_with("event.")));
+}
+
+#[test]
+fn newer_schema_is_refused() {
+    let mut st = storage(&BTreeMap::from([("schema".to_string(), b"99".to_vec())]));
+    assert_eq!(st.upgrade_schema(), Err(StorageError::NewerSchema(99)));
+}
+
+/// Binary records can't be read through JSON; each old task shape has its
+/// own struct.
+#[cfg(feature = "binary-storage")]
+#[test]
+fn binary_task_from_v2() {
+    use planner_core::codec::{self, Format};
+    use planner_core::schema::Record;
+
+    #[derive(serde::Serialize)]
+    struct TaskV2 {
+        id: u32,
+        title: String,
+        done: bool,
+        priority: Priority,
+    }
+    let old = TaskV2 { id: 3, title: "Pay rent".into(), done: true, priority: Priority::Low };
+    let mut keys = BTreeMap::new();
+    keys.insert("schema".to_string(), b"2".to_vec());
+    keys.insert("task.3".to_string(), codec::encode_as(&Record { version: 2, item: old }, Format::Postcard));
+    let mut st = storage(&keys);
+    st.upgrade_schema().unwrap();
+    let tasks = st.load_tasks().unwrap();
+    assert_eq!(
+        (tasks[0].id, tasks[0].title.as_str(), tasks[0].done, tasks[0].extra.len()),
+        (3, "Pay rent", true, 0)
+    );
+}
+
+#[cfg(feature = "binary-storage")]
+#[test]
+fn binary_task_from_v3() {
+    use planner_core::codec::{self, Format};
+    use planner_core::planner::Uid;
+    use planner_core::schema::Record;
+
+    #[derive(serde::Serialize)]
+    struct TaskV3 {
+        id: u32,
+        title: String,
+        done: bool,
+        priority: Priority,
+        extra: Vec<String>,
+    }
+    let extra = vec!["DUE;VALUE=DATE:20260401".to_string()];
+    let old = TaskV3 { id: 3, title: "Pay rent".into(), done: true, priority: Priority::Low, extra: extra.clone() };
+    let mut keys = BTreeMap::new();
+    keys.insert("schema".to_string(), b"3".to_vec());
+    keys.insert("task.3".to_string(), codec::encode_as(&Record { version: 3, item: old }, Format::Postcard));
+    let mut st = storage(&keys);
+    st.upgrade_schema().unwrap();
+    let tasks = st.load_tasks().unwrap();
+    assert_eq!((tasks[0].id, tasks[0].uid, &tasks[0].extra), (3, Uid::NIL, &extra));
+}
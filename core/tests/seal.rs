@@ -0,0 +1,128 @@
+//! Sealed (passphrase-encrypted) exports. Small KDF costs keep these fast;
+//! `open` reads the costs from the header, so that's all it takes.
+
+mod common;
+
+use common::app;
+use planner_core::backup::{BackupError, ImportMode};
+use planner_core::planner::{Date, Priority};
+use planner_core::seal::{self, KdfParams, SealError, NONCE_LEN, SALT_LEN};
+
+const FAST: KdfParams = KdfParams { memory_kib: 64, iterations: 1, lanes: 1 };
+const HEADER_LEN: usize = 8 + 1 + 12 + SALT_LEN + NONCE_LEN + 8;
+const TEXT: &[u8] = b"date,time,title\r\n2026-03-04,09:00,Standup\r\n";
+
+fn sealed() -> Vec<u8> {
+    seal::seal(TEXT, "correct horse", FAST, [7; SALT_LEN], [9; NONCE_LEN]).unwrap()
+}
+
+/// Overwrite the little-endian word at `at` in the header.
+fn set_word(data: &mut [u8], at: usize, value: u32) {
+    data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn round_trip() {
+    let data = sealed();
+    assert!(seal::is_sealed(&data));
+    assert!(data.starts_with(b"DPLANSEA\x01"));
+    assert_eq!(data.len(), HEADER_LEN + TEXT.len() + 16);
+    assert!(!data.windows(7).any(|w| w == b"Standup"));
+    assert_eq!(seal::open(&data, "correct horse").unwrap(), TEXT);
+
+    // A fresh salt and nonce give a different file for the same input.
+    let again = seal::seal(TEXT, "correct horse", FAST, [8; SALT_LEN], [9; NONCE_LEN]).unwrap();
+    assert_ne!(again, data);
+    assert_eq!(seal::open(&again, "correct horse").unwrap(), TEXT);
+}
+
+#[test]
+fn wrong_passphrase() {
+    assert_eq!(seal::open(&sealed(), "correct horse ").unwrap_err(), SealError::WrongPassphrase);
+    assert_eq!(seal::open(&sealed(), "").unwrap_err(), SealError::WrongPassphrase);
+}
+
+#[test]
+fn tampered() {
+    let mut body = sealed();
+    body[HEADER_LEN + 3] ^= 0x01;
+    assert_eq!(seal::open(&body, "correct horse").unwrap_err(), SealError::Tampered);
+
+    let mut tag = sealed();
+    *tag.last_mut().unwrap() ^= 0x80;
+    assert_eq!(seal::open(&tag, "correct horse").unwrap_err(), SealError::Tampered);
+
+    // The nonce is authenticated as part of the header.
+    let mut nonce = sealed();
+    nonce[21 + SALT_LEN] ^= 0x01;
+    assert_eq!(seal::open(&nonce, "correct horse").unwrap_err(), SealError::Tampered);
+
+    // A changed salt derives a different check value.
+    let mut salt = sealed();
+    salt[21] ^= 0x01;
+    assert_eq!(seal::open(&salt, "correct horse").unwrap_err(), SealError::WrongPassphrase);
+}
+
+#[test]
+fn truncated() {
+    let data = sealed();
+    assert_eq!(seal::open(&data[..data.len() - 1], "correct horse").unwrap_err(), SealError::Tampered);
+    assert_eq!(seal::open(&data[..HEADER_LEN + 15], "correct horse").unwrap_err(), SealError::Truncated);
+    assert_eq!(seal::open(&data[..HEADER_LEN], "correct horse").unwrap_err(), SealError::Truncated);
+    assert_eq!(seal::open(&data[..8], "correct horse").unwrap_err(), SealError::Truncated);
+    assert_eq!(seal::open(&data[..4], "correct horse").unwrap_err(), SealError::NotSealed);
+    assert_eq!(seal::open(b"", "correct horse").unwrap_err(), SealError::NotSealed);
+}
+
+#[test]
+fn header_checks() {
+    let mut version = sealed();
+    version[8] = 2;
+    assert_eq!(seal::open(&version, "correct horse").unwrap_err(), SealError::UnsupportedVersion(2));
+
+    // memory_kib at 9, iterations at 13, lanes at 17.
+    for (at, value) in [(9, 64 * 1024 + 1), (9, u32::MAX), (13, 17), (13, 0), (17, 0)] {
+        let mut data = sealed();
+        set_word(&mut data, at, value);
+        assert_eq!(seal::open(&data, "correct horse").unwrap_err(), SealError::BadParams, "{} = {}", at, value);
+    }
+    let too_costly = KdfParams { memory_kib: 1 << 20, ..FAST };
+    assert_eq!(seal::seal(TEXT, "p", too_costly, [0; SALT_LEN], [0; NONCE_LEN]).unwrap_err(), SealError::BadParams);
+}
+
+#[test]
+fn sealed_backups() {
+    let mut from = app();
+    from.create_event(Date::new(2026, 3, 4), None, "Standup", Priority::High, None).unwrap();
+    from.create_task("Report", Priority::Low).unwrap();
+    let data = seal::seal(from.export_backup().as_bytes(), "pass", FAST, [1; SALT_LEN], [2; NONCE_LEN]).unwrap();
+
+    let mut to = app();
+    assert_eq!(
+        to.import_backup_sealed(&data, "wrong", ImportMode::Merge).unwrap_err(),
+        BackupError::Sealed(SealError::WrongPassphrase)
+    );
+    let mut damaged = data.clone();
+    damaged[HEADER_LEN] ^= 0x01;
+    assert_eq!(
+        to.import_backup_sealed(&damaged, "pass", ImportMode::Merge).unwrap_err(),
+        BackupError::Sealed(SealError::Tampered)
+    );
+    assert!(to.events.is_empty() && to.tasks.is_empty());
+
+    let report = to.import_backup_sealed(&data, "pass", ImportMode::Merge).unwrap();
+    assert_eq!((report.events_added, report.tasks_added), (1, 1));
+    assert_eq!(to.events[0].uid, from.events[0].uid);
+}
+
+/// The app seals with the default costs; one run of those is enough.
+#[test]
+fn app_seals_with_default_costs() {
+    let mut from = app();
+    from.create_task("Report", Priority::Low).unwrap();
+    let data = from.export_backup_sealed("pass", [3; SALT_LEN], [4; NONCE_LEN]).unwrap();
+    let word = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let defaults = KdfParams::default();
+    assert_eq!((word(9), word(13), word(17)), (defaults.memory_kib, defaults.iterations, defaults.lanes));
+    assert_eq!(seal::open(&data, "pass").unwrap(), from.export_backup().as_bytes());
+}
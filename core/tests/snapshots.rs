@@ -0,0 +1,129 @@
+//! Snapshots in `Storage` and on the Snapshots screen.
+
+mod common;
+
+use common::{add_event, day};
+use planner_core::app::{AppState, PlannerApp, KEY_MENU};
+use planner_core::backend::MemBackend;
+use planner_core::planner::{Event, Task};
+use planner_core::storage::{SnapshotReason, Storage, SNAPSHOT_KEEP};
+
+/// One event and one task, next ID 3.
+fn stored() -> MemBackend {
+    let mut st = Storage::with_backend(MemBackend::new());
+    st.save_all(&[&Event::new(1, day(), "a".into())], &[Task::new(2, "t".into())], 3).unwrap();
+    st.backend().clone()
+}
+
+fn app() -> PlannerApp<MemBackend> {
+    common::app_with(stored())
+}
+
+#[test]
+fn storage_keeps_the_newest() {
+    let mut st = Storage::with_backend(stored());
+    let ev = Event::new(1, day(), "a".into());
+    let first = st.take_snapshot(day(), SnapshotReason::Manual, &[&ev], &[], 3, &[]).unwrap();
+    assert_eq!(first, "snapshot.2026-03-04.000");
+    let second = st.take_snapshot(day(), SnapshotReason::Daily, &[], &[], 3, &[]).unwrap();
+    assert_eq!(second, "snapshot.2026-03-04.001");
+
+    let list = st.snapshots().unwrap();
+    assert_eq!(list.iter().map(|s| s.key.as_str()).collect::<Vec<_>>(), [second.as_str(), first.as_str()]);
+    assert_eq!((list[1].events, list[1].tasks, &list[1].reason), (1, 0, &SnapshotReason::Manual));
+    assert_eq!(st.load_snapshot(&first).unwrap().events[0].title, "a");
+
+    for n in 0..SNAPSHOT_KEEP {
+        st.take_snapshot(day().add_days(n as i32 + 1), SnapshotReason::Manual, &[], &[], 3, &[]).unwrap();
+    }
+    let list = st.snapshots().unwrap();
+    assert_eq!(list.len(), SNAPSHOT_KEEP);
+    assert!(list.iter().all(|s| s.taken > day()), "the oldest are pruned first");
+    assert_eq!(st.latest_snapshot_date().unwrap(), Some(day().add_days(SNAPSHOT_KEEP as i32)));
+
+    st.delete_snapshot(&list[0].key).unwrap();
+    assert_eq!(st.snapshots().unwrap().len(), SNAPSHOT_KEEP - 1);
+}
+
+#[test]
+fn unreadable_snapshots_are_skipped() {
+    let mut st = Storage::with_backend(stored());
+    st.take_snapshot(day(), SnapshotReason::Manual, &[], &[], 3, &[]).unwrap();
+    st.backend().map.insert("snapshot.2026-03-05.000".into(), b"{bad".to_vec());
+    assert_eq!(st.snapshots().unwrap().len(), 1);
+}
+
+#[test]
+fn daily_snapshot_once_per_day() {
+    let mut app = app();
+    let mem = app.storage().unwrap().backend().clone();
+    assert_eq!(mem.map.keys().filter(|k| k.starts_with("snapshot.")).count(), 1);
+
+    app.reload_storage();
+    app.handle_key('s');
+    assert_eq!(app.snapshots.len(), 1);
+    assert_eq!(app.snapshots[0].reason, SnapshotReason::Daily);
+    assert_eq!((app.snapshots[0].events, app.snapshots[0].tasks), (1, 1));
+
+    let mut tomorrow = PlannerApp::new(day().next_day());
+    tomorrow.attach_storage(Storage::with_backend(mem));
+    tomorrow.handle_key('s');
+    assert_eq!(tomorrow.snapshots.len(), 2);
+}
+
+#[test]
+fn nothing_to_snapshot_when_empty() {
+    let mut app: PlannerApp<MemBackend> = PlannerApp::new(day());
+    app.attach_storage(Storage::with_backend(MemBackend::new()));
+    assert!(!app.storage().unwrap().backend().map.keys().any(|k| k.starts_with("snapshot.")));
+}
+
+#[test]
+fn restore_and_undo() {
+    let mut app = app();
+    add_event(&mut app, "hi", None);
+    assert_eq!(app.events.len(), 2);
+    let next_id = app.next_id;
+
+    app.handle_key('s');
+    assert_eq!(app.state, AppState::Snapshots);
+    app.handle_key('r');
+    assert!(app.snapshot_confirm_restore);
+    assert_eq!(app.events.len(), 2, "restoring needs a second press");
+    app.handle_key('r');
+    assert_eq!(app.events.len(), 1, "{:?}", app.snapshot_status);
+    assert_eq!(app.next_id, next_id, "IDs handed out since aren't reused");
+    assert_eq!(app.snapshots.len(), 2);
+    assert_eq!(app.snapshots[0].reason, SnapshotReason::Before("restore".into()));
+    assert_eq!(app.snapshots[0].events, 2);
+
+    // The restore is what's stored now.
+    let mut reopened: PlannerApp<MemBackend> = PlannerApp::new(day());
+    reopened.attach_storage(Storage::with_backend(app.storage().unwrap().backend().clone()));
+    assert_eq!(reopened.events.len(), 1);
+
+    // And the snapshot taken before it undoes it.
+    app.handle_key('r');
+    app.handle_key('r');
+    assert_eq!(app.events.len(), 2);
+}
+
+#[test]
+fn manual_snapshots_and_delete() {
+    let mut app = app();
+    app.handle_key('s');
+    for _ in 0..SNAPSHOT_KEEP + 2 {
+        app.handle_key('n');
+    }
+    assert_eq!(app.snapshots.len(), SNAPSHOT_KEEP);
+    assert!(app.snapshots.iter().all(|s| s.reason == SnapshotReason::Manual));
+
+    app.handle_key('d');
+    assert_eq!(app.state, AppState::ConfirmDel);
+    app.handle_key('y');
+    assert_eq!(app.state, AppState::Snapshots);
+    assert_eq!(app.snapshots.len(), SNAPSHOT_KEEP - 1);
+
+    app.handle_key(KEY_MENU);
+    assert_eq!(app.state, AppState::DayView);
+}
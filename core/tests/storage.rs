@@ -0,0 +1,92 @@
+//! `Storage` round trips over the host backends.
+
+use planner_core::backend::{Backend, DirBackend, MemBackend};
+use planner_core::planner::{Date, Event, Priority, Task, Time};
+use planner_core::storage::Storage;
+
+fn events() -> Vec<Event> {
+    let mut standup = Event::new(3, Date::new(2026, 3, 4), "Standup".into());
+    standup.time = Some(Time::new(9, 30));
+    standup.priority = Priority::High;
+    let holiday = Event::new(7, Date::new(2026, 12, 25), "Holiday, all day".into());
+    vec![standup, holiday]
+}
+
+fn tasks() -> Vec<Task> {
+    let mut rent = Task::new(4, "Pay rent".into());
+    rent.done = true;
+    rent.priority = Priority::Low;
+    vec![rent, Task::new(5, "Call \"Bob\"".into())]
+}
+
+fn event_fields(e: &Event) -> (u32, Date, Option<Time>, String, Priority) {
+    (e.id, e.date, e.time, e.title.clone(), e.priority)
+}
+
+fn task_fields(t: &Task) -> (u32, String, bool, Priority) {
+    (t.id, t.title.clone(), t.done, t.priority)
+}
+
+fn save<B: Backend>(st: &mut Storage<B>) {
+    let events = events();
+    st.save_all(&events.iter().collect::<Vec<_>>(), &tasks(), 8).unwrap();
+    st.flush().unwrap();
+}
+
+fn check_loaded<B: Backend>(st: &mut Storage<B>) {
+    let mut loaded = st.load_events().unwrap();
+    loaded.sort_by_key(|e| e.id);
+    assert_eq!(
+        loaded.iter().map(event_fields).collect::<Vec<_>>(),
+        events().iter().map(event_fields).collect::<Vec<_>>()
+    );
+    let mut loaded = st.load_tasks().unwrap();
+    loaded.sort_by_key(|t| t.id);
+    assert_eq!(loaded.iter().map(task_fields).collect::<Vec<_>>(), tasks().iter().map(task_fields).collect::<Vec<_>>());
+    assert_eq!(st.load_next_id().unwrap(), 8);
+}
+
+#[test]
+fn mem_round_trip() {
+    let mut st = Storage::with_backend(MemBackend::new());
+    save(&mut st);
+    let mut reopened = Storage::with_backend(st.backend().clone());
+    check_loaded(&mut reopened);
+}
+
+#[test]
+fn dir_round_trip() {
+    let root = std::env::temp_dir().join(format!("planner-core-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    save(&mut Storage::with_backend(DirBackend::new(&root).unwrap()));
+    check_loaded(&mut Storage::with_backend(DirBackend::new(&root).unwrap()));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn single_record_edits() {
+    let mut st = Storage::with_backend(MemBackend::new());
+    save(&mut st);
+    let mut moved = events().remove(0);
+    moved.date = Date::new(2026, 3, 5);
+    st.save_event(&moved).unwrap();
+    st.delete_event(7).unwrap();
+    st.delete_task(4).unwrap();
+    st.save_next_id(9).unwrap();
+
+    let mut reopened = Storage::with_backend(st.backend().clone());
+    let loaded = reopened.load_events().unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].date, Date::new(2026, 3, 5));
+    let loaded = reopened.load_tasks().unwrap();
+    assert_eq!(loaded.iter().map(|t| t.id).collect::<Vec<_>>(), [5]);
+    assert_eq!(reopened.load_next_id().unwrap(), 9);
+}
+
+#[test]
+fn empty_backend_loads_empty() {
+    let mut st = Storage::with_backend(MemBackend::new());
+    assert!(st.load_events().unwrap().is_empty());
+    assert!(st.load_tasks().unwrap().is_empty());
+    assert!(st.load_queries().unwrap().is_empty());
+}
@@ -0,0 +1,115 @@
+//! todo.txt lines: parsing, and writing them back unchanged.
+
+use planner_core::planner::{Date, Priority, Task, Uid};
+use planner_core::todotxt::{self, TodoItem};
+
+const UID: &str = "0f8fad5b-d9cb-469f-a165-70867728950e";
+
+fn parse(line: &str) -> TodoItem {
+    todotxt::parse_line(line).unwrap()
+}
+
+#[test]
+fn lines_round_trip() {
+    for line in [
+        "Call at 3:30 +work @phone",
+        "Read ch:2 of the book",
+        "(A) 2026-03-01 Buy milk @shop due:2026-03-05",
+        "(D) Someday, maybe",
+        "(B) 2026-03-01 Marked normal",
+        "x 2026-03-04 2026-03-01 Call Bob +work @phone pri:A",
+        "x Done, no dates",
+        "Visit https://example.com/a:b and mailto:me",
+        "+project @context only",
+        &format!("x 2026-03-04 Pay rent pri:C uid:{}", UID),
+    ] {
+        assert_eq!(todotxt::to_line(&parse(line)), line);
+    }
+}
+
+#[test]
+fn colons_in_titles() {
+    let item = parse("Call at 3:30 about 10:00:00 meeting");
+    assert_eq!(item.task.title, "Call at 3:30 about 10:00:00 meeting");
+    assert!(item.extras().is_empty());
+
+    let item = parse("ch:2 https://a.b due:2026-03-05 x-y:z :odd empty:");
+    assert_eq!(item.task.title, "ch:2 https://a.b due:2026-03-05 x-y:z :odd empty:");
+    assert_eq!(item.extras(), [("ch", "2"), ("due", "2026-03-05")]);
+}
+
+#[test]
+fn fields() {
+    let item = parse("x 2026-03-04 2026-03-01 Call Bob +work @phone +home pri:A");
+    assert!(item.task.done);
+    assert_eq!((item.task.priority, item.letter), (Priority::High, Some('A')));
+    assert_eq!((item.completed, item.created), (Some(Date::new(2026, 3, 4)), Some(Date::new(2026, 3, 1))));
+    assert_eq!(item.task.title, "Call Bob +work @phone +home");
+    assert_eq!(item.projects(), ["work", "home"]);
+    assert_eq!(item.contexts(), ["phone"]);
+
+    let item = parse(&format!("(C) 2026-03-01 Water plants uid:{}", UID));
+    assert!(!item.task.done);
+    assert_eq!((item.task.priority, item.completed, item.created), (Priority::Low, None, Some(Date::new(2026, 3, 1))));
+    assert_eq!(item.task.uid, Uid::parse(UID).unwrap());
+    assert_eq!(item.task.title, "Water plants");
+
+    // Only done tasks take their priority from `pri:`, and a bad uid is
+    // just a word.
+    let item = parse("Open task pri:A uid:nope");
+    assert_eq!((item.task.priority, item.letter), (Priority::Normal, None));
+    assert!(item.task.uid.is_nil());
+    assert_eq!(item.task.title, "Open task pri:A uid:nope");
+
+    assert_eq!(parse("(Z) far off").task.priority, Priority::Low);
+    assert_eq!(parse("(a) not a priority").task.title, "(a) not a priority");
+    assert!(todotxt::parse_line("   ").is_none());
+}
+
+#[test]
+fn titles_are_checked() {
+    // Dates, priority and uid don't count towards the title's length.
+    let line = format!("(A) 2026-03-01 {} uid:{}", "t".repeat(50), UID);
+    let item = parse(&line);
+    assert!(!item.shortened);
+    assert_eq!(todotxt::to_line(&item), line);
+
+    let item =
+        parse(&format!("x 2026-03-04 Plan the offsite with the whole team +work @office due:2026-04-01 uid:{}", UID));
+    assert!(item.shortened);
+    assert_eq!(item.task.title, "Plan the offsite with the whole team +work @office");
+    assert_eq!(item.task.uid, Uid::parse(UID).unwrap());
+
+    assert_eq!(parse("Tab\tseparated").task.title, "Tab separated");
+    assert!(todotxt::parse_line("x 2026-03-04").is_none(), "no title");
+    assert!(todotxt::parse_line(&format!("(A) uid:{}", UID)).is_none());
+}
+
+#[test]
+fn changed_tasks() {
+    let mut item = parse("(D) 2026-03-01 Someday");
+    item.task.priority = Priority::High;
+    assert_eq!(todotxt::to_line(&item), "(A) 2026-03-01 Someday");
+
+    let mut item = parse("(D) Someday");
+    item.task.done = true;
+    assert_eq!(todotxt::to_line(&item), "x Someday pri:D");
+
+    let mut item = parse("(B) Plain");
+    item.task.priority = Priority::Low;
+    assert_eq!(todotxt::to_line(&item), "(C) Plain");
+
+    let mut task = Task::new(4, "New +work".into());
+    assert_eq!(todotxt::to_line(&TodoItem::from_task(&task)), "New +work");
+    task.priority = Priority::High;
+    task.uid = Uid::parse(UID).unwrap();
+    assert_eq!(todotxt::to_line(&TodoItem::from_task(&task)), format!("(A) New +work uid:{}", UID));
+}
+
+#[test]
+fn whole_files() {
+    let text = "(A) First\n\n  \nx Second\nThird at 9:15\n";
+    let items = todotxt::parse(text);
+    assert_eq!(items.iter().map(|i| i.task.title.as_str()).collect::<Vec<_>>(), ["First", "Second", "Third at 9:15"]);
+    assert_eq!(todotxt::serialize(&items), "(A) First\nx Second\nThird at 9:15\n");
+}
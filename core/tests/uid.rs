@@ -0,0 +1,123 @@
+//! Uids: the text form, derived uids, the generator, and the app giving
+//! uids to items stored before they existed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use planner_core::app::PlannerApp;
+use planner_core::backend::MemBackend;
+use planner_core::planner::{Date, Uid, UidGen};
+use planner_core::storage::Storage;
+use serde_json::Value;
+
+const TEXT: &str = "0f8fad5b-d9cb-469f-a165-70867728950e";
+
+/// The high nibble of byte 6, the UUID version.
+fn version(uid: Uid) -> u8 {
+    uid.0[6] >> 4
+}
+
+/// Byte 8 must start with binary 10, the RFC 4122 variant.
+fn rfc_variant(uid: Uid) -> bool {
+    uid.0[8] & 0xc0 == 0x80
+}
+
+#[test]
+fn parse_and_display() {
+    let uid = Uid::parse(TEXT).unwrap();
+    assert_eq!(uid.0[..4], [0x0f, 0x8f, 0xad, 0x5b]);
+    assert_eq!(uid.to_string(), TEXT);
+    assert_eq!(Uid::parse(&TEXT.to_uppercase()), Some(uid));
+    assert_eq!(Uid::parse(&format!("  {}\n", TEXT)), Some(uid));
+    assert_eq!(Uid::NIL.to_string(), "00000000-0000-0000-0000-000000000000");
+    assert!(Uid::parse(&Uid::NIL.to_string()).unwrap().is_nil());
+
+    for bad in [
+        "",
+        "0f8fad5bd9cb469fa16570867728950e",
+        "0f8fad5b-d9cb-469f-a165-70867728950",
+        "0f8fad5b-d9cb-469f-a165-70867728950e0",
+        "0f8fad5bd-9cb-469f-a165-70867728950e",
+        "0f8fad5b-d9cb-469f-a165-7086772895-e",
+        "0f8fad5g-d9cb-469f-a165-70867728950e",
+        "+f8fad5b-d9cb-469f-a165-70867728950e",
+        "{f8fad5b-d9cb-469f-a165-70867728950e}",
+    ] {
+        assert_eq!(Uid::parse(bad), None, "{:?}", bad);
+    }
+
+    assert_eq!(serde_json::to_string(&uid).unwrap(), format!("\"{}\"", TEXT));
+    assert_eq!(serde_json::from_str::<Uid>(&format!("\"{}\"", TEXT)).unwrap(), uid);
+    assert!(serde_json::from_str::<Uid>("\"not a uid\"").is_err());
+}
+
+#[test]
+fn from_name() {
+    // FNV-1a's offset basis, with the version and variant bits set.
+    assert_eq!(Uid::from_name("").to_string(), "6c62272e-07bb-8142-a2b8-21756295c58d");
+    let uid = Uid::from_name("event-1@example.com");
+    assert_eq!(uid, Uid::from_name("event-1@example.com"));
+    assert_ne!(uid, Uid::from_name("event-2@example.com"));
+    assert_ne!(uid, Uid::from_name("Event-1@example.com"));
+    assert_eq!(version(uid), 8);
+    assert!(rfc_variant(uid));
+}
+
+#[test]
+fn generated_uids_are_unique() {
+    let mut uids = UidGen::new(*b"sixteen byte key");
+    let made: Vec<Uid> = (0..10_000).map(|_| uids.generate()).collect();
+    assert_eq!(made.iter().collect::<BTreeSet<_>>().len(), made.len());
+    assert!(made.iter().all(|&uid| version(uid) == 4 && rfc_variant(uid) && !uid.is_nil()));
+
+    // The same seed gives the same run, another seed a different one.
+    let mut again = UidGen::new(*b"sixteen byte key");
+    assert_eq!(again.generate(), made[0]);
+    assert_ne!(UidGen::new(*b"sixteen byte kez").generate(), made[0]);
+
+    // An all-zero seed still works.
+    let mut zero = UidGen::new([0; 16]);
+    let (a, b) = (zero.generate(), zero.generate());
+    assert!(a != b && !a.is_nil() && !b.is_nil());
+}
+
+fn fixture(version: u32) -> MemBackend {
+    let path = format!("{}/tests/fixtures/schema_v{}.json", env!("CARGO_MANIFEST_DIR"), version);
+    let keys: BTreeMap<String, Value> = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let map = keys
+        .into_iter()
+        .map(|(key, value)| {
+            let blob = match value {
+                Value::String(s) => s.into_bytes(),
+                v => serde_json::to_vec(&v).unwrap(),
+            };
+            (key, blob)
+        })
+        .collect();
+    MemBackend { map }
+}
+
+fn open(backend: MemBackend, seed: u8) -> PlannerApp<MemBackend> {
+    let mut app = PlannerApp::new(Date::new(2026, 3, 4));
+    app.seed_uids([seed; 16]);
+    app.attach_storage(Storage::with_backend(backend));
+    app
+}
+
+fn uids(app: &PlannerApp<MemBackend>) -> Vec<Uid> {
+    let mut events: Vec<_> = app.events.iter().map(|e| (e.id, e.uid)).collect();
+    events.sort();
+    events.into_iter().map(|(_, uid)| uid).chain(app.tasks.iter().map(|t| t.uid)).collect()
+}
+
+#[test]
+fn old_items_get_uids_once() {
+    let mut app = open(fixture(2), 1);
+    let given = uids(&app);
+    assert_eq!(given.len(), 3);
+    assert!(given.iter().all(|&uid| version(uid) == 4 && !uid.is_nil()));
+    assert_eq!(given.iter().collect::<BTreeSet<_>>().len(), 3);
+
+    // They were saved, so a differently seeded app reads the same ones.
+    let reopened = open(app.storage().unwrap().backend().clone(), 2);
+    assert_eq!(uids(&reopened), given);
+}
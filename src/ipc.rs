@@ -0,0 +1,162 @@
+//! The planner's side of the public IPC API (the `planner-api` crate).
+//!
+//! `PlannerApp` (through the `Api` wrapper, as both it and the API traits
+//! live in other crates) answers queries from its in-memory state; only events
+//! outside secret calendars are handed over (see `shareable_events`).
+//! Edits go through the same `PlannerApp` methods as the UI's, which check
+//! them and queue them for saving. Changes from either are collected by
+//! the app and sent to subscribers as a `ChangeNotice`.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use planner_api::{
+    ApiError, Change, ChangeNotice, EventInfo, EventUpdate, Item, NewEvent, NewTask, PlannerData, PlannerEdits,
+    TaskCounts, TaskUpdate,
+};
+
+use planner_core::app::{ChangeKind, Changes, EditError, EventChanges, PlannerApp, TaskChanges};
+use planner_core::backend::Backend;
+use planner_core::planner::{Date, Event, Priority, Time, Uid};
+
+fn api_date(d: Date) -> planner_api::Date {
+    planner_api::Date { year: d.year, month: d.month, day: d.day }
+}
+
+fn api_time(t: Time) -> planner_api::Time {
+    planner_api::Time { hour: t.hour, minute: t.minute }
+}
+
+fn api_priority(p: Priority) -> planner_api::Priority {
+    match p {
+        Priority::Low => planner_api::Priority::Low,
+        Priority::Normal => planner_api::Priority::Normal,
+        Priority::High => planner_api::Priority::High,
+    }
+}
+
+// Taken as sent, without clamping, so bad values are refused rather
+// than quietly changed.
+fn from_api_date(d: planner_api::Date) -> Date {
+    Date { year: d.year, month: d.month, day: d.day }
+}
+
+fn from_api_time(t: planner_api::Time) -> Time {
+    Time { hour: t.hour, minute: t.minute }
+}
+
+fn from_api_priority(p: planner_api::Priority) -> Priority {
+    match p {
+        planner_api::Priority::Low => Priority::Low,
+        planner_api::Priority::Normal => Priority::Normal,
+        planner_api::Priority::High => Priority::High,
+    }
+}
+
+fn api_error(e: EditError) -> ApiError {
+    match e {
+        EditError::BadTitle => ApiError::BadTitle,
+        EditError::BadDate => ApiError::BadDate,
+        EditError::BadTime => ApiError::BadTime,
+        EditError::NoSuchCalendar => ApiError::NoSuchCalendar,
+        EditError::NotFound => ApiError::NotFound,
+        EditError::Unavailable => ApiError::Unavailable,
+    }
+}
+
+fn api_change_kind(kind: ChangeKind) -> planner_api::ChangeKind {
+    match kind {
+        ChangeKind::Added => planner_api::ChangeKind::Added,
+        ChangeKind::Changed => planner_api::ChangeKind::Changed,
+        ChangeKind::Removed => planner_api::ChangeKind::Removed,
+    }
+}
+
+/// The notice telling subscribers about `changes`; `None` if nothing
+/// changed.
+pub fn change_notice(changes: Changes) -> Option<ChangeNotice> {
+    if changes.is_empty() {
+        return None;
+    }
+    let events = changes.events.into_iter().map(|(uid, kind)| (Item::Event, kind, uid));
+    let tasks = changes.tasks.into_iter().map(|(uid, kind)| (Item::Task, kind, uid));
+    let list: Vec<Change> = events
+        .chain(tasks)
+        .map(|(item, kind, uid)| Change { item, kind: api_change_kind(kind), uid: uid.0 })
+        .collect();
+    Some(ChangeNotice::new(list, changes.reset))
+}
+
+pub fn event_info(ev: &Event) -> EventInfo {
+    EventInfo {
+        uid: ev.uid.0,
+        date: api_date(ev.date),
+        time: ev.time.map(api_time),
+        title: ev.title.clone(),
+        priority: api_priority(ev.priority),
+        calendar: ev.calendar,
+    }
+}
+
+/// The app as the API server sees it.
+pub struct Api<'a, B: Backend>(pub &'a mut PlannerApp<B>);
+
+impl<B: Backend> PlannerData for Api<'_, B> {
+    fn events(&self) -> Vec<EventInfo> {
+        self.0.shareable_events().map(event_info).collect()
+    }
+
+    fn task_counts(&self) -> TaskCounts {
+        let open = self.0.tasks.iter().filter(|t| !t.done);
+        TaskCounts {
+            open: open.clone().count() as u32,
+            high: open.filter(|t| t.priority == Priority::High).count() as u32,
+        }
+    }
+}
+
+impl<B: Backend> PlannerEdits for Api<'_, B> {
+    fn create_event(&mut self, event: NewEvent) -> Result<[u8; 16], ApiError> {
+        PlannerApp::create_event(
+            self.0,
+            from_api_date(event.date),
+            event.time.map(from_api_time),
+            &event.title,
+            from_api_priority(event.priority),
+            event.calendar,
+        )
+        .map(|uid| uid.0)
+        .map_err(api_error)
+    }
+
+    fn update_event(&mut self, update: EventUpdate) -> Result<[u8; 16], ApiError> {
+        let changes = EventChanges {
+            date: update.date.map(from_api_date),
+            time: update.time.map(|t| t.map(from_api_time)),
+            title: update.title,
+            priority: update.priority.map(from_api_priority),
+            calendar: update.calendar,
+        };
+        PlannerApp::update_event(self.0, Uid(update.uid), changes).map_err(api_error)?;
+        Ok(update.uid)
+    }
+
+    fn create_task(&mut self, task: NewTask) -> Result<[u8; 16], ApiError> {
+        PlannerApp::create_task(self.0, &task.title, from_api_priority(task.priority)).map(|uid| uid.0).map_err(api_error)
+    }
+
+    fn update_task(&mut self, update: TaskUpdate) -> Result<[u8; 16], ApiError> {
+        let changes = TaskChanges {
+            title: update.title,
+            priority: update.priority.map(from_api_priority),
+            done: update.done,
+        };
+        PlannerApp::update_task(self.0, Uid(update.uid), changes).map_err(api_error)?;
+        Ok(update.uid)
+    }
+
+    fn complete_task(&mut self, uid: [u8; 16]) -> Result<[u8; 16], ApiError> {
+        PlannerApp::complete_task(self.0, Uid(uid)).map_err(api_error)?;
+        Ok(uid)
+    }
+}
@@ -7,24 +7,36 @@
 #![cfg_attr(target_os = "none", no_main)]
 
 
-mod app;
-mod planner;
-mod storage;
+mod ipc;
+mod store;
 mod ui;
 
-use app::PlannerApp;
-use planner::Date;
+use planner_core::app::PlannerApp;
+use planner_core::planner::Date;
+use store::PddbBackend;
 use num_traits::FromPrimitive;
 use num_traits::ToPrimitive;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use planner_api::SERVER_NAME;
 
-const SERVER_NAME: &str = "_Day Planner_";
 const APP_NAME: &str = "Day Planner";
 
+/// Save once edits have paused this long...
+const SAVE_DEBOUNCE_MS: u64 = 2_000;
+/// ...but never hold them longer than this while typing continues.
+const SAVE_MAX_DELAY_MS: u64 = 10_000;
+
+/// Internal messages. Opcodes for other apps are in `planner_api::Opcode`
+/// and must not overlap these.
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 enum AppOp {
     Redraw = 0,
     Rawkeys = 1,
     FocusChange = 2,
+    BasisChange = 3,
+    SaveTimer = 4,
     Quit = 255,
 }
 
@@ -67,13 +79,46 @@ fn main() -> ! {
     let epoch_ms = tt.elapsed_ms();
     let initial_date = epoch_to_date(epoch_ms);
 
-    let mut app = PlannerApp::new(initial_date);
-    app.init_storage();
+    let mut app: PlannerApp<PddbBackend> = PlannerApp::new(initial_date);
+    let trng = trng::Trng::new(&xns).expect("can't connect to TRNG");
+    let mut seed = [0u8; 16];
+    seed[..8].copy_from_slice(&trng.get_u64().expect("TRNG failed").to_le_bytes());
+    seed[8..].copy_from_slice(&trng.get_u64().expect("TRNG failed").to_le_bytes());
+    app.seed_uids(seed);
+    store::init_storage(&mut app);
+    spawn_basis_monitor(xous::connect(sid).expect("can't connect to self"));
+    let save_timer = spawn_save_timer(xous::connect(sid).expect("can't connect to self"));
     let mut allow_redraw = true;
+    let mut subscribers = planner_api::Subscribers::new(planner_api::XousTransport);
     ui::draw(&app, &gam, content);
 
     loop {
-        let msg = xous::receive_message(sid).unwrap();
+        // Whatever the last message changed, tell subscribers now.
+        let changes = app.take_changes();
+        if !subscribers.is_empty() {
+            if let Some(notice) = ipc::change_notice(changes) {
+                subscribers.notify(&notice);
+            }
+        }
+        let mut msg = xous::receive_message(sid).unwrap();
+        // Queries from other apps; see `ipc.rs`.
+        if planner_api::serve(&mut msg, &ipc::Api(&mut app)) {
+            continue;
+        }
+        if subscribers.serve(&mut msg) {
+            continue;
+        }
+        // Edits from other apps, saved and shown as if typed.
+        if planner_api::serve_edits(&mut msg, &mut ipc::Api(&mut app)) {
+            if app.save_pending() {
+                save_timer.send(()).ok();
+            }
+            if app.needs_redraw && allow_redraw {
+                ui::draw(&app, &gam, content);
+                app.needs_redraw = false;
+            }
+            continue;
+        }
         match FromPrimitive::from_usize(msg.body.id()) {
             Some(AppOp::Redraw) => {
                 if allow_redraw {
@@ -98,6 +143,9 @@ fn main() -> ! {
                     }
                 }
                 if should_quit { break; }
+                if app.save_pending() {
+                    save_timer.send(()).ok();
+                }
                 if app.needs_redraw && allow_redraw {
                     ui::draw(&app, &gam, content);
                     app.needs_redraw = false;
@@ -107,7 +155,7 @@ fn main() -> ! {
                 match gam::FocusState::convert_focus_change(state_code) {
                     gam::FocusState::Background => {
                         allow_redraw = false;
-                        app.save_state();
+                        app.flush();
                     }
                     gam::FocusState::Foreground => {
                         allow_redraw = true;
@@ -115,17 +163,73 @@ fn main() -> ! {
                     }
                 }
             }),
+            Some(AppOp::BasisChange) => {
+                store::refresh_bases(&mut app);
+                if app.save_pending() {
+                    save_timer.send(()).ok();
+                }
+                if app.needs_redraw && allow_redraw {
+                    ui::draw(&app, &gam, content);
+                    app.needs_redraw = false;
+                }
+            }
+            Some(AppOp::SaveTimer) => app.save_dirty(),
             Some(AppOp::Quit) => break,
             _ => log::warn!("unknown opcode: {:?}", msg.body.id()),
         }
     }
 
-    app.save_state();
+    app.flush();
     xns.unregister_server(sid).unwrap();
     xous::destroy_server(sid).unwrap();
     xous::terminate_process(0)
 }
 
+/// Wait for secret bases to be unlocked or locked and tell the main loop,
+/// which re-reads the basis list itself.
+fn spawn_basis_monitor(cid: xous::CID) {
+    std::thread::spawn(move || {
+        let pddb = pddb::Pddb::new();
+        loop {
+            pddb.monitor_basis();
+            xous::send_message(
+                cid,
+                xous::Message::new_scalar(AppOp::BasisChange.to_usize().unwrap(), 0, 0, 0, 0),
+            )
+            .ok();
+        }
+    });
+}
+
+/// Debounce save requests: once edits go quiet for `SAVE_DEBOUNCE_MS` (or
+/// `SAVE_MAX_DELAY_MS` after the first), ask the main loop to write them.
+fn spawn_save_timer(cid: xous::CID) -> mpsc::Sender<()> {
+    let (tx, rx) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        let quiet = Duration::from_millis(SAVE_DEBOUNCE_MS);
+        let cap = Duration::from_millis(SAVE_MAX_DELAY_MS);
+        // Ends when the main loop drops the sender.
+        while rx.recv().is_ok() {
+            let first = Instant::now();
+            loop {
+                let wait = quiet.min(cap.saturating_sub(first.elapsed()));
+                match rx.recv_timeout(wait) {
+                    Ok(()) if first.elapsed() < cap => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    _ => break,
+                }
+            }
+            while rx.try_recv().is_ok() {}
+            xous::send_message(
+                cid,
+                xous::Message::new_scalar(AppOp::SaveTimer.to_usize().unwrap(), 0, 0, 0, 0),
+            )
+            .ok();
+        }
+    });
+    tx
+}
+
 /// Convert milliseconds-since-boot to a rough date.
 /// Xous ticktimer gives ms since boot, not epoch. For a real RTC
 /// we'd use the RTC service. This provides a reasonable default
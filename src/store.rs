@@ -0,0 +1,163 @@
+//! PDDB side of Day Planner storage.
+//!
+//! `PddbBackend` pins one dictionary to one basis; `planner-core` does the
+//! rest (see its `storage.rs` for the dictionaries and keys). The helpers
+//! below open the planner's stores and import inbox, and keep secret-basis
+//! calendars in step with the bases PDDB has unlocked.
+
+use planner_core::app::PlannerApp;
+use planner_core::backend::{Backend, BackendError};
+use planner_core::calendar::{self, DEFAULT_CALENDAR};
+use planner_core::storage::{Storage, BASIS_DICT, DICT, INBOX_DICT, SYSTEM_BASIS};
+
+/// One PDDB dictionary as a key-value backend.
+pub struct PddbBackend {
+    pddb: pddb::Pddb,
+    dict: String,
+    basis: String,
+}
+
+impl PddbBackend {
+    /// A dictionary in the system basis.
+    pub fn new(dict: &str) -> Self {
+        Self::in_basis(dict, SYSTEM_BASIS)
+    }
+
+    /// A dictionary pinned to `basis`; reads and writes never touch any
+    /// other basis.
+    pub fn in_basis(dict: &str, basis: &str) -> Self {
+        let pddb = pddb::Pddb::new();
+        pddb.is_mounted_blocking();
+        Self {
+            pddb,
+            dict: String::from(dict),
+            basis: String::from(basis),
+        }
+    }
+
+    /// Currently unlocked bases, system basis included.
+    pub fn list_basis(&self) -> Vec<String> {
+        self.pddb.list_basis()
+    }
+}
+
+impl Backend for PddbBackend {
+    fn read(&mut self, key: &str) -> Result<Option<Vec<u8>>, BackendError> {
+        let mut handle = match self.pddb.get(
+            &self.dict,
+            key,
+            Some(&self.basis),
+            false,
+            false,
+            None,
+            None::<fn()>,
+        ) {
+            Ok(h) => h,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(_) => return Err(BackendError::Io),
+        };
+        let mut buf = Vec::new();
+        use std::io::Read;
+        handle.read_to_end(&mut buf).map_err(|_| BackendError::Io)?;
+        if buf.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(buf))
+        }
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) -> Result<(), BackendError> {
+        let mut handle = self
+            .pddb
+            .get(
+                &self.dict,
+                key,
+                Some(&self.basis),
+                true,
+                true,
+                Some(data.len()),
+                None::<fn()>,
+            )
+            .map_err(|_| BackendError::Io)?;
+        use std::io::{Seek, Write};
+        handle
+            .seek(std::io::SeekFrom::Start(0))
+            .map_err(|_| BackendError::Io)?;
+        handle.write_all(data).map_err(|_| BackendError::Io)?;
+        handle
+            .set_len(data.len() as u64)
+            .map_err(|_| BackendError::Io)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), BackendError> {
+        match self.pddb.delete_key(&self.dict, key, Some(&self.basis)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(BackendError::Io),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, BackendError> {
+        match self.pddb.list_keys(&self.dict, Some(&self.basis)) {
+            Ok(keys) => Ok(keys),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(_) => Err(BackendError::Io),
+        }
+    }
+
+    fn sync(&mut self) -> Result<(), BackendError> {
+        self.pddb.sync().map_err(|_| BackendError::Io)
+    }
+}
+
+/// Storage over the planner's main dictionary. Opening can't fail: PDDB
+/// errors turn up on the first read or write, which `load_all` reports.
+pub fn open_main() -> Storage<PddbBackend> {
+    open(DICT)
+}
+
+/// Storage over another dictionary, e.g. a calendar's.
+pub fn open(dict: &str) -> Storage<PddbBackend> {
+    Storage::with_backend(PddbBackend::new(dict))
+}
+
+/// Storage over a dictionary inside a secret basis.
+pub fn open_in(dict: &str, basis: &str) -> Storage<PddbBackend> {
+    Storage::with_backend(PddbBackend::in_basis(dict, basis))
+}
+
+pub fn init_storage(app: &mut PlannerApp<PddbBackend>) {
+    let mut st = open_main();
+    // Only needed to find the calendar dictionaries; load_all reports
+    // an unreadable registry properly.
+    let calendars = st
+        .load_calendars()
+        .unwrap_or_else(|_| calendar::default_calendars());
+    for cal in calendars.iter().filter(|c| c.id != DEFAULT_CALENDAR) {
+        app.attach_calendar_storage(cal.id, open(&cal.dict));
+    }
+    app.attach_inbox(PddbBackend::new(INBOX_DICT));
+    app.attach_storage(st);
+    refresh_bases(app);
+}
+
+/// Match basis calendars to the bases PDDB has unlocked right now.
+/// Called at startup and whenever PDDB reports a basis change.
+pub fn refresh_bases(app: &mut PlannerApp<PddbBackend>) {
+    let Some(st) = app.storage() else { return };
+    let unlocked: Vec<String> = st
+        .backend()
+        .list_basis()
+        .into_iter()
+        .filter(|b| b != SYSTEM_BASIS)
+        .collect();
+    for basis in app.attached_bases() {
+        if !unlocked.contains(&basis) {
+            app.detach_basis(&basis);
+        }
+    }
+    let attached = app.attached_bases();
+    for basis in unlocked.iter().filter(|b| !attached.contains(b)) {
+        app.attach_basis_storage(basis, open_in(BASIS_DICT, basis));
+    }
+}
@@ -6,13 +6,17 @@
 extern crate alloc;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use gam::*;
 use graphics_server::api::GlyphStyle;
 use graphics_server::{DrawStyle, PixelColor, Point, Rectangle, TextBounds};
 
-use crate::app::*;
-use crate::planner::*;
+use planner_core::app::*;
+use planner_core::backend::Backend;
+use planner_core::dedup::{Choice, Class, Entry, Reviewable};
+use planner_core::planner::*;
+use planner_core::query::Hit;
 
 const SCREEN_W: i16 = 336;
 const HEADER_H: i16 = 30;
@@ -91,7 +95,7 @@ fn draw_text_inverted(gam: &Gam, canvas: Canvas, x: i16, y: i16, w: i16, text: &
     ).ok();
 }
 
-pub fn draw(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+pub fn draw<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     gam.draw_rectangle(
         canvas,
         Rectangle::new(Point::new(0, 0), Point::new(SCREEN_W - 1, 535))
@@ -105,12 +109,19 @@ pub fn draw(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
         AppState::AddTask => draw_add_task(app, gam, canvas),
         AppState::ConfirmDel => draw_confirm(app, gam, canvas),
         AppState::MonthView => draw_month_view(app, gam, canvas),
+        AppState::QueryList => draw_query_list(app, gam, canvas),
+        AppState::QueryResults => draw_query_results(app, gam, canvas),
+        AppState::AddQuery => draw_add_query(app, gam, canvas),
+        AppState::Recovery => draw_recovery(app, gam, canvas),
+        AppState::Snapshots => draw_snapshots(app, gam, canvas),
+        AppState::Calendars => draw_calendars(app, gam, canvas),
+        AppState::ImportReview => draw_import_review(app, gam, canvas),
     }
 
     gam.redraw().ok();
 }
 
-fn draw_day_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_day_view<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     let header = format!(
         "{} {} {} {}",
         app.current_date.weekday_name(),
@@ -134,7 +145,8 @@ fn draw_day_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     } else {
         for (i, ev) in events.iter().enumerate() {
             let prefix = format!(
-                "{} {} {}",
+                "{}{} {} {}",
+                app.calendar_marker(ev.calendar),
                 ev.priority.marker(),
                 ev.time_display(),
                 ev.title
@@ -155,11 +167,11 @@ fn draw_day_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     draw_footer(
         gam,
         canvas,
-        "<>/> Day  A)dd  E)dit  D)el  T)asks  M)onth  Menu=Quit",
+        "<>/> Day  A)dd  E)dit  D)el  T)asks  M)onth  F)ilters  S)naps  C)als  I)mport  Menu=Quit",
     );
 }
 
-fn draw_task_list(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_task_list<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     let done_count = app.tasks.iter().filter(|t| t.done).count();
     let header = format!(
         "Tasks ({}/{})",
@@ -197,7 +209,7 @@ fn draw_task_list(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     );
 }
 
-fn draw_event_form(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_event_form<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     let title = if app.state == AppState::AddEvent {
         "Add Event"
     } else {
@@ -267,6 +279,22 @@ fn draw_event_form(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     } else {
         draw_text(gam, canvas, 12, y, &pri_label, GlyphStyle::Regular);
     }
+    y += LINE_H + 8;
+
+    // Calendar
+    let cal_sel = app.form_field == EventField::Calendar;
+    let cal_name = app
+        .calendar(app.form_calendar)
+        .map(|c| c.name.as_str())
+        .unwrap_or("?");
+    let cal_label = format!("Calendar: {}  (</>  cycle)", cal_name);
+    draw_text(gam, canvas, 8, y, "Calendar:", GlyphStyle::Small);
+    y += 16;
+    if cal_sel {
+        draw_text_inverted(gam, canvas, 8, y, SCREEN_W - 16, &cal_label);
+    } else {
+        draw_text(gam, canvas, 12, y, &cal_label, GlyphStyle::Regular);
+    }
 
     draw_footer(
         gam,
@@ -275,7 +303,7 @@ fn draw_event_form(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     );
 }
 
-fn draw_add_task(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_add_task<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     draw_header(gam, canvas, "Add Task");
 
     let y = HEADER_H + 20;
@@ -291,12 +319,12 @@ fn draw_add_task(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     draw_footer(gam, canvas, "Enter=Save  Menu=Cancel");
 }
 
-fn draw_confirm(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_confirm<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     draw_header(gam, canvas, "Confirm Delete");
 
     let y = HEADER_H + 40;
     let msg = match app.delete_target {
-        Some(DeleteTarget::Event(id)) => {
+        Some(DeleteTarget::Event(id, _)) => {
             let name = app
                 .events
                 .iter()
@@ -314,6 +342,18 @@ fn draw_confirm(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
                 .unwrap_or("?");
             format!("Delete task '{}'?", name)
         }
+        Some(DeleteTarget::Query(idx)) => {
+            let name = app
+                .queries
+                .get(idx)
+                .map(|q| q.name.as_str())
+                .unwrap_or("?");
+            format!("Delete filter '{}'?", name)
+        }
+        Some(DeleteTarget::Snapshot(idx)) => match app.snapshots.get(idx) {
+            Some(snap) => format!("Delete snapshot {} ({})?", snap.taken.display(), snap.reason),
+            None => String::from("Delete snapshot?"),
+        },
         None => String::from("Nothing selected"),
     };
     draw_text(gam, canvas, 8, y, &msg, GlyphStyle::Regular);
@@ -324,7 +364,7 @@ fn draw_confirm(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
     draw_footer(gam, canvas, "Y)es  Any=Cancel");
 }
 
-fn draw_month_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
+fn draw_month_view<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
     let header = format!(
         "{} {} — [/] Month",
         Date::month_name(app.month_view_month),
@@ -358,11 +398,11 @@ fn draw_month_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
 
         let is_cursor = day == app.month_cursor_day;
         let is_today = Date::new(app.month_view_year, app.month_view_month, day) == app.current_date;
-        let has_events = app.event_count_for(Date::new(
+        let markers = app.calendar_markers_for(Date::new(
             app.month_view_year,
             app.month_view_month,
             day,
-        )) > 0;
+        ));
 
         if is_cursor {
             draw_text_inverted(gam, canvas, x + 2, row_y, col_w - 4, &label);
@@ -380,17 +420,9 @@ fn draw_month_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
             draw_text(gam, canvas, x + 4, row_y + 2, &label, GlyphStyle::Regular);
         }
 
-        // Event dot
-        if has_events {
-            let dot_x = x + col_w / 2;
-            let dot_y = row_y + cell_h - 6;
-            let dot = Rectangle::new(
-                Point::new(dot_x - 1, dot_y - 1),
-                Point::new(dot_x + 1, dot_y + 1),
-            );
-            gam.draw_rectangle(canvas, dot.style(
-                DrawStyle::new(PixelColor::Dark, PixelColor::Dark, 0),
-            )).ok();
+        // One marker per calendar with events that day
+        if !markers.is_empty() {
+            draw_text(gam, canvas, x + col_w / 2 + 2, row_y + 4, &markers, GlyphStyle::Small);
         }
 
         col += 1;
@@ -406,3 +438,317 @@ fn draw_month_view(app: &PlannerApp, gam: &Gam, canvas: Canvas) {
         "Arrows=Navigate  [/]=Month  Enter=Select",
     );
 }
+
+fn draw_query_list<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    draw_header(gam, canvas, &format!("Filters ({})", app.queries.len()));
+
+    let mut y = HEADER_H + 4;
+
+    if app.queries.is_empty() {
+        draw_text(gam, canvas, 8, y, "No saved filters", GlyphStyle::Regular);
+        y += LINE_H + 4;
+        draw_text(gam, canvas, 8, y, "Press A to add one, e.g.", GlyphStyle::Small);
+        y += LINE_H;
+        draw_text(gam, canvas, 8, y, "priority:high due:<7d !done", GlyphStyle::Small);
+    } else {
+        for (i, q) in app.queries.iter().enumerate() {
+            if i == app.query_cursor {
+                draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, &q.name);
+            } else {
+                draw_text(gam, canvas, 8, y, &q.name, GlyphStyle::Regular);
+            }
+            y += LINE_H;
+            draw_text(gam, canvas, 16, y, &q.text, GlyphStyle::Small);
+            y += LINE_H;
+            if y > 536 - FOOTER_H - LINE_H * 2 {
+                break;
+            }
+        }
+    }
+
+    draw_footer(
+        gam,
+        canvas,
+        "Enter=Results  A)dd  D)el  <=Back",
+    );
+}
+
+fn draw_query_results<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    let name = app
+        .queries
+        .get(app.query_cursor)
+        .map(|q| q.name.as_str())
+        .unwrap_or("?");
+    let mut y = HEADER_H + 4;
+
+    match app.query_hits() {
+        Err(e) => {
+            draw_header(gam, canvas, name);
+            draw_text(gam, canvas, 8, y, &format!("Query error: {}", e), GlyphStyle::Regular);
+        }
+        Ok(hits) => {
+            draw_header(gam, canvas, &format!("{} ({})", name, hits.len()));
+            if hits.is_empty() {
+                draw_text(gam, canvas, 8, y, "Nothing matches", GlyphStyle::Regular);
+            }
+            // Keep the cursor on screen
+            let per_page = ((536 - FOOTER_H - HEADER_H - 4) / (LINE_H + 2)) as usize;
+            let first = app.query_result_cursor.saturating_sub(per_page.saturating_sub(1));
+            for (i, hit) in hits.iter().enumerate().skip(first) {
+                let line = match hit {
+                    Hit::Event(ev) => format!(
+                        "{} {} {} {}",
+                        ev.priority.marker(),
+                        ev.date.short_display(),
+                        ev.time_display(),
+                        ev.title
+                    ),
+                    Hit::Task(t) => format!(
+                        "{} {} {}",
+                        if t.done { "[x]" } else { "[ ]" },
+                        t.priority.marker(),
+                        t.title
+                    ),
+                };
+                if i == app.query_result_cursor {
+                    draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, &line);
+                } else {
+                    draw_text(gam, canvas, 8, y, &line, GlyphStyle::Regular);
+                }
+                y += LINE_H + 2;
+                if y > 536 - FOOTER_H - LINE_H {
+                    break;
+                }
+            }
+        }
+    }
+
+    draw_footer(
+        gam,
+        canvas,
+        "Enter=Go to day / Toggle task  <=Back",
+    );
+}
+
+fn draw_add_query<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    draw_header(gam, canvas, "Add Filter");
+
+    let mut y = HEADER_H + 8;
+    let fields = [
+        ("Name:", &app.query_name_input, QueryField::Name),
+        ("Query:", &app.query_text_input, QueryField::Text),
+    ];
+    for (label, value, field) in fields.iter() {
+        draw_text(gam, canvas, 8, y, label, GlyphStyle::Small);
+        y += 16;
+        if app.query_field == *field {
+            draw_text_inverted(gam, canvas, 8, y, SCREEN_W - 16, &format!("{}_", value));
+        } else {
+            draw_text(gam, canvas, 12, y, value, GlyphStyle::Regular);
+        }
+        y += LINE_H + 8;
+    }
+
+    if let Some(ref err) = app.query_error {
+        draw_text(gam, canvas, 8, y, err, GlyphStyle::Regular);
+        y += LINE_H + 8;
+    }
+
+    draw_text(gam, canvas, 8, y, "priority:high  due:<7d  tag:work", GlyphStyle::Small);
+    y += LINE_H;
+    draw_text(gam, canvas, 8, y, "done  !done  is:task  \"some text\"", GlyphStyle::Small);
+
+    draw_footer(gam, canvas, "Up/Down=Field  Enter=Save  Menu=Cancel");
+}
+
+fn draw_recovery<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    draw_header(gam, canvas, "Data Recovery");
+
+    let mut y = HEADER_H + 12;
+    let problem = match app.storage_error {
+        Some(ref e) => format!("{}", e),
+        None => String::from("Unknown problem"),
+    };
+    draw_text(gam, canvas, 8, y, "Saved planner data couldn't be read:", GlyphStyle::Small);
+    y += LINE_H;
+    draw_text(gam, canvas, 8, y, &problem, GlyphStyle::Regular);
+    y += LINE_H + 8;
+    draw_text(gam, canvas, 8, y, "Nothing will be saved until this is", GlyphStyle::Small);
+    y += LINE_H - 4;
+    draw_text(gam, canvas, 8, y, "resolved. A copy of the unreadable", GlyphStyle::Small);
+    y += LINE_H - 4;
+    draw_text(gam, canvas, 8, y, "data is kept under a recovery key.", GlyphStyle::Small);
+    y += LINE_H + 12;
+
+    draw_text(gam, canvas, 8, y, "R = Retry loading", GlyphStyle::Regular);
+    y += LINE_H + 2;
+    draw_text(gam, canvas, 8, y, "X = Export raw bytes to log", GlyphStyle::Regular);
+    y += LINE_H + 2;
+    if app.recovery_confirm_fresh {
+        draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, "Press F again to start fresh");
+    } else {
+        draw_text(gam, canvas, 8, y, "F = Start fresh", GlyphStyle::Regular);
+    }
+    y += LINE_H + 12;
+
+    if let Some(ref status) = app.recovery_status {
+        draw_text(gam, canvas, 8, y, status, GlyphStyle::Small);
+    }
+
+    draw_footer(gam, canvas, "R)etry  eX)port  F)resh  Menu=Quit");
+}
+
+fn draw_snapshots<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    draw_header(gam, canvas, &format!("Snapshots ({})", app.snapshots.len()));
+
+    let mut y = HEADER_H + 4;
+
+    if app.snapshots.is_empty() {
+        draw_text(gam, canvas, 8, y, "No snapshots yet", GlyphStyle::Regular);
+        y += LINE_H + 4;
+        draw_text(gam, canvas, 8, y, "Press N to take one now", GlyphStyle::Small);
+        y += LINE_H;
+    } else {
+        // Two lines per snapshot; keep the cursor on screen
+        let per_page = ((536 - FOOTER_H - HEADER_H - LINE_H * 3) / (LINE_H * 2)) as usize;
+        let first = app.snapshot_cursor.saturating_sub(per_page.saturating_sub(1));
+        for (i, snap) in app.snapshots.iter().enumerate().skip(first).take(per_page) {
+            let line = format!("{} {}", snap.taken.display(), snap.reason);
+            if i == app.snapshot_cursor {
+                draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, &line);
+            } else {
+                draw_text(gam, canvas, 8, y, &line, GlyphStyle::Regular);
+            }
+            y += LINE_H;
+            let counts = format!("{} events, {} tasks", snap.events, snap.tasks);
+            draw_text(gam, canvas, 16, y, &counts, GlyphStyle::Small);
+            y += LINE_H;
+        }
+    }
+
+    y += 4;
+    if app.snapshot_confirm_restore {
+        draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, "Press R again to restore");
+    } else if let Some(ref status) = app.snapshot_status {
+        draw_text(gam, canvas, 8, y, status, GlyphStyle::Small);
+    }
+
+    draw_footer(gam, canvas, "R)estore  N)ew  D)el  <=Back");
+}
+
+fn draw_calendars<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    draw_header(gam, canvas, "Calendars");
+
+    let mut y = HEADER_H + 4;
+    for (i, cal) in app.calendars.iter().enumerate() {
+        let count = app.events.iter().filter(|e| e.calendar == cal.id).count();
+        let line = format!(
+            "{} {} {} ({})",
+            if cal.visible { "[x]" } else { "[ ]" },
+            cal.marker,
+            cal.name,
+            count
+        );
+        if i == app.calendar_cursor {
+            draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, &line);
+        } else {
+            draw_text(gam, canvas, 8, y, &line, GlyphStyle::Regular);
+        }
+        y += LINE_H + 2;
+    }
+
+    draw_footer(gam, canvas, "Enter=Show/Hide  <=Back");
+}
+
+fn draw_import_review<B: Backend>(app: &PlannerApp<B>, gam: &Gam, canvas: Canvas) {
+    let event_count = app.event_review.entries.len();
+    if let Some(field) = app.review_field {
+        let cursor = app.review_cursor;
+        if cursor < event_count {
+            draw_review_fields(gam, canvas, &app.event_review.entries[cursor], field);
+        } else if let Some(entry) = app.task_review.entries.get(cursor - event_count) {
+            draw_review_fields(gam, canvas, entry, field);
+        }
+        return;
+    }
+
+    draw_header(gam, canvas, "Import Review");
+
+    let mut y = HEADER_H + 4;
+    if let Some(ref status) = app.review_status {
+        draw_text(gam, canvas, 8, y, status, GlyphStyle::Small);
+        y += LINE_H;
+    }
+    let mut lines: Vec<String> = app
+        .event_review
+        .entries
+        .iter()
+        .map(|e| review_line(e, &format!("{} {} {}", e.item.date.short_display(), e.item.time_display(), e.item.title)))
+        .collect();
+    lines.extend(app.task_review.entries.iter().map(|e| review_line(e, &e.item.title)));
+
+    if lines.is_empty() {
+        draw_text(gam, canvas, 8, y, "Nothing to import", GlyphStyle::Regular);
+        if app.review_file.is_some() {
+            y += LINE_H + 4;
+            draw_text(gam, canvas, 8, y, "Enter removes it from the inbox", GlyphStyle::Small);
+        }
+    } else {
+        let per_page = ((536 - FOOTER_H - HEADER_H - LINE_H * 4) / LINE_H) as usize;
+        let first = app.review_cursor.saturating_sub(per_page.saturating_sub(1));
+        for (i, line) in lines.iter().enumerate().skip(first).take(per_page) {
+            if i == app.review_cursor {
+                draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, line);
+            } else {
+                draw_text(gam, canvas, 8, y, line, GlyphStyle::Regular);
+            }
+            y += LINE_H;
+        }
+    }
+
+    y += 4;
+    draw_text(gam, canvas, 8, y, "+new =same !differs *repeat", GlyphStyle::Small);
+
+    draw_footer(gam, canvas, "A)ccept S)kip M)erge Enter=Import");
+}
+
+/// `! [merge] 03/04 9:00AM Stand-up`
+fn review_line<T: Reviewable>(entry: &Entry<T>, text: &str) -> String {
+    let mark = match entry.class {
+        Class::New => '+',
+        Class::Identical(_) => '=',
+        Class::Conflicting(..) => '!',
+        Class::Repeated(_) => '*',
+    };
+    let choice = match (&entry.class, &entry.choice) {
+        (Class::Identical(_), _) | (_, Choice::Skip) => "skip",
+        (Class::Conflicting(..), Choice::Accept) => "take",
+        (_, Choice::Accept) => "add",
+        (_, Choice::Merge(_)) => "merge",
+    };
+    format!("{} [{}] {}", mark, choice, text)
+}
+
+/// Each differing field of a conflicting entry, ours and theirs, with the
+/// one that will be kept marked.
+fn draw_review_fields<T: Reviewable>(gam: &Gam, canvas: Canvas, entry: &Entry<T>, cursor: usize) {
+    draw_header(gam, canvas, "Merge Fields");
+
+    let mut y = HEADER_H + 4;
+    for (i, &field) in entry.fields().iter().enumerate() {
+        let label = format!("{}: keep {}", field.label(), if entry.takes(field) { "imported" } else { "mine" });
+        if i == cursor {
+            draw_text_inverted(gam, canvas, 4, y, SCREEN_W - 8, &label);
+        } else {
+            draw_text(gam, canvas, 8, y, &label, GlyphStyle::Regular);
+        }
+        y += LINE_H;
+        let mine = entry.existing.as_ref().map(|e| e.show(field)).unwrap_or_default();
+        draw_text(gam, canvas, 16, y, &format!("mine: {}", mine), GlyphStyle::Small);
+        y += LINE_H - 4;
+        draw_text(gam, canvas, 16, y, &format!("imported: {}", entry.item.show(field)), GlyphStyle::Small);
+        y += LINE_H + 2;
+    }
+
+    draw_footer(gam, canvas, "Enter=Switch  <=Done");
+}